@@ -0,0 +1,158 @@
+use std::f32::consts::FRAC_PI_4;
+use std::fs::File;
+use std::io::BufReader;
+
+use rodio::source::{Buffered, ChannelVolume};
+use rodio::{Decoder, OutputStream, OutputStreamHandle, Source};
+
+use crate::consts::{
+    AUDIO_MAX_RANGE, FOOTSTEP_OTHER_VOLUME, FOOTSTEP_OWN_VOLUME, SOUND_DEATH_PATH,
+    SOUND_FOOTSTEP_PATH, SOUND_HIT_PATH, SOUND_SHOT_PATH,
+};
+use crate::map::World;
+use crate::player::Player;
+use crate::utils::sound_occlusion;
+
+/// A decoded clip that can be played any number of times, also overlapping itself
+type Clip = Buffered<Decoder<BufReader<File>>>;
+
+/// The client's sound effects. Without an output device or with a clip missing, those sounds are
+/// skipped and the game carries on silently.
+pub struct AudioManager {
+    // dropping the stream stops all sound, so it lives as long as the manager
+    _stream: Option<OutputStream>,
+    handle: Option<OutputStreamHandle>,
+    shot: Option<Clip>,
+    hit: Option<Clip>,
+    death: Option<Clip>,
+    footstep: Option<Clip>,
+}
+
+impl Default for AudioManager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl AudioManager {
+    pub fn new() -> Self {
+        let (stream, handle) = match OutputStream::try_default() {
+            Ok((stream, handle)) => (Some(stream), Some(handle)),
+            Err(e) => {
+                eprintln!("No audio output, playing without sound: {}", e);
+                (None, None)
+            }
+        };
+        AudioManager {
+            _stream: stream,
+            handle,
+            shot: load_clip(SOUND_SHOT_PATH),
+            hit: load_clip(SOUND_HIT_PATH),
+            death: load_clip(SOUND_DEATH_PATH),
+            footstep: load_clip(SOUND_FOOTSTEP_PATH),
+        }
+    }
+
+    pub fn play_shot(&self) {
+        self.play(&self.shot);
+    }
+
+    /// Plays someone else's gunshot as the listener hears it
+    pub fn play_shot_from(&self, world: &World, listener: &Player, source: (f32, f32)) {
+        self.play_from(&self.shot, 1.0, world, listener, source);
+    }
+
+    /// The player's own footstep, quiet and in the middle
+    pub fn play_footstep(&self) {
+        if let (Some(handle), Some(clip)) = (&self.handle, &self.footstep)
+            && let Err(e) =
+                handle.play_raw(clip.clone().convert_samples().amplify(FOOTSTEP_OWN_VOLUME))
+        {
+            eprintln!("Couldn't play a sound: {}", e);
+        }
+    }
+
+    /// Someone else's footstep as the listener hears it
+    pub fn play_footstep_from(&self, world: &World, listener: &Player, source: (f32, f32)) {
+        self.play_from(
+            &self.footstep,
+            FOOTSTEP_OTHER_VOLUME,
+            world,
+            listener,
+            source,
+        );
+    }
+
+    /// Plays a clip from somewhere on the map: quieter the farther away, panned towards the side it
+    /// came from and muffled by walls in between. Sounds out of earshot are skipped.
+    fn play_from(
+        &self,
+        clip: &Option<Clip>,
+        volume: f32,
+        world: &World,
+        listener: &Player,
+        source: (f32, f32),
+    ) {
+        let (Some(handle), Some(clip)) = (&self.handle, clip) else {
+            return;
+        };
+        let Some(occlusion) = sound_occlusion(world, (listener.x, listener.y), source) else {
+            return;
+        };
+
+        let dx = source.0 - listener.x;
+        let dy = source.1 - listener.y;
+        let dist_sq = dx * dx + dy * dy;
+        let volume =
+            volume * occlusion.volume * (1.0 - dist_sq / (AUDIO_MAX_RANGE * AUDIO_MAX_RANGE));
+
+        // Bearing from the listener's facing, like get_direction in the renderer. Positive is to
+        // the right on screen, and the sine folds sounds from behind onto the same side.
+        let pan = (dy.atan2(dx) - listener.angle).sin();
+        // equal power panning keeps the loudness steady as the sound moves across
+        let side = (pan + 1.0) * FRAC_PI_4;
+        let channels = vec![side.cos() * volume, side.sin() * volume];
+
+        let samples = clip.clone().convert_samples::<f32>();
+        let result = match occlusion.low_pass_hz {
+            Some(hz) => handle.play_raw(ChannelVolume::new(samples.low_pass(hz), channels)),
+            None => handle.play_raw(ChannelVolume::new(samples, channels)),
+        };
+        if let Err(e) = result {
+            eprintln!("Couldn't play a sound: {}", e);
+        }
+    }
+
+    pub fn play_hit(&self) {
+        self.play(&self.hit);
+    }
+
+    pub fn play_death(&self) {
+        self.play(&self.death);
+    }
+
+    fn play(&self, clip: &Option<Clip>) {
+        if let (Some(handle), Some(clip)) = (&self.handle, clip)
+            && let Err(e) = handle.play_raw(clip.clone().convert_samples())
+        {
+            eprintln!("Couldn't play a sound: {}", e);
+        }
+    }
+}
+
+fn load_clip(path: &str) -> Option<Clip> {
+    let file = match File::open(path) {
+        Ok(file) => file,
+        Err(e) => {
+            eprintln!("Couldn't load sound {}: {}", path, e);
+            return None;
+        }
+    };
+    match Decoder::new(BufReader::new(file)) {
+        Ok(decoder) => Some(decoder.buffered()),
+        Err(e) => {
+            eprintln!("Couldn't decode sound {}: {}", path, e);
+            None
+        }
+    }
+}
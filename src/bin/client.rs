@@ -1,29 +1,39 @@
 use anyhow::Result;
 use serde::{Deserialize, Serialize};
-use std::cmp;
-use std::collections::HashMap;
+use std::cell::Cell;
+use std::collections::{HashMap, VecDeque};
+use std::env;
+use std::f32::consts::{PI, TAU};
 use std::io::{self, Write};
 use std::net::{SocketAddr, UdpSocket};
 use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::time::{Duration, Instant};
 
+use gilrs::{Axis, Button, EventType, Gilrs};
 use pixels::{Pixels, SurfaceTexture};
 use winit::dpi::{LogicalSize, PhysicalPosition};
 use winit::event::{DeviceEvent, Event, MouseButton, WindowEvent};
 use winit::event_loop::EventLoop;
 use winit::keyboard::KeyCode;
-use winit::window::{CursorGrabMode, Window, WindowBuilder};
+use winit::platform::run_on_demand::EventLoopExtRunOnDemand;
+use winit::window::{CursorGrabMode, Fullscreen, Window, WindowBuilder};
 use winit_input_helper::WinitInputHelper;
 
 use fps::{
     AnimationState::{Dying, Walking},
-    ClientMessage, Input, ServerMessage,
-    consts::{CLOSE_MENU_ON_NEW_GAME, DIE_FRAME_TIME, HEIGHT, MOUSE_SPEED, MOUSE_SENSITIVITY_MAX, MOUSE_SENSITIVITY_MIN, PORT, SHOOT_COOLDOWN, WALK_FRAME_TIME, WIDTH},
+    Beacon, ClientMessage, Input, MatchStats, ServerMessage, Weapon, Welcome,
+    consts::{ANNOUNCEMENT_DURATION, AXIS_SENSITIVITY_MAX, AXIS_SENSITIVITY_MIN, AXIS_SENSITIVITY_STEP, CLOSE_MENU_ON_NEW_GAME, DEFAULT_AXIS_SENSITIVITY, DEFAULT_CAMERA_BOB, DEFAULT_CAMERA_BOB_AMPLITUDE, DEFAULT_CAMERA_BOB_FREQUENCY, DEFAULT_CLIENT_PREDICTION, DEFAULT_DISTANCE_FOG, DEFAULT_FIRE_SHAKE, DEFAULT_FOG_OF_WAR, DEFAULT_FOV, DEFAULT_FULLSCREEN, DEFAULT_GAMEPAD_SENSITIVITY, DEFAULT_INVERT_Y, DEFAULT_REMOTE_INTERPOLATION, DEFAULT_RENDER_SCALE, DEFAULT_RESET_EXPLORATION_ON_DEATH, DEFAULT_SHOW_BODY, DEFAULT_SHOW_TRACERS, DEFAULT_SMOOTH_MINIMAP, DEFAULT_TEXTURED_FLOOR, DEFAULT_TOGGLE_SPRINT, DIE_FRAME_COUNT, DIE_FRAME_TIME, DISCOVERY_PORT, DISCOVERY_SCAN_TIME, FOV_MAX, FOV_MIN, FOV_STEP, FREE_CAMERA_SPEED, GAMEPAD_DEAD_ZONE, GAMEPAD_MOVE_THRESHOLD, GAMEPAD_SENSITIVITY_MAX, GAMEPAD_SENSITIVITY_MIN, GAMEPAD_SENSITIVITY_STEP, HEIGHT, HIT_MARKER_DURATION, MAX_DATAGRAM_SIZE, MOTD_DURATION, MOUSE_SPEED, MOUSE_SENSITIVITY_MAX, MOUSE_SENSITIVITY_MIN, PLAYER_PITCH_LIMIT, PLAYER_SPRINT_SPEED_MULTIPLIER, PORT, PREDICTION_CORRECTION, PREDICTION_HISTORY, PREDICTION_MAX_CATCH_UP, PREDICTION_SNAP_DISTANCE, REMOTE_INTERPOLATION_MAX, REMOTE_INTERPOLATION_MIN, REMOTE_SNAP_DISTANCE, RENDER_SCALES, SHOOT_COOLDOWN, TICK_RATE, WALK_FRAME_COUNT, WALK_FRAME_TIME, WIDTH},
+    audio::AudioManager,
+    diagnostics::FrameTimes,
     gamestate::GameState,
+    keybindings::{Action, KeyBindings, key_label, pressed_key},
+    map::World,
     player::Player,
-    renderer::{MenuHover, Renderer},
-    spritesheet::hue_variations,
+    reliable::Received,
+    renderer::{Camera, MenuHover, Renderer},
     textures::TextureManager,
+    utils::{player_markers, streak_title},
 };
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -31,6 +41,31 @@ struct Config {
     last_name: Option<String>,
     recent_servers: Vec<String>,
     mouse_sensitivity: Option<f32>,
+    horizontal_sensitivity: Option<f32>,
+    vertical_sensitivity: Option<f32>,
+    gamepad_sensitivity: Option<f32>,
+    invert_y: Option<bool>,
+    toggle_sprint: Option<bool>,
+    camera_bob: Option<bool>,
+    camera_bob_amplitude: Option<f32>,
+    camera_bob_frequency: Option<f32>,
+    show_tracers: Option<bool>,
+    fire_shake: Option<bool>,
+    hit_marker_duration_ms: Option<u64>,
+    fog_of_war: Option<bool>,
+    reset_exploration_on_death: Option<bool>,
+    remote_interpolation: Option<bool>,
+    client_prediction: Option<bool>,
+    smooth_minimap: Option<bool>,
+    view_distance: Option<f32>, // tiles, unlimited when unset
+    show_body: Option<bool>,
+    fullscreen: Option<bool>,
+    render_scale: Option<f32>,
+    fov: Option<f32>, // degrees
+    textured_floor: Option<bool>,
+    distance_fog: Option<bool>,
+    #[serde(default)]
+    key_bindings: KeyBindings,
 }
 
 impl Default for Config {
@@ -39,10 +74,566 @@ impl Default for Config {
             last_name: None,
             recent_servers: Vec::new(),
             mouse_sensitivity: None,
+            horizontal_sensitivity: None,
+            vertical_sensitivity: None,
+            gamepad_sensitivity: None,
+            invert_y: None,
+            toggle_sprint: None,
+            camera_bob: None,
+            camera_bob_amplitude: None,
+            camera_bob_frequency: None,
+            show_tracers: None,
+            fire_shake: None,
+            hit_marker_duration_ms: None,
+            fog_of_war: None,
+            reset_exploration_on_death: None,
+            remote_interpolation: None,
+            client_prediction: None,
+            smooth_minimap: None,
+            view_distance: None,
+            show_body: None,
+            fullscreen: None,
+            render_scale: None,
+            fov: None,
+            textured_floor: None,
+            distance_fog: None,
+            key_bindings: KeyBindings::default(),
         }
     }
 }
 
+impl Config {
+    fn horizontal_sensitivity(&self) -> f32 {
+        self.horizontal_sensitivity
+            .unwrap_or(DEFAULT_AXIS_SENSITIVITY)
+            .clamp(AXIS_SENSITIVITY_MIN, AXIS_SENSITIVITY_MAX)
+    }
+
+    fn vertical_sensitivity(&self) -> f32 {
+        self.vertical_sensitivity
+            .unwrap_or(DEFAULT_AXIS_SENSITIVITY)
+            .clamp(AXIS_SENSITIVITY_MIN, AXIS_SENSITIVITY_MAX)
+    }
+
+    fn gamepad_sensitivity(&self) -> f32 {
+        self.gamepad_sensitivity
+            .unwrap_or(DEFAULT_GAMEPAD_SENSITIVITY)
+            .clamp(GAMEPAD_SENSITIVITY_MIN, GAMEPAD_SENSITIVITY_MAX)
+    }
+
+    fn invert_y(&self) -> bool {
+        self.invert_y.unwrap_or(DEFAULT_INVERT_Y)
+    }
+
+    fn toggle_sprint(&self) -> bool {
+        self.toggle_sprint.unwrap_or(DEFAULT_TOGGLE_SPRINT)
+    }
+
+    fn camera_bob(&self) -> bool {
+        self.camera_bob.unwrap_or(DEFAULT_CAMERA_BOB)
+    }
+
+    fn show_tracers(&self) -> bool {
+        self.show_tracers.unwrap_or(DEFAULT_SHOW_TRACERS)
+    }
+
+    fn fire_shake(&self) -> bool {
+        self.fire_shake.unwrap_or(DEFAULT_FIRE_SHAKE)
+    }
+
+    fn fullscreen(&self) -> bool {
+        self.fullscreen.unwrap_or(DEFAULT_FULLSCREEN)
+    }
+
+    /// Index into RENDER_SCALES of the configured scale, or the nearest one if edited by hand
+    fn render_scale_index(&self) -> usize {
+        let scale = self.render_scale.unwrap_or(DEFAULT_RENDER_SCALE);
+        (0..RENDER_SCALES.len())
+            .min_by(|a, b| {
+                (RENDER_SCALES[*a] - scale)
+                    .abs()
+                    .total_cmp(&(RENDER_SCALES[*b] - scale).abs())
+            })
+            .unwrap_or(RENDER_SCALES.len() - 1)
+    }
+
+    fn fov(&self) -> f32 {
+        self.fov.unwrap_or(DEFAULT_FOV).clamp(FOV_MIN, FOV_MAX)
+    }
+
+    fn textured_floor(&self) -> bool {
+        self.textured_floor.unwrap_or(DEFAULT_TEXTURED_FLOOR)
+    }
+
+    fn distance_fog(&self) -> bool {
+        self.distance_fog.unwrap_or(DEFAULT_DISTANCE_FOG)
+    }
+
+    fn render_scale(&self) -> f32 {
+        RENDER_SCALES[self.render_scale_index()]
+    }
+
+    /// Steps to the next or previous render scale, wrapping around at the ends
+    fn step_render_scale(&mut self, step: isize) {
+        let index = (self.render_scale_index() as isize + step)
+            .rem_euclid(RENDER_SCALES.len() as isize) as usize;
+        self.render_scale = Some(RENDER_SCALES[index]);
+    }
+
+    fn fog_of_war(&self) -> bool {
+        self.fog_of_war.unwrap_or(DEFAULT_FOG_OF_WAR)
+    }
+
+    fn reset_exploration_on_death(&self) -> bool {
+        self.reset_exploration_on_death
+            .unwrap_or(DEFAULT_RESET_EXPLORATION_ON_DEATH)
+    }
+
+    fn remote_interpolation(&self) -> bool {
+        self.remote_interpolation
+            .unwrap_or(DEFAULT_REMOTE_INTERPOLATION)
+    }
+
+    fn client_prediction(&self) -> bool {
+        self.client_prediction.unwrap_or(DEFAULT_CLIENT_PREDICTION)
+    }
+}
+
+/// Labels for the pause menu items, in display order
+fn menu_items(
+    mouse_sensitivity: f32,
+    config: &Config,
+    vote_target: Option<&str>,
+) -> Vec<(MenuHover, String)> {
+    let sprint_mode = if config.toggle_sprint() { "Toggle" } else { "Hold" };
+    vec![
+        (MenuHover::Quit, "Quit".to_string()),
+        (
+            MenuHover::MouseSensitivity,
+            format!("Mouse Sensitivity: {:.2}", mouse_sensitivity),
+        ),
+        (
+            MenuHover::HorizontalSensitivity,
+            format!("Horizontal: {:.1}x", config.horizontal_sensitivity()),
+        ),
+        (
+            MenuHover::VerticalSensitivity,
+            format!("Vertical: {:.1}x", config.vertical_sensitivity()),
+        ),
+        (
+            MenuHover::GamepadSensitivity,
+            format!("Controller Look: {:.1}x", config.gamepad_sensitivity()),
+        ),
+        (
+            MenuHover::InvertY,
+            format!("Invert Y: {}", if config.invert_y() { "On" } else { "Off" }),
+        ),
+        (MenuHover::SprintMode, format!("Sprint: {}", sprint_mode)),
+        (
+            MenuHover::CameraBob,
+            format!("Camera Bob: {}", if config.camera_bob() { "On" } else { "Off" }),
+        ),
+        (
+            MenuHover::Tracers,
+            format!(
+                "Tracers: {}",
+                if config.show_tracers() { "On" } else { "Off" }
+            ),
+        ),
+        (
+            MenuHover::FireShake,
+            format!(
+                "Firing Shake: {}",
+                if config.fire_shake() { "On" } else { "Off" }
+            ),
+        ),
+        (
+            MenuHover::Fov,
+            format!("Field of View: {:.0}°", config.fov()),
+        ),
+        (
+            MenuHover::RenderScale,
+            format!("Render Scale: {:.0}%", config.render_scale() * 100.0),
+        ),
+        (
+            MenuHover::TexturedFloor,
+            format!(
+                "Floor Textures: {}",
+                if config.textured_floor() { "On" } else { "Off" }
+            ),
+        ),
+        (
+            MenuHover::DistanceFog,
+            format!(
+                "Distance Fog: {}",
+                if config.distance_fog() { "On" } else { "Off" }
+            ),
+        ),
+        (MenuHover::KeyBindings, "Key Bindings".to_string()),
+        (
+            MenuHover::VoteKick,
+            format!("Vote Kick: {}", vote_target.unwrap_or("-")),
+        ),
+    ]
+}
+
+/// The key bindings screen, with the action waiting for a new key marked
+fn key_binding_items(
+    bindings: &KeyBindings,
+    rebinding: Option<Action>,
+) -> Vec<(MenuHover, String)> {
+    let mut items = vec![(MenuHover::Back, "Back".to_string())];
+    items.extend(Action::ALL.into_iter().map(|action| {
+        let key = if rebinding == Some(action) {
+            "press a key".to_string()
+        } else {
+            bindings.key(action).map_or("-".to_string(), key_label)
+        };
+        (
+            MenuHover::Binding(action),
+            format!("{}: {}", action.label(), key),
+        )
+    }));
+    items
+}
+
+/// Everyone who can be voted against, by name
+fn vote_kick_candidates(game_state: Option<&GameState>, my_name: &str) -> Vec<String> {
+    let mut names: Vec<String> = game_state
+        .map(|gs| {
+            gs.leaderboard
+                .keys()
+                .filter(|name| *name != my_name)
+                .cloned()
+                .collect()
+        })
+        .unwrap_or_default();
+    names.sort();
+    names
+}
+
+/// The living player after (step 1) or before (step -1) the one being watched, in id order.
+/// Falls back to the own view when nobody else is alive.
+fn cycle_spectating(game_state: &GameState, my_id: u64, camera: Camera, step: isize) -> Camera {
+    let mut alive: Vec<u64> = game_state
+        .players
+        .iter()
+        .filter(|(_, player)| player.health > 0)
+        .filter_map(|(id, _)| id.parse().ok())
+        .filter(|id| *id != my_id)
+        .collect();
+    if alive.is_empty() {
+        return Camera::Own;
+    }
+    alive.sort();
+
+    let current = match camera {
+        Camera::Spectating(id) => alive.iter().position(|alive_id| *alive_id == id),
+        Camera::Own => None,
+    };
+    let next = match current {
+        Some(index) => (index as isize + step).rem_euclid(alive.len() as isize) as usize,
+        // the watched player died or left, start over from one end
+        None if step > 0 => 0,
+        None => alive.len() - 1,
+    };
+    Camera::Spectating(alive[next])
+}
+
+/// Steps an axis sensitivity multiplier, wrapping around at the ends of its range
+fn step_axis_sensitivity(value: f32, step: f32) -> f32 {
+    let stepped = ((value + step) * 10.0).round() / 10.0;
+    if stepped > AXIS_SENSITIVITY_MAX {
+        AXIS_SENSITIVITY_MIN
+    } else if stepped < AXIS_SENSITIVITY_MIN {
+        AXIS_SENSITIVITY_MAX
+    } else {
+        stepped
+    }
+}
+
+/// Steps the field of view along the FOV_STEP grid, wrapping around at the ends of its range
+fn step_fov(value: f32, step: f32) -> f32 {
+    let stepped = ((value + step) / FOV_STEP).round() * FOV_STEP;
+    if stepped > FOV_MAX {
+        FOV_MIN
+    } else if stepped < FOV_MIN {
+        FOV_MAX
+    } else {
+        stepped
+    }
+}
+
+fn step_gamepad_sensitivity(value: f32, step: f32) -> f32 {
+    let stepped = ((value + step) * 10.0).round() / 10.0;
+    if stepped > GAMEPAD_SENSITIVITY_MAX {
+        GAMEPAD_SENSITIVITY_MIN
+    } else if stepped < GAMEPAD_SENSITIVITY_MIN {
+        GAMEPAD_SENSITIVITY_MAX
+    } else {
+        stepped
+    }
+}
+
+/// Controller input read this frame, merged with the keyboard and mouse when building Input
+#[derive(Default)]
+struct GamepadInput {
+    movement: (f32, f32), // (right, forward) from the left stick
+    look: (f32, f32),     // (right, up) from the right stick
+    jump: bool,
+    crouch: bool, // held, unlike the presses above
+    shoot: bool,
+}
+
+/// Reads the first connected controller. Without controller support on the system it reads
+/// nothing and the game plays with keyboard and mouse alone.
+struct GamepadSource {
+    gilrs: Option<Gilrs>,
+}
+
+impl GamepadSource {
+    fn new() -> Self {
+        let gilrs = Gilrs::new()
+            .inspect_err(|e| eprintln!("Controller support unavailable: {}", e))
+            .ok();
+        GamepadSource { gilrs }
+    }
+
+    /// Called once a frame, also while the menu is open so presses made there don't pile up and
+    /// fire once it closes
+    fn poll(&mut self) -> GamepadInput {
+        let Some(gilrs) = &mut self.gilrs else {
+            return GamepadInput::default();
+        };
+        let mut pad = GamepadInput::default();
+        // Draining the events also updates the stick values read below
+        while let Some(event) = gilrs.next_event() {
+            match event.event {
+                EventType::ButtonPressed(Button::South, _) => pad.jump = true,
+                EventType::ButtonPressed(Button::LeftTrigger2 | Button::RightTrigger2, _) => {
+                    pad.shoot = true
+                }
+                _ => {}
+            }
+        }
+        if let Some((_, gamepad)) = gilrs.gamepads().next() {
+            pad.crouch = gamepad.is_pressed(Button::East);
+            pad.movement = apply_dead_zone(
+                gamepad.value(Axis::LeftStickX),
+                gamepad.value(Axis::LeftStickY),
+            );
+            pad.look = apply_dead_zone(
+                gamepad.value(Axis::RightStickX),
+                gamepad.value(Axis::RightStickY),
+            );
+        }
+        pad
+    }
+}
+
+/// Zeroes a stick inside GAMEPAD_DEAD_ZONE and rescales the rest, so movement starts from nothing
+/// at its edge and still reaches full deflection
+fn apply_dead_zone(x: f32, y: f32) -> (f32, f32) {
+    let magnitude = x.hypot(y);
+    if magnitude <= GAMEPAD_DEAD_ZONE {
+        return (0.0, 0.0);
+    }
+    let scaled = ((magnitude - GAMEPAD_DEAD_ZONE) / (1.0 - GAMEPAD_DEAD_ZONE)).min(1.0);
+    (x / magnitude * scaled, y / magnitude * scaled)
+}
+
+/// Another player's glide between their last two updates, as (x, y, z, angle). Each update starts
+/// a new glide from wherever the player is drawn, so a late or bunched update never jumps.
+struct RemoteMotion {
+    from: (f32, f32, f32, f32),
+    to: (f32, f32, f32, f32),
+    started: Instant,
+    duration: Duration,
+}
+
+impl RemoteMotion {
+    /// Without an earlier update to glide from, the player snaps to this one
+    fn new(update: (f32, f32, f32, f32)) -> Self {
+        RemoteMotion {
+            from: update,
+            to: update,
+            started: Instant::now(),
+            duration: REMOTE_INTERPOLATION_MIN,
+        }
+    }
+
+    /// Glides to a new update over the time since the previous one arrived. Moves longer than
+    /// REMOTE_SNAP_DISTANCE (respawns, map changes) snap instead of sliding across the map.
+    fn retarget(&mut self, drawn: (f32, f32, f32, f32), update: (f32, f32, f32, f32)) {
+        let now = Instant::now();
+        let (x, y, _, _) = update;
+        self.from = if (x - drawn.0).hypot(y - drawn.1) > REMOTE_SNAP_DISTANCE {
+            update
+        } else {
+            drawn
+        };
+        self.to = update;
+        self.duration = now
+            .duration_since(self.started)
+            .clamp(REMOTE_INTERPOLATION_MIN, REMOTE_INTERPOLATION_MAX);
+        self.started = now;
+    }
+
+    /// Puts the player where the glide is now. The angle turns along the shorter arc, so crossing
+    /// ±π doesn't spin the sprite the long way round.
+    fn apply(&self, player: &mut Player) {
+        let t = (self.started.elapsed().as_secs_f32() / self.duration.as_secs_f32()).min(1.0);
+        let (x0, y0, z0, angle0) = self.from;
+        let (x1, y1, z1, angle1) = self.to;
+        player.x = x0 + (x1 - x0) * t;
+        player.y = y0 + (y1 - y0) * t;
+        player.z = z0 + (z1 - z0) * t;
+        let turn = (angle1 - angle0 + PI).rem_euclid(TAU) - PI;
+        player.angle = (angle0 + turn * t + PI).rem_euclid(TAU) - PI;
+    }
+}
+
+/// Position, height and view of my own player, as (x, y, z, angle, pitch)
+type View = (f32, f32, f32, f32, f32);
+
+fn view_of(player: &Player) -> View {
+    (player.x, player.y, player.z, player.angle, player.pitch)
+}
+
+/// Shorter way round from angle a to angle b
+fn angle_between(a: f32, b: f32) -> f32 {
+    (b - a + PI).rem_euclid(TAU) - PI
+}
+
+/// Runs my own movement ahead of the server, tick by tick through the same Player::take_input
+/// and Player::apply_gravity, so it answers input at once instead of after a round trip
+#[derive(Default)]
+struct Prediction {
+    unsimulated: f32,        // frame time not yet run as ticks, in seconds
+    history: VecDeque<View>, // view after each recent predicted tick, oldest first
+}
+
+impl Prediction {
+    /// Runs the ticks that fit in the time since the last frame. The rules for jumping come from
+    /// the server's game state.
+    fn advance(
+        &mut self,
+        me: &mut Player,
+        world: &World,
+        (max_air_jumps, bhop): (u8, bool),
+        input: &Input,
+        delta_time: f32,
+    ) {
+        if me.health == 0 {
+            self.reset();
+            return;
+        }
+        let tick = 1.0 / TICK_RATE as f32;
+        self.unsimulated =
+            (self.unsimulated + delta_time).min(tick * PREDICTION_MAX_CATCH_UP as f32);
+        while self.unsimulated >= tick {
+            self.unsimulated -= tick;
+            // only the server's count tells the client a teleport really happened
+            let teleports = me.teleports;
+            me.take_input(input, world, 1.0, max_air_jumps, bhop);
+            me.teleports = teleports;
+            me.apply_gravity(world, 1.0);
+            self.history.push_back(view_of(me));
+            if self.history.len() > PREDICTION_HISTORY {
+                self.history.pop_front();
+            }
+        }
+    }
+
+    /// Compares an update from the server with the predicted tick closest to it, which is about
+    /// a round trip old. Small differences are eased away over a few updates, so there's no
+    /// rubber-banding, and large ones snap to the server.
+    fn reconcile(&mut self, me: &mut Player, server: View) {
+        let distance = |view: &View| {
+            let (dx, dy, dz) = (server.0 - view.0, server.1 - view.1, server.2 - view.2);
+            dx * dx + dy * dy + dz * dz + angle_between(view.3, server.3).powi(2)
+        };
+        let closest = self
+            .history
+            .iter()
+            .enumerate()
+            .min_by(|(_, a), (_, b)| distance(a).total_cmp(&distance(b)))
+            .map(|(index, view)| (index, *view));
+        let Some((index, matched)) = closest else {
+            (me.x, me.y, me.z, me.angle, me.pitch) = server;
+            return;
+        };
+        if (server.0 - matched.0).hypot(server.1 - matched.1) > PREDICTION_SNAP_DISTANCE {
+            (me.x, me.y, me.z, me.angle, me.pitch) = server;
+            self.reset();
+            return;
+        }
+
+        let fix = (
+            (server.0 - matched.0) * PREDICTION_CORRECTION,
+            (server.1 - matched.1) * PREDICTION_CORRECTION,
+            (server.2 - matched.2) * PREDICTION_CORRECTION,
+            angle_between(matched.3, server.3) * PREDICTION_CORRECTION,
+            (server.4 - matched.4) * PREDICTION_CORRECTION,
+        );
+        // ticks before the match are behind the server now, the rest carry the fix so it isn't
+        // applied twice
+        self.history.drain(..index);
+        for view in self.history.iter_mut() {
+            *view = (
+                view.0 + fix.0,
+                view.1 + fix.1,
+                view.2 + fix.2,
+                view.3 + fix.3,
+                view.4 + fix.4,
+            );
+        }
+        me.x += fix.0;
+        me.y += fix.1;
+        me.z = (me.z + fix.2).max(0.0);
+        me.angle += fix.3;
+        me.pitch += fix.4;
+    }
+
+    fn reset(&mut self) {
+        self.unsimulated = 0.0;
+        self.history.clear();
+    }
+}
+
+/// Moves the debug free camera straight from the keys, through walls and without gravity.
+/// Space rises and Control sinks.
+fn fly_free_camera(
+    camera: &mut Player,
+    input: &WinitInputHelper,
+    (turn, pitch): (f32, f32),
+    delta_time: f32,
+) {
+    camera.angle += turn * camera.rot_speed;
+    camera.pitch = (camera.pitch + pitch * camera.rot_speed * 2.0)
+        .clamp(-PLAYER_PITCH_LIMIT, PLAYER_PITCH_LIMIT);
+
+    let axis = |positive: &[KeyCode], negative: &[KeyCode]| {
+        let held = |keys: &[KeyCode]| keys.iter().any(|key| input.key_held(*key));
+        held(positive) as i32 as f32 - held(negative) as i32 as f32
+    };
+    let forward = axis(
+        &[KeyCode::KeyW, KeyCode::ArrowUp],
+        &[KeyCode::KeyS, KeyCode::ArrowDown],
+    );
+    let strafe = axis(&[KeyCode::KeyD], &[KeyCode::KeyA]);
+    let vertical = axis(&[KeyCode::Space], &[KeyCode::ControlLeft]);
+
+    let mut speed = FREE_CAMERA_SPEED * delta_time;
+    if input.key_held(KeyCode::ShiftLeft) {
+        speed *= PLAYER_SPRINT_SPEED_MULTIPLIER;
+    }
+    let (sin, cos) = camera.angle.sin_cos();
+    camera.x += (cos * forward - sin * strafe) * speed;
+    camera.y += (sin * forward + cos * strafe) * speed;
+    camera.z += vertical * speed;
+}
+
 fn save_config(config: &Config) -> Result<()> {
     let config_path = "client_config.toml";
     let config_str = toml::to_string_pretty(config)?;
@@ -58,18 +649,194 @@ fn load_config() -> Config {
         .unwrap_or_default()
 }
 
-fn connect_to_server() -> Result<Option<(UdpSocket, u64, String)>> {
+/// Outcome of a single connection attempt
+enum ConnectAttempt {
+    Connected(UdpSocket, Welcome),
+    Rejected(String),
+    PasswordRequired(String), // the server wants a password, or a different one
+    NoResponse,
+}
+
+fn parse_server_address(server_address_str: &str) -> Result<SocketAddr> {
+    Ok(if server_address_str.contains(':') {
+        server_address_str.parse()?
+    } else {
+        format!("{}:{}", server_address_str, PORT).parse()?
+    })
+}
+
+/// Listens for server beacons for DISCOVERY_SCAN_TIME. Each server is listed once, by the address
+/// to join it at, with its latest beacon.
+fn scan_lan() -> Result<Vec<(String, Beacon)>> {
+    let socket = UdpSocket::bind(("0.0.0.0", DISCOVERY_PORT))?;
+    let mut found: Vec<(String, Beacon)> = Vec::new();
+    let mut buf = [0; 1024];
+    let start = Instant::now();
+    loop {
+        let left = DISCOVERY_SCAN_TIME.saturating_sub(start.elapsed());
+        if left.is_zero() {
+            return Ok(found);
+        }
+        socket.set_read_timeout(Some(left))?;
+        match socket.recv_from(&mut buf) {
+            Ok((amt, src)) => {
+                if let Ok(ServerMessage::Beacon(beacon)) = bincode::deserialize(&buf[..amt]) {
+                    let address = SocketAddr::new(src.ip(), beacon.port).to_string();
+                    match found.iter_mut().find(|(known, _)| *known == address) {
+                        Some(entry) => entry.1 = beacon,
+                        None => found.push((address, beacon)),
+                    }
+                }
+            }
+            Err(ref e)
+                if matches!(
+                    e.kind(),
+                    io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+                ) => {}
+            Err(e) => return Err(e.into()),
+        }
+    }
+}
+
+/// Sends a connect request and waits for the server's answer. Saves the server and name on success.
+fn try_connect(
+    server_address: SocketAddr,
+    username: &str,
+    password: Option<&str>,
+    config: &mut Config,
+) -> Result<ConnectAttempt> {
+    let socket = UdpSocket::bind("0.0.0.0:0")?;
+    socket.connect(server_address)?;
+    socket.set_nonblocking(true)?;
+
+    let mut buf = [0; MAX_DATAGRAM_SIZE];
+
+    // Send connect message
+    let connect_message =
+        ClientMessage::Connect(username.to_string(), password.map(str::to_string));
+    let encoded = bincode::serialize(&connect_message)?;
+    socket.send(&encoded)?;
+
+    // Wait for a response with timeout
+    let start = Instant::now();
+    let timeout = Duration::from_secs(2);
+
+    while start.elapsed() < timeout {
+        match socket.recv_from(&mut buf) {
+            Ok((amt, _)) => {
+                if let Ok(server_message) = bincode::deserialize::<ServerMessage>(&buf[..amt]) {
+                    match server_message {
+                        ServerMessage::Welcome(welcome) => {
+                            println!("Connected to server with id: {}", welcome.id);
+                            if welcome.name != username {
+                                println!(
+                                    "Username '{}' was taken, joined as '{}'",
+                                    username, welcome.name
+                                );
+                            }
+
+                            // Update and save config
+                            config.last_name = Some(welcome.name.clone());
+                            let addr_string = server_address.to_string();
+                            config.recent_servers.retain(|s| s != &addr_string);
+                            config.recent_servers.insert(0, addr_string);
+                            config.recent_servers.truncate(5);
+                            save_config(config)?;
+
+                            if let Some(server_name) = &welcome.server_name {
+                                println!("Joined {}", server_name);
+                            }
+                            return Ok(ConnectAttempt::Connected(socket, welcome));
+                        }
+                        ServerMessage::UsernameRejected(reason) => {
+                            return Ok(ConnectAttempt::Rejected(reason));
+                        }
+                        ServerMessage::PasswordRejected(reason) => {
+                            return Ok(ConnectAttempt::PasswordRequired(reason));
+                        }
+                        _ => {}
+                    }
+                }
+            }
+            Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => {
+                std::thread::sleep(Duration::from_millis(10));
+            }
+            Err(e) => return Err(e.into()),
+        }
+    }
+
+    Ok(ConnectAttempt::NoResponse)
+}
+
+/// Connects to the most recent server with the last used name, without prompting.
+/// Returns None if there is nothing to reconnect to or the attempt fails.
+fn reconnect_to_last(config: &mut Config) -> Result<Option<(UdpSocket, Welcome)>> {
+    let (Some(server), Some(username)) = (
+        config.recent_servers.first().cloned(),
+        config.last_name.clone(),
+    ) else {
+        println!("No previous server to reconnect to.");
+        return Ok(None);
+    };
+
+    println!("Reconnecting to {} as {}...", server, username);
+    // passwords aren't saved, so a protected server goes through the prompt
+    match try_connect(parse_server_address(&server)?, &username, None, config)? {
+        ConnectAttempt::Connected(socket, welcome) => Ok(Some((socket, welcome))),
+        ConnectAttempt::Rejected(reason) | ConnectAttempt::PasswordRequired(reason) => {
+            eprintln!("Connection rejected: {}", reason);
+            Ok(None)
+        }
+        ConnectAttempt::NoResponse => {
+            eprintln!("No response from server.");
+            Ok(None)
+        }
+    }
+}
+
+/// Prompts for a server and username until connected. With quick set, first tries the last
+/// server without prompting.
+fn connect_to_server(quick: bool) -> Result<Option<(UdpSocket, Welcome)>> {
     let mut config = load_config();
 
+    if quick {
+        if let Some(connection) = reconnect_to_last(&mut config)? {
+            return Ok(Some(connection));
+        }
+        println!("Falling back to server selection.");
+    }
+
+    // servers found by the last LAN scan, numbered after the recent ones
+    let mut lan_servers: Vec<(String, Beacon)> = Vec::new();
+
     loop {
         // Get server IP
         println!("Select a server or enter a new IP:");
         for (i, server) in config.recent_servers.iter().enumerate() {
             println!("{}: {}", i + 1, server);
         }
+        for (i, (address, beacon)) in lan_servers.iter().enumerate() {
+            println!(
+                "{}: {} (LAN){} - {} players on {}{}",
+                config.recent_servers.len() + i + 1,
+                address,
+                beacon
+                    .name
+                    .as_deref()
+                    .map(|name| format!(" {}", name))
+                    .unwrap_or_default(),
+                beacon.players,
+                beacon.map,
+                if beacon.password { ", password" } else { "" }
+            );
+        }
+        if let (Some(server), Some(name)) = (config.recent_servers.first(), &config.last_name) {
+            println!("r: Reconnect to last ({} as {})", server, name);
+        }
+        println!("s: Scan the LAN for servers");
         print!(
             "Enter selection (1-{}, default: 1), or new IP: ",
-            config.recent_servers.len()
+            config.recent_servers.len() + lan_servers.len()
         );
         io::stdout().flush()?;
 
@@ -77,6 +844,27 @@ fn connect_to_server() -> Result<Option<(UdpSocket, u64, String)>> {
         io::stdin().read_line(&mut selection)?;
         let selection = selection.trim();
 
+        if selection.eq_ignore_ascii_case("r") {
+            if let Some(connection) = reconnect_to_last(&mut config)? {
+                return Ok(Some(connection));
+            }
+            continue;
+        }
+
+        if selection.eq_ignore_ascii_case("s") {
+            println!("Scanning the LAN...");
+            match scan_lan() {
+                Ok(found) => {
+                    if found.is_empty() {
+                        println!("No servers found on the LAN.");
+                    }
+                    lan_servers = found;
+                }
+                Err(e) => eprintln!("Couldn't scan the LAN: {}", e),
+            }
+            continue;
+        }
+
         let server_address_str = if selection.is_empty() {
             if let Some(first) = config.recent_servers.get(0) {
                 first.clone()
@@ -87,8 +875,12 @@ fn connect_to_server() -> Result<Option<(UdpSocket, u64, String)>> {
                 server_ip.trim().to_string()
             }
         } else if let Ok(num) = selection.parse::<usize>() {
-            if num > 0 && num <= config.recent_servers.len() {
-                config.recent_servers.get(num - 1).cloned().unwrap()
+            let mut listed = config
+                .recent_servers
+                .iter()
+                .chain(lan_servers.iter().map(|(address, _)| address));
+            if let Some(address) = num.checked_sub(1).and_then(|i| listed.nth(i)) {
+                address.clone()
             } else {
                 println!("Invalid selection. Please enter a new IP:");
                 let mut server_ip = String::new();
@@ -99,17 +891,9 @@ fn connect_to_server() -> Result<Option<(UdpSocket, u64, String)>> {
             selection.to_string()
         };
 
-        let server_address: SocketAddr = if server_address_str.contains(':') {
-            server_address_str.parse()?
-        } else {
-            format!("{}:{}", server_address_str, PORT).parse()?
-        };
-
-        let socket = UdpSocket::bind("0.0.0.0:0")?;
-        socket.connect(server_address)?;
-        socket.set_nonblocking(true)?;
-
-        let mut buf = [0; 2048];
+        let server_address = parse_server_address(&server_address_str)?;
+        // asked for once the server says it needs one, kept for this server's username attempts
+        let mut password: Option<String> = None;
 
         // Inner loop for username attempts
         loop {
@@ -133,59 +917,45 @@ fn connect_to_server() -> Result<Option<(UdpSocket, u64, String)>> {
                 continue;
             }
 
-            // Send connect message
-            let connect_message = ClientMessage::Connect(final_username.clone());
-            let encoded = bincode::serialize(&connect_message)?;
-            socket.send(&encoded)?;
-
-            // Wait for a response with timeout
-            let start = Instant::now();
-            let timeout = Duration::from_secs(2);
-            let mut got_response = false;
-
-            while start.elapsed() < timeout {
-                match socket.recv_from(&mut buf) {
-                    Ok((amt, _)) => {
-                        if let Ok(server_message) =
-                            bincode::deserialize::<ServerMessage>(&buf[..amt])
-                        {
-                            match server_message {
-                                ServerMessage::Welcome(welcome) => {
-                                    println!("Connected to server with id: {}", welcome.id);
-
-                                    // Update and save config
-                                    config.last_name = Some(final_username.clone());
-                                    let addr_string = server_address.to_string();
-                                    config.recent_servers.retain(|s| s != &addr_string);
-                                    config.recent_servers.insert(0, addr_string);
-                                    config.recent_servers.truncate(5);
-                                    save_config(&config)?;
-
-                                    return Ok(Some((socket, welcome.id, final_username)));
-                                }
-                                ServerMessage::UsernameRejected(reason) => {
-                                    eprintln!("Connection rejected: {}", reason);
-                                    // prompt for a new username
-                                    got_response = true;
-                                    break;
-                                }
-                                _ => {}
-                            }
-                        }
-                    }
-                    Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => {
-                        std::thread::sleep(Duration::from_millis(10));
-                    }
-                    Err(e) => return Err(e.into()),
+            let mut attempt = try_connect(
+                server_address,
+                &final_username,
+                password.as_deref(),
+                &mut config,
+            )?;
+            while let ConnectAttempt::PasswordRequired(reason) = &attempt {
+                eprintln!("Connection rejected: {}", reason);
+                print!("Enter the server password (empty to cancel): ");
+                io::stdout().flush()?;
+                let mut password_input = String::new();
+                io::stdin().read_line(&mut password_input)?;
+                let password_trimmed = password_input.trim();
+                if password_trimmed.is_empty() {
+                    break;
                 }
+                password = Some(password_trimmed.to_string());
+                attempt = try_connect(
+                    server_address,
+                    &final_username,
+                    password.as_deref(),
+                    &mut config,
+                )?;
             }
 
-            if got_response {
-                // Username was rejected, loop again for a new username
-                continue;
-            } else {
-                eprintln!("No response from server. Check the IP and server status.");
-                break; // Breaks inner loop to re-prompt for IP
+            match attempt {
+                ConnectAttempt::Connected(socket, welcome) => {
+                    return Ok(Some((socket, welcome)));
+                }
+                ConnectAttempt::Rejected(reason) => {
+                    eprintln!("Connection rejected: {}", reason);
+                    // Username was rejected, loop again for a new username
+                    continue;
+                }
+                ConnectAttempt::NoResponse => {
+                    eprintln!("No response from server. Check the IP and server status.");
+                    break; // Breaks inner loop to re-prompt for IP
+                }
+                ConnectAttempt::PasswordRequired(_) => break, // no password given, pick another server
             }
         }
 
@@ -200,14 +970,49 @@ fn connect_to_server() -> Result<Option<(UdpSocket, u64, String)>> {
 }
 
 fn main() -> Result<()> {
-    let (socket, my_id, _username) = match connect_to_server()? {
-        Some(conn) => conn,
-        None => return Ok(()), // User chose to exit
+    let mut event_loop = EventLoop::new()?;
+
+    // --quick skips the prompt on launch and rejoins the last server
+    let mut quick = env::args().skip(1).any(|arg| arg == "--quick" || arg == "-q");
+    // --free-camera lets F5 detach the view from the player, only in debug builds
+    let free_camera = env::args().skip(1).any(|arg| arg == "--free-camera");
+    if free_camera && !cfg!(debug_assertions) {
+        println!("--free-camera is only available in debug builds, ignoring it");
+    }
+    let free_camera = free_camera && cfg!(debug_assertions);
+
+    // Loop ends when the user chooses to exit. A closed server sends the player back to the prompt.
+    while let Some((socket, welcome)) = connect_to_server(quick)? {
+        quick = false;
+        if !play(&mut event_loop, socket, welcome, free_camera)? {
+            break;
+        }
+    }
+    Ok(())
+}
+
+/// Runs the game window for one server connection. Returns true if the server shut down.
+fn play(
+    event_loop: &mut EventLoop<()>,
+    socket: UdpSocket,
+    welcome: Welcome,
+    free_camera_allowed: bool,
+) -> Result<bool> {
+    let my_id = welcome.id;
+    let my_name = welcome.name.clone();
+    // Window title, with the server's name when it has one
+    let title = match &welcome.server_name {
+        Some(server_name) => format!("Blob Hunter 3-D - {}", server_name),
+        None => "Blob Hunter 3-D".to_string(),
     };
+    let server_closed = Cell::new(false);
+    let server_closed_flag = &server_closed;
 
+    let connected = Arc::new(AtomicBool::new(true));
+    let ping_connected = connected.clone();
     let socket_clone = socket.try_clone()?;
     std::thread::spawn(move || {
-        loop {
+        while ping_connected.load(Ordering::Relaxed) {
             let ping_message = ClientMessage::Ping;
             let encoded = bincode::serialize(&ping_message).unwrap();
             if let Err(e) = socket_clone.send(&encoded) {
@@ -218,15 +1023,14 @@ fn main() -> Result<()> {
         }
     });
 
-    let event_loop = EventLoop::new()?;
     let mut input = WinitInputHelper::new();
     let window = Arc::new({
         let size = LogicalSize::new(WIDTH as f64, HEIGHT as f64);
         WindowBuilder::new()
-            .with_title("FPS Game")
+            .with_title(&title)
             .with_inner_size(size)
             .with_min_inner_size(size)
-            .build(&event_loop)?
+            .build(event_loop)?
     });
 
     // move cursor to center of window to prevent clicking elsewhere and don't allow it to move or show
@@ -240,24 +1044,48 @@ fn main() -> Result<()> {
         Pixels::new(WIDTH as u32, HEIGHT as u32, surface_texture)?
     };
 
-    // generate hue variations of the spritesheet, if they don't already exist
-    hue_variations("assets/blob0.png");
-
-    // define spritesheets
+    // spritesheets are loaded when a player using them first appears
     let mut texture_manager = TextureManager::new();
     fps::textures::load_game_textures(&mut texture_manager)?;
-    let mut spritesheets = HashMap::new();
-    for i in 0..10 {
-        spritesheets.insert(
-            format!("{i}"), // key matches a player's texture property
-            fps::spritesheet::SpriteSheet::new(&format!("assets/blob{i}.png"))?,
-        );
+    let mut renderer = Renderer::new(texture_manager, HashMap::new());
+    let audio = AudioManager::new();
+    let mut config = load_config();
+    renderer.camera_bob = config.camera_bob();
+    renderer.camera_bob_amplitude = config
+        .camera_bob_amplitude
+        .unwrap_or(DEFAULT_CAMERA_BOB_AMPLITUDE);
+    renderer.camera_bob_frequency = config
+        .camera_bob_frequency
+        .unwrap_or(DEFAULT_CAMERA_BOB_FREQUENCY);
+    renderer.show_tracers = config.show_tracers();
+    renderer.fire_shake = config.fire_shake();
+    renderer.set_render_scale(config.render_scale());
+    renderer.set_fov(config.fov());
+    renderer.textured_floor = config.textured_floor();
+    renderer.distance_fog = config.distance_fog();
+    renderer.fog_of_war = config.fog_of_war();
+    renderer.smooth_minimap = config.smooth_minimap.unwrap_or(DEFAULT_SMOOTH_MINIMAP);
+    renderer.view_distance = config.view_distance.filter(|distance| *distance > 0.0);
+    renderer.show_body = config.show_body.unwrap_or(DEFAULT_SHOW_BODY);
+    renderer.hit_marker_duration = config
+        .hit_marker_duration_ms
+        .map(Duration::from_millis)
+        .unwrap_or(HIT_MARKER_DURATION);
+    renderer.spawn_protection = welcome.spawn_protection;
+    renderer.score_to_win = welcome.score_to_win;
+    if config.fullscreen() {
+        window.set_fullscreen(Some(Fullscreen::Borderless(None)));
+    }
+    if let Some(motd) = welcome.motd {
+        renderer.show_announcement(motd, MOTD_DURATION);
     }
-    let mut renderer = Renderer::new(texture_manager, spritesheets);
     let mut game_state: Option<GameState> = None;
 
     let mut frame_count = 0;
     let mut fps_timer = Instant::now();
+    let mut frame_times = FrameTimes::new();
+    let mut last_redraw = Instant::now();
+    let mut show_frame_stats = false;
     let window_clone = window.clone();
     let mut mouse_dx = 0.0;
     let mut mouse_dy = 0.0;
@@ -266,15 +1094,30 @@ fn main() -> Result<()> {
     let mut last_frame_time = Instant::now();
     let mut last_shot_timestamp = Instant::now().checked_sub(SHOOT_COOLDOWN).unwrap_or(Instant::now());
     let mut show_menu = false;
-    let mut config = load_config();
     let mut mouse_sensitivity = config
         .mouse_sensitivity
         .unwrap_or(MOUSE_SPEED)
         .clamp(MOUSE_SENSITIVITY_MIN, MOUSE_SENSITIVITY_MAX);
     let mut cursor_pos = (0.0, 0.0);
     let mut menu_hovered_item: Option<MenuHover> = None;
+    // Menu selection, moved with the arrow keys or by pointing at an item
+    let mut menu_selected = 0;
+    let mut menu_cursor_pos = (0.0, 0.0);
+    let mut sprint_toggled = false;
+    let mut match_stats: Option<MatchStats> = None;
+    let mut match_end_dismissed = false;
+    // Index into the vote kick candidates, picked in the menu
+    let mut vote_kick_index = 0;
+    // Key bindings screen in place of the menu, and the action waiting for a new key there
+    let mut key_bindings_open = false;
+    let mut rebinding: Option<Action> = None;
+    let mut gamepad = GamepadSource::new();
+    // Other players' glides between updates, my own player is drawn as the server sends it
+    let mut remote_motion: HashMap<String, RemoteMotion> = HashMap::new();
+    let mut prediction = Prediction::default();
+    let mut received = Received::default();
 
-    Ok(event_loop.run(move |event, elwt| {
+    event_loop.run_on_demand(move |event, elwt| {
         let delta_time = last_frame_time.elapsed().as_secs_f32();
         last_frame_time = Instant::now();
 
@@ -297,12 +1140,25 @@ fn main() -> Result<()> {
                 }
                 WindowEvent::CursorMoved { position, .. } => {
                     if show_menu {
-                        //cursor_pos = (position.x as f32, position.y as f32);
-                        let scale_factor = window_clone.scale_factor();
-                        cursor_pos = (
-                            (position.x / scale_factor) as f32,
-                            (position.y / scale_factor) as f32,
-                        );
+                        // the frame is scaled to the window, so map back to frame pixels
+                        let (x, y) = pixels
+                            .window_pos_to_pixel((position.x as f32, position.y as f32))
+                            .unwrap_or_else(|pos| pixels.clamp_pixel_pos(pos));
+                        cursor_pos = (x as f32, y as f32);
+                    }
+                }
+                WindowEvent::Resized(size) => {
+                    // a minimized window has no surface to draw to
+                    if size.width > 0
+                        && size.height > 0
+                        && let Err(e) = pixels.resize_surface(size.width, size.height)
+                    {
+                        eprintln!("Error resizing surface: {}", e);
+                    }
+                    // the grabbed cursor is kept at the center, which moved with the window's size
+                    if cursor_grabbed && !show_menu {
+                        center_and_grab_cursor(window_clone.clone());
+                        first_mouse_move = true;
                     }
                 }
                 WindowEvent::Focused(is_focused) => {
@@ -314,27 +1170,53 @@ fn main() -> Result<()> {
                     if let Some(ref gs) = game_state {
                         renderer.render(gs, my_id);
                         renderer.draw_to_buffer(pixels.frame_mut());
+                        renderer.display_damage_numbers(gs, my_id, pixels.frame_mut());
                         renderer.display_health(gs, my_id, pixels.frame_mut());
+                        renderer.display_heat(gs, my_id, pixels.frame_mut());
+                        renderer.display_stamina(gs, my_id, pixels.frame_mut());
                         renderer.display_leaderboard(gs, pixels.frame_mut());
                         renderer.took_damage(pixels.frame_mut());
+                        renderer.display_teleport_flash(pixels.frame_mut());
+                        renderer.display_spawn_protection(gs, my_id, pixels.frame_mut());
+                        renderer.display_announcement(pixels.frame_mut());
+                        renderer.display_vote_kick(pixels.frame_mut());
+                        renderer.display_warmup(pixels.frame_mut());
+                        renderer.display_round_time(pixels.frame_mut());
 
-                        if !show_menu {
-                            if let Some(winner) = &gs.winner {
+                        if !show_menu && !match_end_dismissed {
+                            if let Some(stats) = &match_stats {
+                                renderer.display_match_end(stats, pixels.frame_mut());
+                            } else if let Some(winner) = &gs.winner {
                                 renderer.display_winner(&winner, pixels.frame_mut());
                             }
                         }
 
                         // Display menu if it's open
                         if show_menu {
-                            renderer.display_menu(mouse_sensitivity, pixels.frame_mut(), menu_hovered_item);
+                            let candidates = vote_kick_candidates(Some(gs), &my_name);
+                            let vote_target = candidates
+                                .get(vote_kick_index % candidates.len().max(1))
+                                .map(String::as_str);
+                            let items = if key_bindings_open {
+                                key_binding_items(&config.key_bindings, rebinding)
+                            } else {
+                                menu_items(mouse_sensitivity, &config, vote_target)
+                            };
+                            renderer.display_menu(&items, pixels.frame_mut(), menu_hovered_item);
                         }
 
+                        if show_frame_stats {
+                            renderer.display_frame_stats(&frame_times, pixels.frame_mut());
+                        }
+
+                        frame_times.push(last_redraw.elapsed());
+                        last_redraw = Instant::now();
                         frame_count += 1;
                         if fps_timer.elapsed() >= Duration::from_secs(1) {
                             let fps = frame_count;
                             frame_count = 0;
                             fps_timer = Instant::now();
-                            window_clone.set_title(&format!("Blob Hunter 3-D - {} FPS", fps));
+                            window_clone.set_title(&format!("{} - {} FPS", title, fps));
                         }
 
                         if let Err(err) = pixels.render() {
@@ -354,9 +1236,58 @@ fn main() -> Result<()> {
                 elwt.exit();
                 return;
             }
-            if input.key_pressed(KeyCode::Escape) {
+            if input.key_pressed(KeyCode::F3) {
+                show_frame_stats = !show_frame_stats;
+            }
+            if input.key_pressed(KeyCode::F11) {
+                let fullscreen = window_clone.fullscreen().is_none();
+                window_clone.set_fullscreen(fullscreen.then_some(Fullscreen::Borderless(None)));
+                config.fullscreen = Some(fullscreen);
+                if let Err(e) = save_config(&config) {
+                    eprintln!("Error saving config: {}", e);
+                }
+            }
+            if free_camera_allowed && input.key_pressed(KeyCode::F5) {
+                renderer.free_camera = match renderer.free_camera {
+                    Some(_) => None,
+                    None => game_state
+                        .as_ref()
+                        .and_then(|gs| gs.players.get(&my_id.to_string()))
+                        .cloned(),
+                };
+            }
+            // Debug dump of the tile grid with everyone's position
+            if input.key_pressed(KeyCode::F4)
+                && let Some(gs) = &game_state
+            {
+                let names: Vec<(u64, String)> = gs
+                    .players
+                    .keys()
+                    .filter_map(|id| id.parse::<u64>().ok())
+                    .map(|id| {
+                        let name = if id == my_id {
+                            "you".to_string()
+                        } else {
+                            format!("player {}", id)
+                        };
+                        (id, name)
+                    })
+                    .collect();
+                let (markers, legend) =
+                    player_markers(gs, names.iter().map(|(id, name)| (*id, name.as_str())));
+                print!("{}{}", gs.world.to_ascii(&markers), legend);
+            }
+            // Escape backs out of rebinding a key and the key bindings screen before the menu
+            if input.key_pressed(KeyCode::Escape) && rebinding.is_some() {
+                rebinding = None;
+            } else if input.key_pressed(KeyCode::Escape) && key_bindings_open {
+                key_bindings_open = false;
+                menu_selected = 0;
+            } else if input.key_pressed(KeyCode::Escape) {
                 show_menu = !show_menu;
                 if show_menu {
+                    menu_selected = 0;
+                    menu_cursor_pos = cursor_pos;
                     cursor_grabbed = false;
                     window_clone.set_cursor_visible(true);
                     window_clone.set_cursor_grab(CursorGrabMode::None).unwrap();
@@ -374,6 +1305,7 @@ fn main() -> Result<()> {
                         pitch: 0.0,
                         jump: false,
                         sprint: false,
+                        crouching: false,
                         shoot: false,
                     };
                     let encoded_input = bincode::serialize(&ClientMessage::Input(zero_input)).unwrap();
@@ -389,48 +1321,241 @@ fn main() -> Result<()> {
                 }
             }
 
-            if show_menu {
-                // Update hover state and handle menu clicks
-                let (quit_bounds, sens_bounds) = renderer.get_menu_item_bounds(mouse_sensitivity);
-                menu_hovered_item = if quit_bounds.contains(cursor_pos.0, cursor_pos.1) {
-                    Some(MenuHover::Quit)
-                } else if sens_bounds.contains(cursor_pos.0, cursor_pos.1) {
-                    Some(MenuHover::MouseSensitivity)
+            // Close the post-match stats until the next match starts
+            if match_stats.is_some() && !show_menu && input.key_pressed(KeyCode::Enter) {
+                match_end_dismissed = true;
+            }
+
+            if show_menu && let Some(action) = rebinding {
+                // The next key pressed goes to the action, taking it from any other action
+                if let Some(key) = pressed_key(&input) {
+                    rebinding = None;
+                    if let Some(unbound) = config.key_bindings.bind(action, key) {
+                        renderer.show_announcement(
+                            format!(
+                                "{} is now {}, {} has no key",
+                                key_label(key),
+                                action.label(),
+                                unbound.label()
+                            ),
+                            ANNOUNCEMENT_DURATION,
+                        );
+                    }
+                    if let Err(e) = save_config(&config) {
+                        eprintln!("Error saving config: {}", e);
+                    }
+                }
+            } else if show_menu {
+                // Update the selection from the mouse or arrow keys and handle activation
+                let candidates = vote_kick_candidates(game_state.as_ref(), &my_name);
+                let vote_target = candidates
+                    .get(vote_kick_index % candidates.len().max(1))
+                    .cloned();
+                let items = if key_bindings_open {
+                    key_binding_items(&config.key_bindings, None)
                 } else {
-                    None
+                    menu_items(mouse_sensitivity, &config, vote_target.as_deref())
+                };
+                let bounds = renderer.get_menu_item_bounds(&items);
+                let under_cursor = bounds
+                    .iter()
+                    .position(|b| b.contains(cursor_pos.0, cursor_pos.1));
+                if cursor_pos != menu_cursor_pos {
+                    menu_cursor_pos = cursor_pos;
+                    if let Some(i) = under_cursor {
+                        menu_selected = i;
+                    }
+                }
+                if input.key_pressed(KeyCode::ArrowDown) {
+                    menu_selected = (menu_selected + 1) % items.len();
+                }
+                if input.key_pressed(KeyCode::ArrowUp) {
+                    menu_selected = (menu_selected + items.len() - 1) % items.len();
+                }
+                let selected = items[menu_selected].0;
+                menu_hovered_item = Some(selected);
+
+                // Clicks act on the item under the cursor, keys on the selected one
+                let clicked = |button| {
+                    if input.mouse_pressed(button) {
+                        under_cursor.map(|i| items[i].0)
+                    } else {
+                        None
+                    }
                 };
+                let activated = clicked(MouseButton::Left).or_else(|| {
+                    (input.key_pressed(KeyCode::Enter)
+                        || (input.key_pressed(KeyCode::ArrowRight) && selected.is_adjustable()))
+                    .then_some(selected)
+                });
+                let decreased = clicked(MouseButton::Right)
+                    .or_else(|| input.key_pressed(KeyCode::ArrowLeft).then_some(selected));
 
-                let mut sensitivity_changed = false;
-                if input.mouse_pressed(MouseButton::Left) {
-                    if quit_bounds.contains(cursor_pos.0, cursor_pos.1) {
-                        elwt.exit();
-                        return;
-                    } else if sens_bounds.contains(cursor_pos.0, cursor_pos.1) {
-                        mouse_sensitivity += 0.01;
-                        if mouse_sensitivity > MOUSE_SENSITIVITY_MAX {
-                            mouse_sensitivity = MOUSE_SENSITIVITY_MIN;
-                        }
-                        sensitivity_changed = true;
+                // Left and right pick who to vote against, Enter or a click casts the vote
+                if selected == MenuHover::VoteKick && !candidates.is_empty() {
+                    if input.key_pressed(KeyCode::ArrowRight) {
+                        vote_kick_index = (vote_kick_index + 1) % candidates.len();
                     }
-                } else if input.mouse_pressed(MouseButton::Right) {
-                    if sens_bounds.contains(cursor_pos.0, cursor_pos.1) {
-                        mouse_sensitivity -= 0.01;
-                        if mouse_sensitivity < MOUSE_SENSITIVITY_MIN {
-                            mouse_sensitivity = MOUSE_SENSITIVITY_MAX;
-                        }
-                        sensitivity_changed = true;
+                    if input.key_pressed(KeyCode::ArrowLeft) {
+                        vote_kick_index =
+                            (vote_kick_index + candidates.len() - 1) % candidates.len();
                     }
                 }
 
-                if sensitivity_changed {
-                    config.mouse_sensitivity = Some(mouse_sensitivity);
-                    if let Err(e) = save_config(&config) {
-                        eprintln!("Error saving config: {}", e);
+                let mut config_changed = false;
+                if activated.is_some() {
+                    match activated {
+                        Some(MenuHover::Quit) => {
+                            elwt.exit();
+                            return;
+                        }
+                        Some(MenuHover::MouseSensitivity) => {
+                            mouse_sensitivity += 0.01;
+                            if mouse_sensitivity > MOUSE_SENSITIVITY_MAX {
+                                mouse_sensitivity = MOUSE_SENSITIVITY_MIN;
+                            }
+                            config.mouse_sensitivity = Some(mouse_sensitivity);
+                            config_changed = true;
+                        }
+                        Some(MenuHover::HorizontalSensitivity) => {
+                            config.horizontal_sensitivity = Some(step_axis_sensitivity(
+                                config.horizontal_sensitivity(),
+                                AXIS_SENSITIVITY_STEP,
+                            ));
+                            config_changed = true;
+                        }
+                        Some(MenuHover::VerticalSensitivity) => {
+                            config.vertical_sensitivity = Some(step_axis_sensitivity(
+                                config.vertical_sensitivity(),
+                                AXIS_SENSITIVITY_STEP,
+                            ));
+                            config_changed = true;
+                        }
+                        Some(MenuHover::GamepadSensitivity) => {
+                            config.gamepad_sensitivity = Some(step_gamepad_sensitivity(
+                                config.gamepad_sensitivity(),
+                                GAMEPAD_SENSITIVITY_STEP,
+                            ));
+                            config_changed = true;
+                        }
+                        Some(MenuHover::InvertY) => {
+                            config.invert_y = Some(!config.invert_y());
+                            config_changed = true;
+                        }
+                        Some(MenuHover::SprintMode) => {
+                            config.toggle_sprint = Some(!config.toggle_sprint());
+                            sprint_toggled = false;
+                            config_changed = true;
+                        }
+                        Some(MenuHover::CameraBob) => {
+                            config.camera_bob = Some(!config.camera_bob());
+                            renderer.camera_bob = config.camera_bob();
+                            config_changed = true;
+                        }
+                        Some(MenuHover::Tracers) => {
+                            config.show_tracers = Some(!config.show_tracers());
+                            renderer.show_tracers = config.show_tracers();
+                            config_changed = true;
+                        }
+                        Some(MenuHover::FireShake) => {
+                            config.fire_shake = Some(!config.fire_shake());
+                            renderer.fire_shake = config.fire_shake();
+                            config_changed = true;
+                        }
+                        Some(MenuHover::Fov) => {
+                            config.fov = Some(step_fov(config.fov(), FOV_STEP));
+                            renderer.set_fov(config.fov());
+                            config_changed = true;
+                        }
+                        Some(MenuHover::RenderScale) => {
+                            config.step_render_scale(1);
+                            renderer.set_render_scale(config.render_scale());
+                            config_changed = true;
+                        }
+                        Some(MenuHover::TexturedFloor) => {
+                            config.textured_floor = Some(!config.textured_floor());
+                            renderer.textured_floor = config.textured_floor();
+                            config_changed = true;
+                        }
+                        Some(MenuHover::DistanceFog) => {
+                            config.distance_fog = Some(!config.distance_fog());
+                            renderer.distance_fog = config.distance_fog();
+                            config_changed = true;
+                        }
+                        Some(MenuHover::KeyBindings) => {
+                            key_bindings_open = true;
+                            menu_selected = 0;
+                        }
+                        Some(MenuHover::Back) => {
+                            key_bindings_open = false;
+                            menu_selected = 0;
+                        }
+                        Some(MenuHover::Binding(action)) => {
+                            rebinding = Some(action);
+                        }
+                        Some(MenuHover::VoteKick) => {
+                            if let Some(target) = vote_target {
+                                let encoded =
+                                    bincode::serialize(&ClientMessage::VoteKick(target)).unwrap();
+                                if let Err(e) = socket.send(&encoded) {
+                                    eprintln!("Error sending vote: {}", e);
+                                }
+                            }
+                        }
+                        None => {}
                     }
+                } else if decreased.is_some() {
+                    match decreased {
+                        Some(MenuHover::MouseSensitivity) => {
+                            mouse_sensitivity -= 0.01;
+                            if mouse_sensitivity < MOUSE_SENSITIVITY_MIN {
+                                mouse_sensitivity = MOUSE_SENSITIVITY_MAX;
+                            }
+                            config.mouse_sensitivity = Some(mouse_sensitivity);
+                            config_changed = true;
+                        }
+                        Some(MenuHover::HorizontalSensitivity) => {
+                            config.horizontal_sensitivity = Some(step_axis_sensitivity(
+                                config.horizontal_sensitivity(),
+                                -AXIS_SENSITIVITY_STEP,
+                            ));
+                            config_changed = true;
+                        }
+                        Some(MenuHover::VerticalSensitivity) => {
+                            config.vertical_sensitivity = Some(step_axis_sensitivity(
+                                config.vertical_sensitivity(),
+                                -AXIS_SENSITIVITY_STEP,
+                            ));
+                            config_changed = true;
+                        }
+                        Some(MenuHover::GamepadSensitivity) => {
+                            config.gamepad_sensitivity = Some(step_gamepad_sensitivity(
+                                config.gamepad_sensitivity(),
+                                -GAMEPAD_SENSITIVITY_STEP,
+                            ));
+                            config_changed = true;
+                        }
+                        Some(MenuHover::Fov) => {
+                            config.fov = Some(step_fov(config.fov(), -FOV_STEP));
+                            renderer.set_fov(config.fov());
+                            config_changed = true;
+                        }
+                        Some(MenuHover::RenderScale) => {
+                            config.step_render_scale(-1);
+                            renderer.set_render_scale(config.render_scale());
+                            config_changed = true;
+                        }
+                        _ => {}
+                    }
+                }
+
+                if config_changed && let Err(e) = save_config(&config) {
+                    eprintln!("Error saving config: {}", e);
                 }
             } else {
                 menu_hovered_item = None;
             }
+            let pad = gamepad.poll();
             if !show_menu && game_state.as_ref().map(|gs| gs.winner.is_none()).unwrap_or(false) {
                 if input.key_pressed(KeyCode::Tab) {
                     cursor_grabbed = !cursor_grabbed;
@@ -452,16 +1577,56 @@ fn main() -> Result<()> {
                         .unwrap();
                 }
 
-                let mut turn = mouse_dx * mouse_sensitivity;
-                if input.key_held(KeyCode::ArrowLeft) {
+                let mut turn = mouse_dx * mouse_sensitivity * config.horizontal_sensitivity();
+                if config.key_bindings.held(&input, Action::TurnLeft) {
                     turn -= 1.0;
                 }
-                if input.key_held(KeyCode::ArrowRight) {
+                if config.key_bindings.held(&input, Action::TurnRight) {
                     turn += 1.0;
                 }
+                turn += pad.look.0 * config.gamepad_sensitivity();
 
-                let can_shoot = last_shot_timestamp.elapsed() >= SHOOT_COOLDOWN;
-                let mouse_pressed = input.mouse_pressed(MouseButton::Left);
+                // Invert mouse_dy for natural pitch control, unless the player wants it the other way
+                let mut pitch = -mouse_dy * mouse_sensitivity * config.vertical_sensitivity()
+                    + pad.look.1 * config.gamepad_sensitivity();
+                if config.invert_y() {
+                    pitch = -pitch;
+                }
+
+                // The free camera is view only, the player stands still and can't shoot meanwhile
+                let flying = renderer.free_camera.is_some();
+                let me = game_state
+                    .as_ref()
+                    .and_then(|gs| gs.players.get(&my_id.to_string()));
+
+                // While dead, left and right (mouse buttons or arrow keys) pick a living player to
+                // watch. Respawning snaps back to the own view.
+                if me.is_some_and(|player| player.health > 0) {
+                    renderer.camera = Camera::Own;
+                } else if let Some(gs) = &game_state {
+                    let step = if input.mouse_pressed(MouseButton::Left)
+                        || input.key_pressed(KeyCode::ArrowRight)
+                    {
+                        Some(1)
+                    } else if input.mouse_pressed(MouseButton::Right)
+                        || input.key_pressed(KeyCode::ArrowLeft)
+                    {
+                        Some(-1)
+                    } else {
+                        None
+                    };
+                    if let Some(step) = step {
+                        renderer.camera = cycle_spectating(gs, my_id, renderer.camera, step);
+                    }
+                }
+                // a click while dead picks whom to spectate, it doesn't fire
+                let can_shoot = !flying
+                    && me.is_some_and(|player| {
+                        player.health > 0
+                            && player.can_fire()
+                            && last_shot_timestamp.elapsed() >= player.current_weapon.cooldown()
+                    });
+                let mouse_pressed = input.mouse_pressed(MouseButton::Left) || pad.shoot;
                 
                 if mouse_pressed && can_shoot {
                     let shot_message = ClientMessage::Shot;
@@ -470,19 +1635,88 @@ fn main() -> Result<()> {
                         eprintln!("Error sending shot data: {}", e);
                     } else {
                         last_shot_timestamp = Instant::now();
+                        audio.play_shot();
+                        renderer.shake_from_firing();
+                        renderer.show_muzzle_flash(my_id);
                     }
                 }
 
-                let client_input = Input {
-                    forth: input.key_held(KeyCode::ArrowUp) || input.key_held(KeyCode::KeyW),
-                    back: input.key_held(KeyCode::ArrowDown) || input.key_held(KeyCode::KeyS),
-                    left: input.key_held(KeyCode::KeyA),
-                    right: input.key_held(KeyCode::KeyD),
-                    turn,
-                    pitch: -mouse_dy * mouse_sensitivity, // Invert mouse_dy for natural pitch control
-                    jump: input.key_pressed(KeyCode::Space),
-                    sprint: input.key_held(KeyCode::ShiftLeft),
-                    shoot: mouse_pressed && can_shoot,
+                // Weapon keys pick a weapon, the one already in hand isn't switched to again
+                let picked_slot = [Action::Pistol, Action::Shotgun, Action::Rifle]
+                    .into_iter()
+                    .position(|action| config.key_bindings.pressed(&input, action))
+                    .map(|index| index as u8 + 1);
+                if let Some(slot) = picked_slot
+                    && !flying
+                    && me.is_some_and(|player| {
+                        Some(player.current_weapon) != Weapon::from_slot(slot)
+                    })
+                {
+                    let encoded_switch =
+                        bincode::serialize(&ClientMessage::SwitchWeapon(slot)).unwrap();
+                    if let Err(e) = socket.send(&encoded_switch) {
+                        eprintln!("Error sending weapon switch: {}", e);
+                    }
+                }
+
+                if config.key_bindings.pressed(&input, Action::Reload) && !flying {
+                    let encoded_reload = bincode::serialize(&ClientMessage::Reload).unwrap();
+                    if let Err(e) = socket.send(&encoded_reload) {
+                        eprintln!("Error sending reload: {}", e);
+                    }
+                }
+
+                if config.key_bindings.pressed(&input, Action::Use) && !flying {
+                    let encoded_interact = bincode::serialize(&ClientMessage::Interact).unwrap();
+                    if let Err(e) = socket.send(&encoded_interact) {
+                        eprintln!("Error sending interact: {}", e);
+                    }
+                }
+
+                // the up and down arrows always move too, whatever forward and back are bound to
+                let bindings = &config.key_bindings;
+                let forth = input.key_held(KeyCode::ArrowUp)
+                    || bindings.held(&input, Action::Forward)
+                    || pad.movement.1 > GAMEPAD_MOVE_THRESHOLD;
+                let back = input.key_held(KeyCode::ArrowDown)
+                    || bindings.held(&input, Action::Back)
+                    || pad.movement.1 < -GAMEPAD_MOVE_THRESHOLD;
+                let left = bindings.held(&input, Action::StrafeLeft)
+                    || pad.movement.0 < -GAMEPAD_MOVE_THRESHOLD;
+                let right = bindings.held(&input, Action::StrafeRight)
+                    || pad.movement.0 > GAMEPAD_MOVE_THRESHOLD;
+                let jump = bindings.pressed(&input, Action::Jump) || pad.jump;
+                let crouching = bindings.held(&input, Action::Crouch) || pad.crouch;
+
+                // Toggle sprint stays on until pressed again or the player stops moving
+                let sprint = if config.toggle_sprint() {
+                    if bindings.pressed(&input, Action::Sprint) {
+                        sprint_toggled = !sprint_toggled;
+                    }
+                    if !(forth || back || left || right) {
+                        sprint_toggled = false;
+                    }
+                    sprint_toggled
+                } else {
+                    bindings.held(&input, Action::Sprint)
+                };
+
+                let client_input = if let Some(camera) = &mut renderer.free_camera {
+                    fly_free_camera(camera, &input, (turn, pitch), delta_time);
+                    Input::default()
+                } else {
+                    Input {
+                        forth,
+                        back,
+                        left,
+                        right,
+                        turn,
+                        pitch,
+                        jump,
+                        sprint,
+                        crouching,
+                        shoot: mouse_pressed && can_shoot,
+                    }
                 };
                 mouse_dx = 0.0;
                 mouse_dy = 0.0;
@@ -498,19 +1732,49 @@ fn main() -> Result<()> {
             }
         }
 
-        let mut buf = [0; 2048];
+        let mut buf = [0; MAX_DATAGRAM_SIZE];
 
         loop {
             match socket.recv(&mut buf) {
                 Ok(amt) => {
                     if let Ok(server_message) = bincode::deserialize::<ServerMessage>(&buf[..amt]) {
+                        // critical messages come numbered, every copy is acknowledged but only
+                        // the first one handled, and a snapshot older than one already handled not
+                        // even that
+                        let server_message = match server_message {
+                            ServerMessage::Reliable(seq, message) => {
+                                let encoded_ack =
+                                    bincode::serialize(&ClientMessage::Ack(seq)).unwrap();
+                                if let Err(e) = socket.send(&encoded_ack) {
+                                    eprintln!("Error sending ack: {}", e);
+                                }
+                                if !received.first_time(seq) || received.outdated(seq, &message) {
+                                    continue;
+                                }
+                                *message
+                            }
+                            message => message,
+                        };
                         match server_message {
                             ServerMessage::Welcome(_) => {
                                 // This should not happen after initial connection
                                 eprintln!("Received unexpected Welcome message");
                             }
                             ServerMessage::InitialState(initial_state) => {
-                                game_state = Some(initial_state);
+                                for player in initial_state.players.values() {
+                                    renderer.load_sprite_sheet(&player.texture);
+                                }
+                                renderer.set_projectiles(initial_state.projectiles.clone());
+                                fps::textures::load_map_textures(
+                                    &mut renderer.texture_manager,
+                                    &initial_state.world,
+                                );
+                                game_state = Some(*initial_state);
+                                remote_motion.clear();
+                                prediction.reset();
+                                renderer.reset_exploration();
+                                match_stats = None;
+                                match_end_dismissed = false;
                                 // Reset menu state when a new game starts
                                 if show_menu && CLOSE_MENU_ON_NEW_GAME {
                                     show_menu = false;
@@ -519,20 +1783,92 @@ fn main() -> Result<()> {
                                     first_mouse_move = true;
                                 }
                             }
-                            ServerMessage::GameUpdate(player_updates) => {
+                            // a delta is the same kind of update, just for fewer players
+                            ServerMessage::GameUpdate(player_updates)
+                            | ServerMessage::GameDelta(player_updates) => {
                                 if let Some(ref mut gs) = game_state {
                                     for (id, update) in player_updates {
+                                        renderer.load_sprite_sheet(&update.texture);
                                         if let Some(player) = gs.players.get_mut(&id) {
-                                            player.x = update.x;
-                                            player.y = update.y;
-                                            player.z = update.z;
-                                            player.angle = update.angle;
-                                            player.pitch = update.pitch;
+                                            // teleports jump straight to the other end instead of
+                                            // gliding across the map
+                                            let teleported = player.teleports != update.teleports;
+                                            if teleported && id == my_id.to_string() {
+                                                renderer.show_teleport_flash();
+                                            }
+                                            if teleported {
+                                                remote_motion.remove(&id);
+                                            }
+                                            // my own player is predicted and only eased towards
+                                            // the server while alive
+                                            let predicted = id == my_id.to_string()
+                                                && config.client_prediction()
+                                                && !teleported
+                                                && player.health > 0
+                                                && update.health > 0;
+                                            if predicted {
+                                                prediction.reconcile(
+                                                    player,
+                                                    (
+                                                        update.x,
+                                                        update.y,
+                                                        update.z,
+                                                        update.angle,
+                                                        update.pitch,
+                                                    ),
+                                                );
+                                            } else if id != my_id.to_string()
+                                                && config.remote_interpolation()
+                                                && !teleported
+                                            {
+                                                let drawn =
+                                                    (player.x, player.y, player.z, player.angle);
+                                                let target =
+                                                    (update.x, update.y, update.z, update.angle);
+                                                match remote_motion.get_mut(&id) {
+                                                    Some(motion) => motion.retarget(drawn, target),
+                                                    None => {
+                                                        remote_motion.insert(
+                                                            id.clone(),
+                                                            RemoteMotion::new(target),
+                                                        );
+                                                    }
+                                                }
+                                            } else {
+                                                player.x = update.x;
+                                                player.y = update.y;
+                                                player.z = update.z;
+                                                player.angle = update.angle;
+                                                if id == my_id.to_string() {
+                                                    prediction.reset();
+                                                }
+                                            }
+                                            if !predicted {
+                                                player.pitch = update.pitch;
+                                                player.crouch = update.crouch;
+                                            }
                                             player.texture = update.texture;
+                                            // restart frame timing on every state change, so a
+                                            // death always plays from its first frame
+                                            if player.animation_state != update.animation_state {
+                                                player.frame = 0;
+                                                player.frame_timer = 0.0;
+                                            }
                                             player.animation_state = update.animation_state;
                                             player.shooting = update.shooting;
                                             player.health = update.health;
                                             player.score = update.score;
+                                            player.heat = update.heat;
+                                            player.overheated = update.overheated;
+                                            player.protected = update.protected;
+                                            player.ammo = update.ammo;
+                                            player.reserve_ammo = update.reserve_ammo;
+                                            player.reloading = update.reloading;
+                                            player.current_weapon = update.current_weapon;
+                                            player.switching = update.switching;
+                                            player.teleports = update.teleports;
+                                            player.stamina = update.stamina;
+                                            player.exhausted = update.exhausted;
                                         } else {
                                             // New player joined — insert into local game state
                                             let mut p = Player::new("0".to_string(), &gs.world);
@@ -544,6 +1880,9 @@ fn main() -> Result<()> {
                                             p.texture = update.texture;
                                             p.animation_state = update.animation_state;
                                             p.shooting = update.shooting;
+                                            p.protected = update.protected;
+                                            p.teleports = update.teleports;
+                                            p.crouch = update.crouch;
                                             p.direction = fps::Direction::Front;
                                             gs.players.insert(id.clone(), p);
                                         }
@@ -559,13 +1898,31 @@ fn main() -> Result<()> {
                                 if let Some(ref mut gs) = game_state {
                                     gs.players.remove(&id.to_string());
                                 }
+                                remote_motion.remove(&id.to_string());
                             }
                             ServerMessage::ShotHit(hit) => {
-                                if hit.shooter_id == my_id {
-                                    // Flash a hit marker for successful hit
-                                    renderer.show_hit_marker(0x00FFFFFF);
-                                } else if hit.target_id == my_id {
+                                if hit.shooter_id == my_id || hit.target_id == my_id {
+                                    if hit.killed {
+                                        audio.play_death();
+                                    } else {
+                                        audio.play_hit();
+                                    }
+                                }
+                                // a fall hits us as both shooter and target, take it as damage
+                                if hit.target_id == my_id {
                                     renderer.show_damage_flash();
+                                    renderer.shake_from_hit();
+                                    if hit.killed && config.reset_exploration_on_death() {
+                                        renderer.reset_exploration();
+                                    }
+                                } else if hit.shooter_id == my_id {
+                                    // Flash a hit marker for successful hit
+                                    renderer.show_hit_marker(hit.damage, hit.killed, hit.kind);
+                                    renderer.add_damage_number(
+                                        hit.damage,
+                                        hit.killed,
+                                        hit.target_pos,
+                                    );
                                 }
                             }
                             ServerMessage::LeaderboardUpdate(leaderboard) => {
@@ -577,8 +1934,78 @@ fn main() -> Result<()> {
                                 if let Some(ref mut gs) = game_state {
                                     gs.winner = Some(winner);
                                 }
+                                renderer.clear_round_time();
                                 break;
                             }
+                            ServerMessage::MatchEnd(stats) => {
+                                match_stats = Some(stats);
+                            }
+                            ServerMessage::Tracer(tracer) => {
+                                renderer.add_tracer(tracer);
+                            }
+                            ServerMessage::Projectiles(projectiles) => {
+                                renderer.set_projectiles(projectiles);
+                            }
+                            ServerMessage::ShotFired(shooter_id) => {
+                                // our own shots already played and flashed when they were sent
+                                if shooter_id != my_id {
+                                    renderer.show_muzzle_flash(shooter_id);
+                                }
+                                if shooter_id != my_id
+                                    && let Some(gs) = &game_state
+                                    && let Some(me) = gs.players.get(&my_id.to_string())
+                                    && let Some(shooter) = gs.players.get(&shooter_id.to_string())
+                                {
+                                    audio.play_shot_from(&gs.world, me, (shooter.x, shooter.y));
+                                }
+                            }
+                            ServerMessage::DoorUpdate((x, y), open) => {
+                                if let Some(gs) = &mut game_state {
+                                    gs.world.set_door(x, y, open);
+                                }
+                            }
+                            ServerMessage::KillStreak(streak) => {
+                                let title = if streak.first_blood {
+                                    Some("First Blood!")
+                                } else {
+                                    streak_title(streak.streak)
+                                };
+                                if let Some(title) = title {
+                                    renderer.show_announcement(
+                                        format!("{} - {}", streak.name, title),
+                                        ANNOUNCEMENT_DURATION,
+                                    );
+                                }
+                            }
+                            ServerMessage::VoteKick(status) => {
+                                renderer.show_vote_kick(status);
+                            }
+                            ServerMessage::VoteKickRejected(reason) => {
+                                renderer.show_announcement(reason, ANNOUNCEMENT_DURATION);
+                            }
+                            ServerMessage::Warmup(remaining) => {
+                                renderer.show_warmup(remaining);
+                            }
+                            ServerMessage::RoundTime(remaining) => {
+                                renderer.show_round_time(remaining);
+                            }
+                            ServerMessage::DummyUpdate(dummies) => {
+                                if let Some(ref mut gs) = game_state {
+                                    gs.dummies = dummies;
+                                }
+                            }
+                            ServerMessage::Kicked(reason) => {
+                                println!("{}", reason);
+                                server_closed_flag.set(true);
+                                elwt.exit();
+                                return;
+                            }
+                            ServerMessage::ServerShutdown => {
+                                println!("The server was closed.");
+                                server_closed_flag.set(true);
+                                elwt.exit();
+                                return;
+                            }
                             _ => {}
                         }
                     }
@@ -599,27 +2026,63 @@ fn main() -> Result<()> {
         }
 
         if let Some(gs) = &mut game_state {
-            for player in gs.players.values_mut() {
+            // doors slide on their own, only opening and closing comes from the server
+            gs.world.step_doors(delta_time);
+
+            // my own player runs the input last sent to the server ahead of its updates
+            if config.client_prediction()
+                && renderer.free_camera.is_none()
+                && let Some(me) = gs.players.get_mut(&my_id.to_string())
+            {
+                let input = prev_input.clone().unwrap_or_default();
+                let rules = (gs.max_air_jumps, gs.bhop);
+                prediction.advance(me, &gs.world, rules, &input, delta_time);
+            }
+
+            // other players are drawn partway along their glide to the latest server state
+            for (id, motion) in &remote_motion {
+                if let Some(player) = gs.players.get_mut(id) {
+                    motion.apply(player);
+                }
+            }
+
+            // one footstep per walk cycle, none once a player stops walking
+            let mut footsteps = Vec::new();
+            for (id, player) in gs.players.iter_mut() {
                 if player.animation_state == Walking {
                     player.frame_timer += delta_time;
                     if player.frame_timer > WALK_FRAME_TIME {
                         player.frame_timer = 0.0;
-                        player.frame = (player.frame + 1) % 4;
+                        player.frame = (player.frame + 1) % WALK_FRAME_COUNT;
+                        if player.frame == 0 {
+                            footsteps.push((id.clone(), (player.x, player.y)));
+                        }
                     }
                 } else if player.animation_state == Dying {
+                    // the last frame holds, its timer keeps running for the camera slump
                     player.frame_timer += delta_time;
-                    if player.frame_timer > DIE_FRAME_TIME {
+                    if player.frame_timer > DIE_FRAME_TIME && player.frame + 1 < DIE_FRAME_COUNT {
                         player.frame_timer = 0.0;
-                        player.frame = cmp::min(player.frame + 1, 2);
+                        player.frame += 1;
                     }
                 } else {
                     player.frame = 0;
                 }
             }
+            for (id, position) in footsteps {
+                if id == my_id.to_string() {
+                    audio.play_footstep();
+                } else if let Some(me) = gs.players.get(&my_id.to_string()) {
+                    audio.play_footstep_from(&gs.world, me, position);
+                }
+            }
         }
 
         window_clone.request_redraw();
-    })?)
+    })?;
+
+    connected.store(false, Ordering::Relaxed);
+    Ok(server_closed.get())
 }
 
 fn center_and_grab_cursor(window: Arc<Window>) {
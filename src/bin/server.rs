@@ -1,39 +1,78 @@
 use fps::{
-    ClientMessage, PlayerUpdate, ServerMessage, Welcome,
-    consts::{PORT, SCORE_TO_WIN, SHOOT_COOLDOWN, TICK_RATE, WIN_SLEEP_TIME},
+    Beacon, ClientMessage, HitKind, KillStreak, PlayerUpdate, Score, ServerMessage, Weapon,
+    Welcome,
+    bot::Bot,
+    consts::{
+        BEACON_INTERVAL, DEFAULT_RATE_LIMIT, DEFAULT_SCORE_TO_WIN, DISCOVERY_PORT, DOOR_OPEN_TIME,
+        DOOR_USE_RANGE, DOUBLE_JUMP_AIR_JUMPS, GAME_UPDATE_KEYFRAME_TICKS, IDLE_WAIT_TIMEOUT,
+        MAP_WATCH_INTERVAL, METRICS_INTERVAL, RATE_LIMIT_IGNORE_TIME, RELIABLE_RESEND_INTERVAL,
+        REPLAY_DIR, ROUND_TIME_INTERVAL, ROUTER_POLL_INTERVAL, SPRITE_SHEET_COUNT, TICK_RATE,
+        WIN_SLEEP_TIME,
+    },
+    diagnostics::ServerMetrics,
     flags,
     gamestate::GameState,
+    map::World,
     player::Player,
+    ratelimit::{RateLimiter, RateVerdict},
+    reliable::{OUTBOX, Outbox},
+    replay::ReplayBuffer,
     utils,
+    votekick::VoteKicks,
 };
 use local_ip_address::local_ip;
 use rand::prelude::*;
 use rand::rng;
 use std::{
-    collections::HashMap,
+    collections::{HashMap, VecDeque},
     env,
-    net::{SocketAddr, UdpSocket},
+    net::{Ipv4Addr, SocketAddr, UdpSocket},
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        mpsc,
+    },
+    thread::JoinHandle,
     time::{Duration, Instant},
 };
 
 fn main() -> std::io::Result<()> {
     let parsed_flags = flags::parse_flags(env::args()).expect("Failed to parse flags");
 
-    let my_local_ip = local_ip().unwrap();
-    let socket = UdpSocket::bind(format!("{}:{}", my_local_ip, PORT))?;
-    socket.set_nonblocking(true)?;
-    println!("Server started at {}:{}", my_local_ip, PORT);
+    // Ctrl-C only raises a flag, the main loop notices it and tells clients before exiting
+    let shutdown = Arc::new(AtomicBool::new(false));
+    let shutdown_handler = shutdown.clone();
+    ctrlc::set_handler(move || shutdown_handler.store(true, Ordering::SeqCst))
+        .expect("Failed to set Ctrl-C handler");
+
+    // without --bind, listen on the LAN address clients are told to connect to
+    let bind_ip = match parsed_flags.bind {
+        Some(address) => address,
+        None => local_ip().unwrap(),
+    };
+    let socket = UdpSocket::bind((bind_ip, parsed_flags.port))?;
+    socket.set_read_timeout(Some(ROUTER_POLL_INTERVAL))?;
+    println!("Server started at {}", socket.local_addr()?);
     let random_map = parsed_flags.random_map;
-    let map_display = if parsed_flags.specific_map {
+    let map_display = if let Some(first) = parsed_flags.rotation.first() {
+        let labels: Vec<String> = parsed_flags
+            .rotation
+            .iter()
+            .map(|map| map.label())
+            .collect();
+        println!("Rotating maps between matches: {}", labels.join(", "));
+        first.clone()
+    } else if parsed_flags.specific_map {
         println!(
             "Using specific map: {}",
             match &parsed_flags.map {
                 flags::MapIdentifier::Id(id) => id.to_string(),
                 flags::MapIdentifier::Name(name) => name.clone(),
+                flags::MapIdentifier::Path(path) => path.clone(),
                 _ => panic!("Invalid map identifier"),
             }
         );
-        parsed_flags.map
+        parsed_flags.map.clone()
     } else if random_map {
         println!("Using randomly generated map");
         flags::MapIdentifier::Random
@@ -41,256 +80,1196 @@ fn main() -> std::io::Result<()> {
         println!("Using random premade map");
         flags::MapIdentifier::Id(rand::random_range(1..=3))
     };
+    if let Some(name) = &parsed_flags.server_name {
+        println!("Server name: {}", name);
+    }
+    if let Some(motd) = &parsed_flags.motd {
+        println!("Message of the day: {}", motd);
+    }
+    if parsed_flags.password.is_some() {
+        println!("Players need the password to join");
+    }
     if parsed_flags.permanent_map {
         println!("And keeping it between matches");
     }
+    if parsed_flags.double_jump {
+        println!("Double jump enabled");
+    }
+    if parsed_flags.bhop {
+        println!("Chained jumps keep and build up speed");
+    }
+    if parsed_flags.projectiles {
+        println!("Shots fly as projectiles");
+    }
+    if parsed_flags.kill_bonuses {
+        println!("Bonus points for first blood and kill streaks");
+    }
+    if parsed_flags.mode == flags::GameMode::Instagib {
+        println!("Instagib: every hit kills, fast respawns");
+    }
+    if let Some(duration) = parsed_flags.spawn_protection {
+        println!(
+            "Spawn protection for {:.1} s after respawning",
+            duration.as_secs_f32()
+        );
+    }
+    if parsed_flags.overheat {
+        println!("Weapons overheat under sustained fire");
+    }
+    if let Some(duration) = parsed_flags.warmup {
+        println!(
+            "Each match starts with a {:.1} s warmup, type 'start' to end it early",
+            duration.as_secs_f32()
+        );
+    }
+    if parsed_flags.score_to_win != DEFAULT_SCORE_TO_WIN {
+        println!("First to {} points wins", parsed_flags.score_to_win);
+    }
+    if let Some(limit) = parsed_flags.time_limit {
+        println!(
+            "Matches end after {:.0} s, the leader wins or a tie goes to sudden death",
+            limit.as_secs_f32()
+        );
+    }
+    if let Some(seconds) = parsed_flags.replay_seconds {
+        println!(
+            "Keeping {} s of replay, suspicious events are saved to {}/",
+            seconds, REPLAY_DIR
+        );
+    }
+    if parsed_flags.metrics {
+        println!(
+            "Printing server metrics every {} s",
+            METRICS_INTERVAL.as_secs()
+        );
+    }
+    if parsed_flags.watch {
+        println!("Watching the map file for changes");
+    }
+    if parsed_flags.suffix_names {
+        println!("Duplicate usernames get a number suffix");
+    }
+    let time_scale = parsed_flags.time_scale;
+    if time_scale != 1.0 {
+        println!("Running simulation at {}x speed", time_scale);
+    }
+    if parsed_flags.rate_limit != DEFAULT_RATE_LIMIT || parsed_flags.log_rate_limit {
+        println!(
+            "Limiting each client to {} messages per second",
+            parsed_flags.rate_limit
+        );
+    }
+    if parsed_flags.arenas > 1 {
+        println!(
+            "Hosting {} arenas, new players join the least full one",
+            parsed_flags.arenas
+        );
+    }
+    if parsed_flags.bots > 0 {
+        println!(
+            "{} {} bots join each arena",
+            parsed_flags.bots,
+            parsed_flags.bot_difficulty.label()
+        );
+    }
+    if let Some(max_players) = parsed_flags.max_players {
+        println!("At most {} players per arena", max_players);
+    }
+
+    // Each arena runs its own matches on its own thread with its own players
+    let parsed_flags = Arc::new(parsed_flags);
+    let mut arenas = Vec::with_capacity(parsed_flags.arenas);
+    let (departure_sender, departures) = mpsc::channel();
+    for index in 0..parsed_flags.arenas {
+        let (packet_sender, packets) = mpsc::channel();
+        let (command_sender, commands) = mpsc::channel();
+        let map = Arc::new(Mutex::new(map_display.label()));
+        let link = ArenaLink {
+            index,
+            packets,
+            commands,
+            departures: departure_sender.clone(),
+            map: map.clone(),
+        };
+        let arena_flags = parsed_flags.clone();
+        let arena_map = map_display.clone();
+        let arena_socket = socket.try_clone()?;
+        let arena_shutdown = shutdown.clone();
+        let thread = std::thread::spawn(move || {
+            run_arena(&arena_flags, arena_map, arena_socket, link, &arena_shutdown)
+        });
+        arenas.push(ArenaHandle {
+            packets: packet_sender,
+            commands: command_sender,
+            players: AtomicUsize::new(0),
+            map,
+            thread,
+        });
+    }
+
+    // The main thread only reads the socket and hands each datagram to its sender's arena.
+    // Addresses seen for the first time go to the arena with the fewest players.
+    let console = utils::spawn_console();
+    let mut rate_limiter = RateLimiter::new(parsed_flags.rate_limit);
+    let mut assigned = HashMap::<SocketAddr, usize>::new();
+    let mut buf = [0; 1024];
+    // Beacons let clients on the LAN find the server without typing its address
+    let game_port = socket.local_addr()?.port();
+    let mut beacon_socket = UdpSocket::bind((bind_ip, 0))
+        .and_then(|beacon_socket| beacon_socket.set_broadcast(true).map(|_| beacon_socket))
+        .inspect(|_| println!("Announcing the server on the LAN, port {}", DISCOVERY_PORT))
+        .inspect_err(|e| eprintln!("No LAN beacons, couldn't open a broadcast socket: {}", e))
+        .ok();
+    let mut last_beacon: Option<Instant> = None;
+    while !shutdown.load(Ordering::SeqCst) && arenas.iter().all(|arena| !arena.thread.is_finished())
+    {
+        while let Ok(command) = console.try_recv() {
+            for arena in &arenas {
+                let _ = arena.commands.send(command.clone());
+            }
+        }
+
+        // Addresses an arena is done with are free to join the emptiest arena again
+        while let Ok(addr) = departures.try_recv() {
+            if let Some(index) = assigned.remove(&addr) {
+                arenas[index].players.fetch_sub(1, Ordering::Relaxed);
+            }
+        }
+
+        if let Some(beacon) = &beacon_socket
+            && last_beacon.is_none_or(|sent| sent.elapsed() >= BEACON_INTERVAL)
+        {
+            last_beacon = Some(Instant::now());
+            if let Err(e) = send_beacon(beacon, game_port, &parsed_flags, &arenas) {
+                eprintln!("Stopped LAN beacons, couldn't send one: {}", e);
+                beacon_socket = None;
+            }
+        }
+
+        match socket.recv_from(&mut buf) {
+            Ok((amt, src)) => {
+                // Floods are dropped here, before an arena spends any time on them
+                match rate_limiter.check(src) {
+                    RateVerdict::Allow => {}
+                    RateVerdict::Drop { first } => {
+                        if first && parsed_flags.log_rate_limit {
+                            println!(
+                                "Dropping messages from {}, over {} per second",
+                                src, parsed_flags.rate_limit
+                            );
+                        }
+                        continue;
+                    }
+                    RateVerdict::Ignore { started } => {
+                        if started {
+                            println!(
+                                "Ignoring {} for {} s, flooding the server",
+                                src,
+                                RATE_LIMIT_IGNORE_TIME.as_secs()
+                            );
+                        }
+                        continue;
+                    }
+                }
+                let index = *assigned.entry(src).or_insert_with(|| {
+                    let (index, arena) = arenas
+                        .iter()
+                        .enumerate()
+                        .min_by_key(|(_, arena)| arena.players.load(Ordering::Relaxed))
+                        .expect("there is always at least one arena");
+                    // counted right away so a burst of joins spreads out before the arena catches up
+                    arena.players.fetch_add(1, Ordering::Relaxed);
+                    if arenas.len() > 1 {
+                        println!("{} joined arena {}", src, index + 1);
+                    }
+                    index
+                });
+                let _ = arenas[index].packets.send((buf[..amt].to_vec(), src));
+            }
+            Err(ref e)
+                if matches!(
+                    e.kind(),
+                    std::io::ErrorKind::WouldBlock
+                        | std::io::ErrorKind::TimedOut
+                        | std::io::ErrorKind::Interrupted
+                ) => {}
+            Err(ref e) if e.kind() == std::io::ErrorKind::ConnectionReset => {
+                // On Windows, we get "connection reset" errors on UDP sockets
+                // when a client sends an ICMP port unreachable message.
+                // We can safely ignore these and have a clean terminal.
+                // Later client will be safely timed out.
+            }
+            Err(e) => eprintln!("Couldn't receive a datagram: {}", e),
+        }
+    }
+
+    // One arena failing stops the others too, like the whole server stopped before arenas
+    shutdown.store(true, Ordering::SeqCst);
+    for arena in arenas {
+        arena.thread.join().expect("Arena thread panicked")?;
+    }
+    Ok(())
+}
+
+/// An arena's side of the main thread's routing: datagrams from its players, commands typed into
+/// the console, the players it's done with, and its current map, which the main thread reads to
+/// fill in beacons
+struct ArenaLink {
+    index: usize,
+    packets: mpsc::Receiver<(Vec<u8>, SocketAddr)>,
+    commands: mpsc::Receiver<String>,
+    // Addresses of players that left, were kicked or timed out, and of rejected joins
+    departures: mpsc::Sender<SocketAddr>,
+    map: Arc<Mutex<String>>,
+}
+
+/// The main thread's side of a running arena
+struct ArenaHandle {
+    packets: mpsc::Sender<(Vec<u8>, SocketAddr)>,
+    commands: mpsc::Sender<String>,
+    // Addresses routed here and not yet reported gone
+    players: AtomicUsize,
+    map: Arc<Mutex<String>>,
+    thread: JoinHandle<std::io::Result<()>>,
+}
+
+/// Starts the --time-limit clock as the match goes live and tells the players how long it runs.
+/// Returns when the match ends, None without a time limit.
+fn start_round_clock(
+    time_limit: Option<Duration>,
+    socket: &UdpSocket,
+    clients: &HashMap<SocketAddr, (u64, String, Instant)>,
+) -> std::io::Result<Option<Instant>> {
+    let Some(limit) = time_limit else {
+        return Ok(None);
+    };
+    if !clients.is_empty() {
+        utils::broadcast_message(ServerMessage::RoundTime(limit), socket, Some(clients), None)?;
+    }
+    Ok(Some(Instant::now() + limit))
+}
+
+/// Tells a player why they're being removed and drops them from the match, letting everyone else
+/// know they left
+fn kick_client(
+    addr: SocketAddr,
+    reason: &str,
+    socket: &UdpSocket,
+    clients: &mut HashMap<SocketAddr, (u64, String, Instant)>,
+    game_state: &mut GameState,
+    client_inputs: &mut HashMap<u64, fps::Input>,
+    client_sprites: &mut HashMap<u64, usize>,
+) -> std::io::Result<()> {
+    let Some((id, name, _)) = clients.remove(&addr) else {
+        return Ok(());
+    };
+    utils::broadcast_message(
+        ServerMessage::Kicked(reason.to_string()),
+        socket,
+        None,
+        Some(addr),
+    )?;
+    game_state.players.remove(&id.to_string());
+    game_state.leaderboard.remove(&name);
+    client_inputs.remove(&id);
+    client_sprites.remove(&id);
+    utils::broadcast_message(ServerMessage::PlayerLeft(id), socket, Some(clients), None)?;
+    utils::broadcast_message(
+        ServerMessage::LeaderboardUpdate(game_state.leaderboard.clone()),
+        socket,
+        Some(clients),
+        None,
+    )
+}
+
+/// The first sprite in the arena's shuffled order that no connected player has. Past
+/// SPRITE_SHEET_COUNT players the colours have to repeat.
+fn free_sprite(sprite_nums: &[usize], taken: &HashMap<u64, usize>, id: u64) -> usize {
+    sprite_nums
+        .iter()
+        .copied()
+        .find(|sprite| !taken.values().any(|used| used == sprite))
+        .unwrap_or(sprite_nums[id as usize % SPRITE_SHEET_COUNT])
+}
 
-    let current_map = map_display;
+/// Broadcasts the server's name, game port and player count, with the map of the arena a new
+/// player would join
+fn send_beacon(
+    socket: &UdpSocket,
+    game_port: u16,
+    parsed_flags: &flags::Flags,
+    arenas: &[ArenaHandle],
+) -> std::io::Result<()> {
+    let emptiest = arenas
+        .iter()
+        .min_by_key(|arena| arena.players.load(Ordering::Relaxed))
+        .expect("there is always at least one arena");
+    let beacon = ServerMessage::Beacon(Beacon {
+        name: parsed_flags.server_name.clone(),
+        port: game_port,
+        players: arenas
+            .iter()
+            .map(|arena| arena.players.load(Ordering::Relaxed))
+            .sum(),
+        map: emptiest.map.lock().unwrap().clone(),
+        password: parsed_flags.password.is_some(),
+    });
+    let encoded = bincode::serialize(&beacon).unwrap();
+    socket.send_to(&encoded, (Ipv4Addr::BROADCAST, DISCOVERY_PORT))?;
+    Ok(())
+}
+
+/// Keeps the finished match's result up for WIN_SLEEP_TIME, resending the winner and stats to
+/// whoever hasn't acknowledged them. Returns everything else that came in meanwhile, for the next
+/// match to handle.
+fn show_result(
+    packets: &mpsc::Receiver<(Vec<u8>, SocketAddr)>,
+    socket: &UdpSocket,
+) -> std::io::Result<VecDeque<(Vec<u8>, SocketAddr)>> {
+    let until = Instant::now() + WIN_SLEEP_TIME;
+    let mut held = VecDeque::new();
+    while let Some(left) = until.checked_duration_since(Instant::now()) {
+        if let Ok((datagram, src)) = packets.recv_timeout(left.min(RELIABLE_RESEND_INTERVAL)) {
+            match bincode::deserialize(&datagram) {
+                Ok(ClientMessage::Ack(seq)) => {
+                    OUTBOX.with_borrow_mut(|outbox| outbox.acknowledge(src, seq))
+                }
+                _ => held.push_back((datagram, src)),
+            }
+        }
+        OUTBOX.with_borrow_mut(|outbox| outbox.resend(socket))?;
+    }
+    Ok(held)
+}
+
+/// Plays matches one after another for the players routed to this arena until the server shuts
+/// down. Sends go straight out on the shared socket.
+fn run_arena(
+    parsed_flags: &flags::Flags,
+    current_map: flags::MapIdentifier,
+    socket: UdpSocket,
+    link: ArenaLink,
+    shutdown: &AtomicBool,
+) -> std::io::Result<()> {
+    let ArenaLink {
+        index,
+        packets,
+        commands: console,
+        departures,
+        map: shown_map,
+    } = link;
+    let random_map = parsed_flags.random_map;
+    let time_scale = parsed_flags.time_scale;
     let mut used_map = false;
+    let mut rotation_index = 0; // next map in --maps
     let mut clients = HashMap::<SocketAddr, (u64, String, Instant)>::new();
     let mut client_inputs = HashMap::<u64, fps::Input>::new();
+    let mut client_sprites = HashMap::<u64, usize>::new();
     let mut last_shot_timestamp = HashMap::<u64, Instant>::new();
     let mut next_id: u64 = 0;
     let mut _pending_win: Option<(String, usize)> = None; // (winner_name, score)
 
     // Create and shuffle numbers for assigning random sprites to players
     let mut rng = rng();
-    let mut sprite_nums: Vec<u8> = (0..10).collect();
+    let mut sprite_nums: Vec<usize> = (0..SPRITE_SHEET_COUNT).collect();
     sprite_nums.shuffle(&mut rng);
 
     let tick_duration = Duration::from_secs(1) / TICK_RATE;
+    // Simulated time per tick, differs from tick_duration when running with --time-scale
+    let sim_dt = tick_duration.mul_f32(time_scale);
+
+    // cooldowns outlive matches, so a new match doesn't reset them
+    let mut vote_kicks = VoteKicks::new();
+
+    // --bots play alongside the humans and pick their colours first
+    let bot_config = parsed_flags.bot_difficulty.bot_config();
+    let mut bots: Vec<Bot> = (0..parsed_flags.bots)
+        .map(|index| Bot::new(index, bot_config))
+        .collect();
+    for bot in &bots {
+        let sprite = free_sprite(&sprite_nums, &client_sprites, bot.id);
+        client_sprites.insert(bot.id, sprite);
+    }
+
+    // Counters for --metrics, None keeps the loop free of bookkeeping
+    let mut metrics = parsed_flags.metrics.then(ServerMetrics::new);
+
+    // datagrams that came in while the last match's result was showing, handled by the next one
+    let mut held = VecDeque::new();
 
     loop {
         // Full game loop
-        let mut game_state: GameState;
         _pending_win = None; // Reset pending win for new round
-        if !used_map || parsed_flags.permanent_map {
+        let mut first_blood_taken = false;
+        // What the clients were last sent of each player, and ticks until everyone is sent again
+        let mut last_sent_updates = HashMap::<String, PlayerUpdate>::new();
+        let mut ticks_to_keyframe = 0;
+        // a fresh buffer each match, new spawns would look like instant turns
+        let mut replay = parsed_flags.replay_seconds.map(|seconds| {
+            ReplayBuffer::new(
+                seconds,
+                parsed_flags.suspicious_distance,
+                parsed_flags.suspicious_turn_rate,
+            )
+        });
+        let match_map = if !parsed_flags.rotation.is_empty() {
+            let map = parsed_flags.rotation[rotation_index % parsed_flags.rotation.len()].clone();
+            rotation_index += 1;
+            map
+        } else if !used_map || parsed_flags.permanent_map {
             used_map = true;
-            game_state = GameState::new(Some(current_map.clone()), parsed_flags.rand_map_side);
+            current_map.clone()
+        } else if random_map {
+            flags::MapIdentifier::Random
         } else {
-            if random_map {
-                game_state = GameState::new(
-                    Some(flags::MapIdentifier::Random),
-                    parsed_flags.rand_map_side,
-                );
-            } else {
-                game_state = GameState::new(
-                    Some(flags::MapIdentifier::Id(rng.random_range(1..=3))),
-                    None,
-                );
-            }
+            flags::MapIdentifier::Id(rng.random_range(1..=3))
+        };
+        let mut game_state = GameState::new(Some(match_map.clone()), parsed_flags.rand_map_side);
+        *shown_map.lock().unwrap() = match_map.label();
+        // hits of the last match mean nothing in this one, only its result is still on its way
+        OUTBOX.with_borrow_mut(Outbox::clear);
+
+        // With --watch, edits to this match's map file are loaded while the match runs
+        let watched_map = if parsed_flags.watch {
+            match_map.file_path()
+        } else {
+            None
+        };
+        let mut watched_modified = watched_map.as_deref().and_then(utils::file_modified);
+        let mut last_watch_check = Instant::now();
+
+        if parsed_flags.double_jump {
+            game_state.max_air_jumps = DOUBLE_JUMP_AIR_JUMPS;
+        }
+        game_state.bhop = parsed_flags.bhop;
+        game_state.respawn_delay = parsed_flags.mode.respawn_delay();
+        game_state.spawn_protection = parsed_flags.spawn_protection.unwrap_or(Duration::ZERO);
+
+        for bot in &bots {
+            let mut new_player =
+                Player::new(client_sprites[&bot.id].to_string(), &game_state.world);
+            new_player.move_speed *= bot.config.move_speed;
+            game_state.players.insert(bot.id.to_string(), new_player);
+            game_state
+                .leaderboard
+                .insert(bot.name.clone(), Score::default());
         }
 
         // Re-add all currently connected players to the new game
         for (_, (id, username, _)) in clients.iter() {
-            let new_player = Player::new(
-                sprite_nums[(*id % 10) as usize].to_string(),
-                &game_state.world,
-            );
+            let new_player = Player::new(client_sprites[id].to_string(), &game_state.world);
             game_state.players.insert(id.to_string(), new_player);
-            game_state.leaderboard.insert(username.clone(), 0);
+            game_state
+                .leaderboard
+                .insert(username.clone(), Score::default());
 
             // Send initial state to reconnected players
             let mut stripped_state = game_state.clone();
             stripped_state.players = HashMap::new();
-            //let initial_state = ServerMessage::InitialState(game_state.clone());
-            let initial_state = ServerMessage::InitialState(stripped_state);
+            //let initial_state = ServerMessage::InitialState(Box::new(game_state.clone()));
+            let initial_state = ServerMessage::InitialState(Box::new(stripped_state));
             utils::broadcast_message(initial_state, &socket, Some(&clients), None)?;
         }
 
+        // With --warmup each match starts with practice time that doesn't score
+        let mut warmup_until = parsed_flags
+            .warmup
+            .map(|duration| Instant::now() + duration);
+        if let Some(duration) = parsed_flags.warmup
+            && !clients.is_empty()
+        {
+            utils::broadcast_message(
+                ServerMessage::Warmup(Some(duration)),
+                &socket,
+                Some(&clients),
+                None,
+            )?;
+        }
+        // With --time-limit the clock runs once the match is live
+        let mut round_ends = None;
+        let mut round_time_sent = Instant::now();
+        let mut sudden_death = false;
+        if warmup_until.is_none() {
+            round_ends = start_round_clock(parsed_flags.time_limit, &socket, &clients)?;
+        }
+
         let mut last_tick = Instant::now();
-        let mut buf = [0; 1024];
+        let match_start = Instant::now();
+        // a datagram that ended an idle wait, handled before the rest
+        let mut waiting = None;
+        // ids of players and bots that fired, handled after the incoming messages
+        let mut shots: Vec<u64> = Vec::new();
+        // (shooter id, target id, damage, kind) for shots that hit, scored after the incoming messages
+        let mut landed_hits: Vec<(u64, u64, u16, HitKind)> = Vec::new();
+        // when each open door shuts by itself
+        let mut door_close_at = HashMap::<(usize, usize), Instant>::new();
 
         'match_loop: loop {
-            // Handle incoming messages
-            loop {
-                match socket.recv_from(&mut buf) {
-                    Ok((amt, src)) => {
-                        let client_message: ClientMessage =
-                            bincode::deserialize(&buf[..amt]).unwrap();
-
-                        if let Some((_, _, last_seen)) = clients.get_mut(&src) {
-                            *last_seen = Instant::now();
+            if let Some(path) = &watched_map
+                && last_watch_check.elapsed() >= MAP_WATCH_INTERVAL
+            {
+                last_watch_check = Instant::now();
+                let modified = utils::file_modified(path);
+                if modified != watched_modified {
+                    watched_modified = modified;
+                    match World::try_parse_from_file(path).and_then(|world| {
+                        world.validate()?;
+                        Ok(world)
+                    }) {
+                        Ok(world) => {
+                            println!("Reloaded map {}", path);
+                            game_state.replace_world(world);
+                            door_close_at.clear();
+                            utils::broadcast_message(
+                                ServerMessage::InitialState(Box::new(game_state.clone())),
+                                &socket,
+                                Some(&clients),
+                                None,
+                            )?;
                         }
+                        Err(e) => eprintln!("Keeping the old map, reload failed: {}", e),
+                    }
+                }
+            }
+
+            if let Some(metrics) = &mut metrics
+                && metrics.due()
+            {
+                if parsed_flags.arenas > 1 {
+                    println!("arena={} {}", index + 1, metrics.report(&clients));
+                } else {
+                    println!("{}", metrics.report(&clients));
+                }
+            }
 
-                        match client_message {
-                            ClientMessage::Connect(username) => {
-                                if !clients.contains_key(&src) {
-                                    if clients.values().any(|(_, name, _)| {
-                                        name.to_lowercase() == username.to_lowercase()
-                                    }) {
-                                        println!(
-                                            "Rejected connection from {} — username '{}' is already in use.",
-                                            src, username
-                                        );
-
-                                        let rejection = ServerMessage::UsernameRejected(
-                                            "Username already in use".to_string(),
-                                        );
-                                        utils::broadcast_message(
-                                            rejection,
-                                            &socket,
-                                            None,
-                                            Some(src),
-                                        )?;
-                                    } else if username.is_empty() {
-                                        println!(
-                                            "Rejected connection from {} — username '{}' is empty.",
-                                            src, username
-                                        );
-
-                                        let rejection = ServerMessage::UsernameRejected(
-                                            "Empty username".to_string(),
-                                        );
-                                        utils::broadcast_message(
-                                            rejection,
-                                            &socket,
-                                            None,
-                                            Some(src),
-                                        )?;
-                                    } else {
-                                        println!(
-                                            "New client connected: {} (username: {})",
-                                            src, username
-                                        );
-                                        clients.insert(
-                                            src,
-                                            (next_id, username.clone(), Instant::now()),
-                                        );
-
-                                        let welcome = Welcome { id: next_id };
-                                        utils::broadcast_message(
-                                            ServerMessage::Welcome(welcome),
-                                            &socket,
-                                            None,
-                                            Some(src),
-                                        )?;
-
-                                        let new_player = Player::new(
-                                            sprite_nums[(next_id % 10) as usize].to_string(),
-                                            &game_state.world,
-                                        );
-                                        game_state.players.insert(next_id.to_string(), new_player);
-                                        game_state.leaderboard.insert(username.clone(), 0);
-                                        client_inputs.insert(next_id, fps::Input::default()); // Initialize with default input
-                                        next_id += 1;
-
-                                        let initial_state =
-                                            ServerMessage::InitialState(game_state.clone());
-                                        let encoded_initial_state =
-                                            bincode::serialize(&initial_state).unwrap();
-                                        socket.send_to(&encoded_initial_state, src)?;
-
-                                        let leaderboard_update = ServerMessage::LeaderboardUpdate(
-                                            game_state
-                                                .leaderboard
-                                                .clone()
-                                                .into_iter()
-                                                .map(|(name, score)| (name, score as usize))
-                                                .collect(),
-                                        );
-                                        utils::broadcast_message(
-                                            leaderboard_update,
-                                            &socket,
-                                            Some(&clients),
-                                            None,
-                                        )?;
-                                    }
+            while let Ok(line) = console.try_recv() {
+                let (command, argument) = line
+                    .split_once(' ')
+                    .map_or((line.as_str(), ""), |(command, argument)| {
+                        (command, argument.trim())
+                    });
+                match command {
+                    "map" => {
+                        let (markers, legend) = utils::player_markers(
+                            &game_state,
+                            clients.values().map(|(id, name, _)| (*id, name.as_str())),
+                        );
+                        if parsed_flags.arenas > 1 {
+                            println!("Arena {}:", index + 1);
+                        }
+                        print!("{}{}", game_state.world.to_ascii(&markers), legend);
+                    }
+                    "start" => {
+                        if warmup_until.is_some() {
+                            warmup_until = Some(Instant::now());
+                        } else {
+                            println!("The match is already live");
+                        }
+                    }
+                    "list" => {
+                        if parsed_flags.arenas > 1 {
+                            println!("Arena {}:", index + 1);
+                        }
+                        let mut listed: Vec<_> = clients.iter().collect();
+                        listed.sort_by_key(|(_, (id, _, _))| *id);
+                        for (addr, (id, name, _)) in &listed {
+                            println!("{:>4}  {:<16} {}", id, name, addr);
+                        }
+                        if listed.is_empty() {
+                            println!("No players");
+                        }
+                    }
+                    "kick" => match utils::client_named(&clients, argument) {
+                        Some(addr) => {
+                            println!("{} was kicked", argument);
+                            vote_kicks.forget(argument);
+                            kick_client(
+                                addr,
+                                "You were kicked by the server",
+                                &socket,
+                                &mut clients,
+                                &mut game_state,
+                                &mut client_inputs,
+                                &mut client_sprites,
+                            )?;
+                            let _ = departures.send(addr);
+                        }
+                        None if parsed_flags.arenas > 1 => {
+                            println!("No player named '{}' in arena {}", argument, index + 1)
+                        }
+                        None => println!("No player named '{}'", argument),
+                    },
+                    _ => println!(
+                        "Unknown command '{}'. Commands: map, start, list, kick <name>",
+                        line
+                    ),
+                }
+            }
+
+            // The warmup ends on its timer or the start command, and the match begins from zero
+            if warmup_until.is_some_and(|until| Instant::now() >= until) {
+                warmup_until = None;
+                first_blood_taken = false;
+                for player in game_state.players.values_mut() {
+                    player.kills = 0;
+                    player.deaths = 0;
+                    player.current_streak = 0;
+                }
+                println!("Warmup over, the match is live");
+                utils::broadcast_message(
+                    ServerMessage::Warmup(None),
+                    &socket,
+                    Some(&clients),
+                    None,
+                )?;
+                round_ends = start_round_clock(parsed_flags.time_limit, &socket, &clients)?;
+            }
+
+            if shutdown.load(Ordering::SeqCst) {
+                println!("Shutting down, notifying {} client(s)", clients.len());
+                utils::broadcast_message(
+                    ServerMessage::ServerShutdown,
+                    &socket,
+                    Some(&clients),
+                    None,
+                )?;
+                return Ok(());
+            }
+
+            // Handle incoming messages
+            while let Ok((datagram, src)) = waiting
+                .take()
+                .or_else(|| held.pop_front())
+                .map_or_else(|| packets.try_recv(), Ok)
+            {
+                if let Some(metrics) = &mut metrics {
+                    metrics.packets_in += 1;
+                }
+                let client_message: ClientMessage = bincode::deserialize(&datagram).unwrap();
+
+                if let Some((_, _, last_seen)) = clients.get_mut(&src) {
+                    *last_seen = Instant::now();
+                } else if !matches!(client_message, ClientMessage::Connect(..)) {
+                    // e.g. a client still sending after it timed out, it isn't playing here
+                    let _ = departures.send(src);
+                }
+
+                match client_message {
+                    ClientMessage::Connect(username, password) => {
+                        if !clients.contains_key(&src) {
+                            let name_taken = utils::username_taken(&clients, &username)
+                                || bots
+                                    .iter()
+                                    .any(|bot| bot.name.eq_ignore_ascii_case(&username));
+                            if let Some(required) = &parsed_flags.password
+                                && password.as_ref() != Some(required)
+                            {
+                                let reason = if password.is_some() {
+                                    "Wrong password"
+                                } else {
+                                    "Password required"
+                                };
+                                println!(
+                                    "Rejected connection from {} — {}.",
+                                    src,
+                                    reason.to_lowercase()
+                                );
+
+                                let rejection = ServerMessage::PasswordRejected(reason.to_string());
+                                utils::broadcast_message(rejection, &socket, None, Some(src))?;
+                            } else if parsed_flags
+                                .max_players
+                                .is_some_and(|max_players| clients.len() >= max_players)
+                            {
+                                println!("Rejected connection from {} — server is full.", src);
+
+                                let rejection =
+                                    ServerMessage::UsernameRejected("Server is full".to_string());
+                                utils::broadcast_message(rejection, &socket, None, Some(src))?;
+                            } else if name_taken && !parsed_flags.suffix_names {
+                                println!(
+                                    "Rejected connection from {} — username '{}' is already in use.",
+                                    src, username
+                                );
+
+                                let rejection = ServerMessage::UsernameRejected(
+                                    "Username already in use".to_string(),
+                                );
+                                utils::broadcast_message(rejection, &socket, None, Some(src))?;
+                            } else if username.is_empty() {
+                                println!(
+                                    "Rejected connection from {} — username '{}' is empty.",
+                                    src, username
+                                );
+
+                                let rejection =
+                                    ServerMessage::UsernameRejected("Empty username".to_string());
+                                utils::broadcast_message(rejection, &socket, None, Some(src))?;
+                            } else {
+                                let username = if name_taken {
+                                    utils::suffixed_username(&clients, &username)
+                                } else {
+                                    username
+                                };
+                                println!("New client connected: {} (username: {})", src, username);
+                                clients.insert(src, (next_id, username.clone(), Instant::now()));
+
+                                let welcome = Welcome {
+                                    id: next_id,
+                                    name: username.clone(),
+                                    server_name: parsed_flags.server_name.clone(),
+                                    motd: parsed_flags.motd.clone(),
+                                    spawn_protection: game_state.spawn_protection,
+                                    score_to_win: parsed_flags.score_to_win,
+                                };
+                                utils::broadcast_message(
+                                    ServerMessage::Welcome(welcome),
+                                    &socket,
+                                    None,
+                                    Some(src),
+                                )?;
+
+                                let sprite = free_sprite(&sprite_nums, &client_sprites, next_id);
+                                client_sprites.insert(next_id, sprite);
+                                let new_player = Player::new(sprite.to_string(), &game_state.world);
+                                game_state.players.insert(next_id.to_string(), new_player);
+                                game_state
+                                    .leaderboard
+                                    .insert(username.clone(), Score::default());
+                                client_inputs.insert(next_id, fps::Input::default()); // Initialize with default input
+                                next_id += 1;
+
+                                let initial_state =
+                                    ServerMessage::InitialState(Box::new(game_state.clone()));
+                                utils::broadcast_message(initial_state, &socket, None, Some(src))?;
+
+                                if let Some(until) = warmup_until {
+                                    utils::broadcast_message(
+                                        ServerMessage::Warmup(Some(
+                                            until.saturating_duration_since(Instant::now()),
+                                        )),
+                                        &socket,
+                                        None,
+                                        Some(src),
+                                    )?;
                                 }
-                            }
-                            ClientMessage::Input(input) => {
-                                if let Some((id, _, _)) = clients.get(&src) {
-                                    // Process shoot=true immediately since mouse_pressed is only true for one frame, causing
-                                    // a later Input { shoot: false } to overwrite it in client_inputs before the tick processes it.
-                                    if input.shoot {
-                                        game_state.update(id.to_string(), &input, tick_duration);
-                                    }
-                                    client_inputs.insert(*id, input);
+                                // the clock of an empty arena starts over for its first player
+                                if clients.len() == 1 && round_ends.is_some() {
+                                    round_ends =
+                                        parsed_flags.time_limit.map(|limit| Instant::now() + limit);
+                                    sudden_death = false;
                                 }
+                                if let Some(ends) = round_ends {
+                                    utils::broadcast_message(
+                                        ServerMessage::RoundTime(
+                                            ends.saturating_duration_since(Instant::now()),
+                                        ),
+                                        &socket,
+                                        None,
+                                        Some(src),
+                                    )?;
+                                }
+
+                                let leaderboard_update = ServerMessage::LeaderboardUpdate(
+                                    game_state.leaderboard.clone(),
+                                );
+                                utils::broadcast_message(
+                                    leaderboard_update,
+                                    &socket,
+                                    Some(&clients),
+                                    None,
+                                )?;
                             }
-                            ClientMessage::Ping => {
-                                // Ping received, client is alive
+                            // a rejected player may try again, maybe landing in another arena
+                            if !clients.contains_key(&src) {
+                                let _ = departures.send(src);
                             }
-                            ClientMessage::Shot => {
-                                if let Some((shooter_id, shooter_name, _)) = clients.get(&src) {
-                                    let can_shoot = last_shot_timestamp
-                                        .get(shooter_id)
-                                        .map(|last_time| last_time.elapsed() >= SHOOT_COOLDOWN)
-                                        .unwrap_or(true); // First shot is always allowed
-                                    
-                                    if !can_shoot {
-                                        continue;
-                                    }
-                                    
-                                    last_shot_timestamp.insert(*shooter_id, Instant::now());
-                                    
-                                    if let Some(target_id) = game_state.measure_shot(shooter_id) {
-                                        // reduce target hp
-                                        if let Some(target) =
-                                            game_state.players.get_mut(&target_id.to_string())
-                                        {
-                                            if target.take_damage(20) {
-                                                let new_score = utils::update_leaderboard(
-                                                    &mut game_state,
-                                                    shooter_name.clone(),
-                                                    &socket,
-                                                    &clients,
-                                                    None,
-                                                    Some(1),
-                                                    false,
-                                                );
-
-                                                if new_score >= SCORE_TO_WIN {
-                                                    // Don't end game immediately - store pending win
-                                                    // to check after death animation completes
-                                                    _pending_win =
-                                                        Some((shooter_name.clone(), new_score));
-                                                }
-                                            }
-                                        }
-
-                                        // Send message about hit to clients
-                                        let target_name = clients
-                                            .values()
-                                            .find(|(id, _, _)| *id == target_id)
-                                            .unwrap()
-                                            .1
-                                            .clone();
-
-                                        let hit = fps::Hit {
-                                            shooter_id: *shooter_id,
-                                            shooter_name: shooter_name.to_string(),
-                                            target_id,
-                                            target_name,
-                                        };
-                                        let shot_hit_message = ServerMessage::ShotHit(hit);
-                                        utils::broadcast_message(
-                                            shot_hit_message,
-                                            &socket,
-                                            Some(&clients),
-                                            None,
-                                        )?;
-                                    }
+                        }
+                    }
+                    ClientMessage::Input(input) => {
+                        if let Some((id, _, _)) = clients.get(&src) {
+                            // Process shoot=true immediately since mouse_pressed is only true for one frame, causing
+                            // a later Input { shoot: false } to overwrite it in client_inputs before the tick processes it.
+                            if input.shoot {
+                                game_state.update(id.to_string(), &input, sim_dt);
+                            }
+                            client_inputs.insert(*id, input);
+                        }
+                    }
+                    ClientMessage::Reload => {
+                        if let Some((id, _, _)) = clients.get(&src)
+                            && let Some(player) = game_state.players.get_mut(&id.to_string())
+                        {
+                            player.start_reload();
+                        }
+                    }
+                    ClientMessage::SwitchWeapon(slot) => {
+                        if let Some((id, _, _)) = clients.get(&src)
+                            && let Some(weapon) = Weapon::from_slot(slot)
+                            && let Some(player) = game_state.players.get_mut(&id.to_string())
+                        {
+                            player.switch_weapon(weapon);
+                        }
+                    }
+                    ClientMessage::Interact => {
+                        // Opens the nearest door in reach, or shuts it if it's open and clear
+                        let Some((id, _, _)) = clients.get(&src) else {
+                            continue;
+                        };
+                        let Some(door) = game_state
+                            .players
+                            .get(&id.to_string())
+                            .filter(|player| player.health > 0)
+                            .and_then(|player| {
+                                game_state
+                                    .world
+                                    .nearest_door(player.x, player.y, DOOR_USE_RANGE)
+                            })
+                        else {
+                            continue;
+                        };
+                        let tile = (door.x, door.y);
+                        let open = !door.open;
+                        if !open && game_state.tile_occupied(tile.0, tile.1) {
+                            continue;
+                        }
+                        game_state.world.set_door(tile.0, tile.1, open);
+                        if open {
+                            door_close_at.insert(tile, Instant::now() + DOOR_OPEN_TIME);
+                        } else {
+                            door_close_at.remove(&tile);
+                        }
+                        utils::broadcast_message(
+                            ServerMessage::DoorUpdate(tile, open),
+                            &socket,
+                            Some(&clients),
+                            None,
+                        )?;
+                    }
+                    ClientMessage::Ping => {
+                        // Ping received, client is alive
+                    }
+                    ClientMessage::Ack(seq) => {
+                        OUTBOX.with_borrow_mut(|outbox| outbox.acknowledge(src, seq));
+                    }
+                    ClientMessage::VoteKick(target) => {
+                        let Some((_, voter, _)) = clients.get(&src) else {
+                            continue;
+                        };
+                        let names: Vec<&str> =
+                            clients.values().map(|(_, name, _)| name.as_str()).collect();
+                        match vote_kicks.cast(voter, &target, &names) {
+                            Ok(status) => {
+                                let passed = status.passed == Some(true);
+                                utils::broadcast_message(
+                                    ServerMessage::VoteKick(status),
+                                    &socket,
+                                    Some(&clients),
+                                    None,
+                                )?;
+                                if passed && let Some(addr) = utils::client_named(&clients, &target)
+                                {
+                                    println!("{} was kicked by vote", target);
+                                    kick_client(
+                                        addr,
+                                        "You were kicked by a vote",
+                                        &socket,
+                                        &mut clients,
+                                        &mut game_state,
+                                        &mut client_inputs,
+                                        &mut client_sprites,
+                                    )?;
+                                    let _ = departures.send(addr);
                                 }
                             }
+                            Err(reason) => {
+                                utils::broadcast_message(
+                                    ServerMessage::VoteKickRejected(reason),
+                                    &socket,
+                                    None,
+                                    Some(src),
+                                )?;
+                            }
+                        }
+                    }
+                    ClientMessage::Shot => {
+                        if let Some((shooter_id, _, _)) = clients.get(&src) {
+                            shots.push(*shooter_id);
                         }
                     }
-                    Err(ref e) if e.kind() == std::io::ErrorKind::WouldBlock => {
-                        break; // No more messages to read
+                }
+            }
+
+            // Shots from players and bots, all measured against where everyone stands now
+            let mut decals_added = false;
+            for shooter_id in &shots {
+                let weapon = game_state
+                    .players
+                    .get(&shooter_id.to_string())
+                    .map(|shooter| shooter.current_weapon)
+                    .unwrap_or_default();
+                let can_shoot = last_shot_timestamp
+                    .get(shooter_id)
+                    .map(|last_time| last_time.elapsed() >= weapon.cooldown())
+                    .unwrap_or(true); // First shot is always allowed
+
+                if !can_shoot {
+                    continue;
+                }
+
+                // A dead shooter, an empty magazine or a reload keeps the gun quiet
+                if game_state
+                    .players
+                    .get(&shooter_id.to_string())
+                    .is_none_or(|shooter| !shooter.can_fire())
+                {
+                    continue;
+                }
+
+                // With --overheat every shot heats the weapon, except during the warmup
+                if parsed_flags.overheat
+                    && warmup_until.is_none()
+                    && let Some(shooter) = game_state.players.get_mut(&shooter_id.to_string())
+                    && !shooter.add_heat()
+                {
+                    continue;
+                }
+
+                last_shot_timestamp.insert(*shooter_id, Instant::now());
+
+                // firing gives up spawn protection, so it can't be used to camp
+                if let Some(shooter) = game_state.players.get_mut(&shooter_id.to_string()) {
+                    shooter.protect(Duration::ZERO);
+                }
+
+                // the warmup has unlimited ammo
+                if warmup_until.is_none()
+                    && let Some(shooter) = game_state.players.get_mut(&shooter_id.to_string())
+                {
+                    shooter.ammo -= 1;
+                }
+
+                // gunfire is only heard from the living
+                if game_state
+                    .players
+                    .get(&shooter_id.to_string())
+                    .is_some_and(|shooter| shooter.health > 0)
+                {
+                    utils::broadcast_message(
+                        ServerMessage::ShotFired(*shooter_id),
+                        &socket,
+                        Some(&clients),
+                        None,
+                    )?;
+                }
+
+                // With --projectiles the shot flies and lands in a later tick
+                if parsed_flags.projectiles {
+                    game_state.spawn_projectiles(shooter_id);
+                    if let Some(replay) = &mut replay {
+                        replay.record_shot(&game_state, &clients, *shooter_id, None);
                     }
-                    Err(ref e) if e.kind() == std::io::ErrorKind::ConnectionReset => {
-                        // On Windows, we get "connection reset" errors on UDP sockets
-                        // when a client sends an ICMP port unreachable message.
-                        // We can safely ignore these and have a clean terminal.
-                        // Later client will be safely timed out.
+                    continue;
+                }
+
+                // Pellets hitting the same player add up to one hit, each losing
+                // damage with distance. One pellet in the head makes it a headshot.
+                let mut hits: Vec<(u64, u16, HitKind)> = Vec::new();
+                for (target_id, distance, kind) in game_state.measure_shot(shooter_id) {
+                    let shot_damage = parsed_flags.mode.shot_damage(weapon, distance, kind);
+                    match hits.iter_mut().find(|(id, _, _)| *id == target_id) {
+                        Some((_, damage, hit_kind)) => {
+                            *damage = damage.saturating_add(shot_damage);
+                            if kind == HitKind::Head {
+                                *hit_kind = kind;
+                            }
+                        }
+                        None => hits.push((target_id, shot_damage, kind)),
+                    }
+                }
+                let shot_target = hits.first().map(|(id, _, _)| *id);
+                if let Some(replay) = &mut replay {
+                    replay.record_shot(&game_state, &clients, *shooter_id, shot_target);
+                }
+
+                if let Some(tracer) = game_state.shot_tracer(shooter_id, shot_target) {
+                    utils::broadcast_message(
+                        ServerMessage::Tracer(tracer),
+                        &socket,
+                        Some(&clients),
+                        None,
+                    )?;
+                }
+
+                // a miss leaves blood where it hits the wall or floor
+                if shot_target.is_none()
+                    && let Some(impact) = game_state.shot_impact(shooter_id)
+                {
+                    game_state.add_blood(impact);
+                    decals_added = true;
+                }
+
+                for (target_id, damage, kind) in hits {
+                    landed_hits.push((*shooter_id, target_id, damage, kind));
+                }
+            }
+            shots.clear();
+
+            // Hits from this round of shots and from projectiles that landed in the last tick
+            for (shooter_id, target_id, damage, kind) in landed_hits.drain(..) {
+                // and a hit leaves it on the floor under the target, falls don't
+                if shooter_id != target_id
+                    && let Some((x, y)) = game_state.target_position(target_id)
+                {
+                    game_state.add_blood((x, y, 0.0));
+                    decals_added = true;
+                }
+
+                let name_of = |player_id: u64| {
+                    clients
+                        .values()
+                        .find(|(id, _, _)| *id == player_id)
+                        .map(|(_, name, _)| name.clone())
+                        .or_else(|| {
+                            bots.iter()
+                                .find(|bot| bot.id == player_id)
+                                .map(|bot| bot.name.clone())
+                        })
+                };
+                // practice dummies take the damage and score nobody
+                if let Some(index) = game_state.dummy_index(target_id) {
+                    let Some(shooter_name) = name_of(shooter_id) else {
                         continue;
+                    };
+                    let dummy = &mut game_state.dummies[index];
+                    let killed = dummy.take_damage(damage);
+                    let hit = fps::Hit {
+                        shooter_id,
+                        shooter_name,
+                        target_id,
+                        target_name: "Dummy".to_string(),
+                        damage,
+                        kind,
+                        killed,
+                        target_pos: (dummy.x, dummy.y, 0.0),
+                    };
+                    utils::broadcast_message(
+                        ServerMessage::ShotHit(hit),
+                        &socket,
+                        Some(&clients),
+                        None,
+                    )?;
+                    if killed {
+                        utils::broadcast_message(
+                            ServerMessage::DummyUpdate(game_state.dummies.clone()),
+                            &socket,
+                            Some(&clients),
+                            None,
+                        )?;
+                    }
+                    continue;
+                }
+
+                // either one may have left while a projectile was in flight
+                let (Some(shooter_name), Some(target_name)) =
+                    (name_of(shooter_id), name_of(target_id))
+                else {
+                    continue;
+                };
+                let target_pos = game_state
+                    .players
+                    .get(&target_id.to_string())
+                    .map(|target| (target.x, target.y, target.z))
+                    .unwrap_or_default();
+
+                // reduce target hp
+                let mut killed = false;
+                if let Some(target) = game_state.players.get_mut(&target_id.to_string()) {
+                    killed = target.take_damage(damage, game_state.respawn_delay);
+                }
+
+                // kills and deaths during the warmup don't count. A fall is a hit of the player's
+                // own and scores nobody.
+                if killed && warmup_until.is_none() && shooter_id == target_id {
+                    if let Some(score) = game_state.leaderboard.get_mut(&target_name) {
+                        score.deaths += 1;
                     }
-                    Err(e) => {
-                        eprintln!("Couldn't receive a datagram: {}", e);
-                        // Consider what to do with this error, e.g., continue or break
-                        break;
+                    utils::broadcast_message(
+                        ServerMessage::LeaderboardUpdate(game_state.leaderboard.clone()),
+                        &socket,
+                        Some(&clients),
+                        None,
+                    )?;
+                } else if killed && warmup_until.is_none() {
+                    // broadcast along with the shooter's new score below
+                    if let Some(score) = game_state.leaderboard.get_mut(&target_name) {
+                        score.deaths += 1;
+                    }
+                    let streak = game_state
+                        .players
+                        .get_mut(&shooter_id.to_string())
+                        .map_or(0, |shooter| shooter.add_kill());
+                    let first_blood = !first_blood_taken;
+                    first_blood_taken = true;
+
+                    let points = if parsed_flags.kill_bonuses {
+                        if first_blood || utils::streak_title(streak).is_some() {
+                            utils::broadcast_message(
+                                ServerMessage::KillStreak(KillStreak {
+                                    name: shooter_name.clone(),
+                                    streak,
+                                    first_blood,
+                                }),
+                                &socket,
+                                Some(&clients),
+                                None,
+                            )?;
+                        }
+                        utils::kill_points(streak, first_blood)
+                    } else {
+                        1
+                    };
+
+                    let new_score = utils::update_leaderboard(
+                        &mut game_state,
+                        shooter_name.clone(),
+                        &socket,
+                        &clients,
+                        None,
+                        Some(points),
+                        false,
+                    );
+
+                    if new_score >= parsed_flags.score_to_win {
+                        // Don't end game immediately - store pending win
+                        // to check after death animation completes
+                        _pending_win = Some((shooter_name.clone(), new_score));
                     }
                 }
+
+                // Send message about hit to clients
+                let hit = fps::Hit {
+                    shooter_id,
+                    shooter_name: shooter_name.to_string(),
+                    target_id,
+                    target_name,
+                    damage,
+                    kind,
+                    killed,
+                    target_pos,
+                };
+                let shot_hit_message = ServerMessage::ShotHit(hit);
+                utils::broadcast_message(shot_hit_message, &socket, Some(&clients), None)?;
+            }
+
+            if decals_added {
+                game_state.limit_sprites();
+                utils::broadcast_message(
+                    ServerMessage::SpriteUpdate(game_state.floor_sprites.clone()),
+                    &socket,
+                    Some(&clients),
+                    None,
+                )?;
             }
 
             // Remove timed out clients
@@ -298,21 +1277,17 @@ fn main() -> std::io::Result<()> {
             let timeout = Duration::from_secs(5);
             let mut timed_out_clients = Vec::new();
             let clients_clone = clients.clone();
-            clients.retain(|_, (id, username, last_seen)| {
+            clients.retain(|addr, (id, username, last_seen)| {
                 if now.duration_since(*last_seen) > timeout {
                     println!("Client {} ({}) timed out.", id, username);
                     timed_out_clients.push(*id);
+                    let _ = departures.send(*addr);
+                    vote_kicks.forget(username);
 
                     // Remove player from leaderboard
                     game_state.leaderboard.remove(username);
-                    let leaderboard_update = ServerMessage::LeaderboardUpdate(
-                        game_state
-                            .leaderboard
-                            .clone()
-                            .into_iter()
-                            .map(|(name, score)| (name, score as usize))
-                            .collect(),
-                    );
+                    let leaderboard_update =
+                        ServerMessage::LeaderboardUpdate(game_state.leaderboard.clone());
                     utils::broadcast_message(
                         leaderboard_update,
                         &socket,
@@ -330,10 +1305,28 @@ fn main() -> std::io::Result<()> {
             for id in timed_out_clients {
                 game_state.players.remove(&id.to_string());
                 client_inputs.remove(&id);
+                client_sprites.remove(&id);
                 let player_left_message = ServerMessage::PlayerLeft(id);
                 utils::broadcast_message(player_left_message, &socket, Some(&clients), None)?;
             }
 
+            if let Some(status) = vote_kicks.expire(clients.len()) {
+                utils::broadcast_message(
+                    ServerMessage::VoteKick(status),
+                    &socket,
+                    Some(&clients),
+                    None,
+                )?;
+            }
+
+            OUTBOX.with_borrow_mut(|outbox| outbox.resend(&socket))?;
+
+            // Nobody to simulate for: sleep until someone connects
+            if clients.is_empty() {
+                waiting = packets.recv_timeout(IDLE_WAIT_TIMEOUT).ok();
+                continue;
+            }
+
             // Game logic update and broadcast
             let now = Instant::now();
             if now - last_tick >= tick_duration {
@@ -341,13 +1334,89 @@ fn main() -> std::io::Result<()> {
 
                 let mut sprites_changed = false;
 
+                // Bots pick their input for this tick, and their shots go out with the next messages
+                for bot in &mut bots {
+                    let (input, fire) = bot.update_bot(&game_state, &mut rng);
+                    if fire {
+                        shots.push(bot.id);
+                    }
+                    client_inputs.insert(bot.id, input);
+                    if let Some(player) = game_state.players.get_mut(&bot.id.to_string())
+                        && player.ammo == 0
+                    {
+                        player.start_reload();
+                    }
+                }
+
                 // Apply inputs and update game state
+                game_state.update_weapons(sim_dt);
                 for (id, input) in &client_inputs {
-                    if game_state.update(id.to_string(), input, tick_duration) {
+                    if game_state.update(id.to_string(), input, sim_dt) {
                         sprites_changed = true
                     }
                 }
 
+                if parsed_flags.projectiles {
+                    let had_projectiles = !game_state.projectiles.is_empty();
+                    // projectiles landing in walls and the floor leave blood there
+                    let sprite_count = game_state.floor_sprites.len();
+                    for hit in game_state.advance_projectiles(sim_dt) {
+                        let damage =
+                            parsed_flags
+                                .mode
+                                .shot_damage(hit.weapon, hit.distance, hit.kind);
+                        landed_hits.push((hit.owner_id, hit.target_id, damage, hit.kind));
+                    }
+                    if game_state.floor_sprites.len() != sprite_count {
+                        sprites_changed = true;
+                    }
+                    // one last update once they are all gone clears them from the clients
+                    if had_projectiles {
+                        utils::broadcast_message(
+                            ServerMessage::Projectiles(game_state.projectiles.clone()),
+                            &socket,
+                            Some(&clients),
+                            None,
+                        )?;
+                    }
+                }
+
+                // Doors left open shut by themselves, waiting for anyone standing in them to move
+                game_state.world.step_doors(sim_dt.as_secs_f32());
+                let mut closing = Vec::new();
+                door_close_at.retain(|tile, close_at| {
+                    if *close_at <= now && !game_state.tile_occupied(tile.0, tile.1) {
+                        closing.push(*tile);
+                        false
+                    } else {
+                        true
+                    }
+                });
+                for tile in closing {
+                    game_state.world.set_door(tile.0, tile.1, false);
+                    utils::broadcast_message(
+                        ServerMessage::DoorUpdate(tile, false),
+                        &socket,
+                        Some(&clients),
+                        None,
+                    )?;
+                }
+
+                // Health and ammo packs go to whoever walks over them and come back after a while
+                if game_state.update_pickups(sim_dt) {
+                    sprites_changed = true;
+                }
+
+                // Dummies that were shot down stand up again after a while
+                if game_state.update_dummies(sim_dt) {
+                    utils::broadcast_message(
+                        ServerMessage::DummyUpdate(game_state.dummies.clone()),
+                        &socket,
+                        Some(&clients),
+                        None,
+                    )?;
+                }
+
                 // remove puddles if they hit timeout
                 if game_state.limit_sprites() {
                     sprites_changed = true;
@@ -363,25 +1432,68 @@ fn main() -> std::io::Result<()> {
                     )?;
                 }
 
+                // When time runs out the leader wins, a tie plays on until someone pulls ahead
+                if _pending_win.is_none() && round_ends.is_some_and(|ends| Instant::now() >= ends) {
+                    match utils::sole_leader(&game_state.leaderboard) {
+                        Some(leader) => _pending_win = Some(leader),
+                        None if !sudden_death => {
+                            sudden_death = true;
+                            println!("Time is up with a tie, sudden death");
+                            utils::broadcast_message(
+                                ServerMessage::RoundTime(Duration::ZERO),
+                                &socket,
+                                Some(&clients),
+                                None,
+                            )?;
+                        }
+                        None => {}
+                    }
+                }
+
+                // The time left goes out again now and then, so a lost one is soon made good
+                if let Some(ends) = round_ends
+                    && round_time_sent.elapsed() >= ROUND_TIME_INTERVAL
+                    && let Some(remaining) = ends.checked_duration_since(Instant::now())
+                    && !remaining.is_zero()
+                {
+                    round_time_sent = Instant::now();
+                    utils::broadcast_message(
+                        ServerMessage::RoundTime(remaining),
+                        &socket,
+                        Some(&clients),
+                        None,
+                    )?;
+                }
+
                 // Check for pending win after death animations complete
                 if let Some((winner_name, _score)) = &_pending_win {
                     let any_dying = game_state.players.values().any(|p| p.dying);
                     if !any_dying {
                         // All death animations complete, declare winner
-                        utils::set_winner(&mut game_state, winner_name.clone(), &socket, &clients);
-                        std::thread::sleep(WIN_SLEEP_TIME);
+                        utils::set_winner(
+                            &mut game_state,
+                            winner_name.clone(),
+                            match_start.elapsed(),
+                            &socket,
+                            &clients,
+                            &bots,
+                        );
+                        held = show_result(&packets, &socket)?;
                         break 'match_loop;
                     }
                 }
 
                 // Adjust players' z if jumped
-                for player in game_state.players.values_mut() {
-                    player.z += player.velocity_z;
-                    if player.z > 0.0 {
-                        player.velocity_z -= 0.0012;
-                    } else {
-                        player.velocity_z = 0.0;
-                        player.z = 0.0;
+                for (id, player) in game_state.players.iter_mut() {
+                    let landing_speed = player.apply_gravity(&game_state.world, time_scale);
+                    // hard landings hurt, applied with the hits at the start of the next round
+                    let damage = Player::fall_damage(landing_speed);
+                    if damage > 0
+                        && player.health > 0
+                        && !player.protected
+                        && let Ok(id) = id.parse::<u64>()
+                    {
+                        landed_hits.push((id, id, damage, HitKind::Body));
                     }
                 }
 
@@ -401,16 +1513,59 @@ fn main() -> std::io::Result<()> {
                             shooting: player.shooting,
                             health: player.health,
                             score: player.score,
+                            heat: player.heat,
+                            overheated: player.overheated,
+                            protected: player.protected,
+                            ammo: player.ammo,
+                            reserve_ammo: player.reserve_ammo,
+                            reloading: player.reloading,
+                            current_weapon: player.current_weapon,
+                            switching: player.switching,
+                            teleports: player.teleports,
+                            crouch: player.crouch,
+                            stamina: player.stamina,
+                            exhausted: player.exhausted,
                         },
                     );
                 }
 
-                utils::broadcast_message(
-                    ServerMessage::GameUpdate(player_updates),
-                    &socket,
-                    Some(&clients),
-                    None,
-                )?;
+                // Every client is sent the same updates, and joining clients start from the full
+                // game state, so one record of what was sent serves them all. Players that left
+                // drop out of it with the next update.
+                let update_message = if ticks_to_keyframe == 0 {
+                    ticks_to_keyframe = GAME_UPDATE_KEYFRAME_TICKS;
+                    Some(ServerMessage::GameUpdate(player_updates.clone()))
+                } else {
+                    ticks_to_keyframe -= 1;
+                    let changed: HashMap<String, PlayerUpdate> = player_updates
+                        .iter()
+                        .filter(|(id, update)| last_sent_updates.get(*id) != Some(update))
+                        .map(|(id, update)| (id.clone(), update.clone()))
+                        .collect();
+                    (!changed.is_empty()).then_some(ServerMessage::GameDelta(changed))
+                };
+                last_sent_updates = player_updates;
+                if let Some(message) = update_message {
+                    utils::broadcast_message(message, &socket, Some(&clients), None)?;
+                }
+
+                if let Some(replay) = &mut replay
+                    && let Some(reason) = replay.record_tick(&game_state, &clients)
+                {
+                    match replay.dump(&reason) {
+                        Ok(Some(path)) => {
+                            println!("Suspicious: {}, saved {}", reason, path.display())
+                        }
+                        Ok(None) => println!("Suspicious: {}", reason),
+                        Err(e) => {
+                            eprintln!("Suspicious: {}, saving the replay failed: {}", reason, e)
+                        }
+                    }
+                }
+
+                if let Some(metrics) = &mut metrics {
+                    metrics.record_tick(now.elapsed());
+                }
             }
 
             // Sleep for a short duration to prevent busy-waiting, but allow for immediate processing if a message arrives
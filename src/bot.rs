@@ -0,0 +1,215 @@
+use rand::Rng;
+use std::f32::consts::{FRAC_PI_2, PI, TAU};
+
+use crate::Input;
+use crate::consts::{
+    BOT_ID_START, BOT_PATH_FACING, BOT_PATH_TICKS, BOT_SIGHT_RANGE, BOT_WALL_LOOKAHEAD,
+    BOT_WANDER_TICKS, BOT_WANDER_TURN, BOT_WAYPOINT_REACHED, TICK_RATE,
+};
+use crate::gamestate::GameState;
+use crate::player::Player;
+
+/// Bots take the ids from BOT_ID_START up, far above any client's
+pub fn is_bot(id: u64) -> bool {
+    id >= BOT_ID_START
+}
+
+fn tile_of(player: &Player) -> (usize, usize) {
+    (player.x as usize, player.y as usize)
+}
+
+/// Radians a player has to turn to face a point, negative to the left
+fn angle_to(me: &Player, (x, y): (f32, f32)) -> f32 {
+    ((y - me.y).atan2(x - me.x) - me.angle + PI).rem_euclid(TAU) - PI
+}
+
+/// How a bot plays, picked with --bot-difficulty
+#[derive(Debug, Clone, Copy)]
+pub struct BotConfig {
+    pub reaction_ticks: u32, // ticks a player has to be in sight before the bot fires
+    pub turn_speed: f32,     // most Input::turn the bot uses
+    pub aim_error: f32,      // most radians its aim is jittered by, new for every shot
+    pub aim_tolerance: f32,  // tiles beside its aim point at the target it still fires at
+    pub move_speed: f32,     // times the normal player speed
+    pub shot_spacing: f32,   // times the weapon cooldown between its shots
+}
+
+/// A server-side player for --bots. It wanders the map and shoots at the humans it can see.
+pub struct Bot {
+    pub id: u64,
+    pub name: String,
+    pub config: BotConfig,
+    wander_turn: f32, // Input::turn while wandering
+    wander_ticks: u32,
+    avoid_turn: f32, // Input::turn while facing a wall, kept until clear of it for a while
+    aim_error: f32,  // radians its aim is off the target, new for every shot
+    sighted_ticks: u32, // how long the target has been in sight
+    shot_wait: u32,  // ticks until it fires again
+    hunted: Option<(String, (usize, usize))>, // player the path leads to and their tile then
+    path: Vec<(f32, f32)>, // tile centers still ahead on the way to them
+    path_ticks: u32,
+}
+
+impl Bot {
+    pub fn new(index: usize, config: BotConfig) -> Self {
+        Bot {
+            id: BOT_ID_START + index as u64,
+            name: format!("Bot{}", index + 1),
+            config,
+            wander_turn: 0.0,
+            wander_ticks: 0,
+            avoid_turn: 0.0,
+            aim_error: 0.0,
+            sighted_ticks: 0,
+            shot_wait: 0,
+            hunted: None,
+            path: Vec::new(),
+            path_ticks: 0,
+        }
+    }
+
+    /// Picks this tick's input and whether to fire. Turns to the nearest human in sight and fires
+    /// once it has had them in sight for its reaction time and is facing them, otherwise follows a
+    /// path to the nearest one it can reach. With nobody to reach it walks on and turns away from
+    /// walls ahead.
+    pub fn update_bot(&mut self, game_state: &GameState, rng: &mut impl Rng) -> (Input, bool) {
+        let mut input = Input::default();
+        self.shot_wait = self.shot_wait.saturating_sub(1);
+        let Some(me) = game_state.players.get(&self.id.to_string()) else {
+            return (input, false);
+        };
+        if me.health == 0 {
+            return (input, false);
+        }
+
+        let target = game_state
+            .players
+            .iter()
+            .filter(|(id, player)| {
+                id.parse().is_ok_and(|id| !is_bot(id)) && player.health > 0 && !player.protected
+            })
+            .map(|(_, player)| (player, (player.x - me.x).hypot(player.y - me.y)))
+            .filter(|(player, distance)| {
+                *distance <= BOT_SIGHT_RANGE
+                    && game_state
+                        .world
+                        .walls_between((me.x, me.y), (player.x, player.y))
+                        == 0
+            })
+            .min_by(|a, b| a.1.total_cmp(&b.1));
+
+        let Some((player, distance)) = target else {
+            self.sighted_ticks = 0;
+            return self.walk(me, game_state, rng);
+        };
+        self.sighted_ticks = self.sighted_ticks.saturating_add(1);
+
+        // the jitter turns the whole aim, so the shot ray leaves off by it. The check measures how
+        // far beside the aim point the bot's facing passes at the target's distance.
+        let off = angle_to(me, (player.x, player.y)) + self.aim_error;
+        input.turn = self.turn(me, off);
+        let fire = self.sighted_ticks > self.config.reaction_ticks
+            && self.shot_wait == 0
+            && off.abs() < FRAC_PI_2
+            && distance * off.sin().abs() < self.config.aim_tolerance
+            && me.can_fire();
+        if fire {
+            let aim_error = self.config.aim_error;
+            self.aim_error = rng.random_range(-aim_error..=aim_error);
+            let spacing = me.current_weapon.cooldown().as_secs_f32() * self.config.shot_spacing;
+            self.shot_wait = (spacing * TICK_RATE as f32).ceil() as u32;
+        }
+        input.shoot = fire;
+        (input, fire)
+    }
+
+    /// Input::turn towards an angle off the bot's facing, as fast as its difficulty lets it
+    fn turn(&self, me: &Player, off: f32) -> f32 {
+        (off / me.rot_speed).clamp(-self.config.turn_speed, self.config.turn_speed)
+    }
+
+    /// Input for a bot with nobody in sight: follows a path to the nearest human it can reach,
+    /// with nobody to reach it walks on and turns away from walls ahead
+    fn walk(&mut self, me: &Player, game_state: &GameState, rng: &mut impl Rng) -> (Input, bool) {
+        let mut input = Input::default();
+        if let Some(waypoint) = self.hunt(me, game_state) {
+            let off = angle_to(me, waypoint);
+            input.turn = self.turn(me, off);
+            input.forth = off.abs() < BOT_PATH_FACING;
+            return (input, false);
+        }
+
+        // a wall ahead turns the bot until the way is clear, then it walks straight away from it.
+        // Otherwise it drifts a little, picking a new drift every BOT_WANDER_TICKS.
+        let ahead_x = me.x + me.angle.cos() * BOT_WALL_LOOKAHEAD;
+        let ahead_y = me.y + me.angle.sin() * BOT_WALL_LOOKAHEAD;
+        let blocked = ahead_x < 0.0
+            || ahead_y < 0.0
+            || game_state
+                .world
+                .is_solid(ahead_x as usize, ahead_y as usize);
+        if blocked {
+            // one way per wall, turning back and forth would keep the bot stuck in corners
+            if self.avoid_turn == 0.0 {
+                self.avoid_turn = if rng.random_bool(0.5) {
+                    self.config.turn_speed
+                } else {
+                    -self.config.turn_speed
+                };
+            }
+            self.wander_turn = 0.0;
+            self.wander_ticks = BOT_WANDER_TICKS;
+            input.turn = self.avoid_turn;
+        } else {
+            if self.wander_ticks == 0 {
+                self.wander_turn = rng.random_range(-BOT_WANDER_TURN..=BOT_WANDER_TURN);
+                self.wander_ticks = BOT_WANDER_TICKS;
+                self.avoid_turn = 0.0;
+            } else {
+                self.wander_ticks -= 1;
+            }
+            input.turn = self.wander_turn;
+        }
+        input.forth = !blocked;
+        (input, false)
+    }
+
+    /// Next tile center on the way to the nearest living human, None if nobody can be reached.
+    /// The path is searched again every BOT_PATH_TICKS and whenever the hunted player moves to
+    /// another tile or dies.
+    fn hunt(&mut self, me: &Player, game_state: &GameState) -> Option<(f32, f32)> {
+        let lost = self.hunted.as_ref().is_some_and(|(id, tile)| {
+            game_state
+                .players
+                .get(id)
+                .is_none_or(|player| player.health == 0 || tile_of(player) != *tile)
+        });
+        if self.path_ticks == 0 || lost {
+            let mut humans: Vec<_> = game_state
+                .players
+                .iter()
+                .filter(|(id, player)| id.parse().is_ok_and(|id| !is_bot(id)) && player.health > 0)
+                .map(|(id, player)| (id, player, (player.x - me.x).hypot(player.y - me.y)))
+                .collect();
+            humans.sort_by(|a, b| a.2.total_cmp(&b.2));
+            (self.hunted, self.path) = humans
+                .into_iter()
+                .find_map(|(id, player, _)| {
+                    let tile = tile_of(player);
+                    let path = game_state.world.find_path(tile_of(me), tile)?;
+                    Some((Some((id.clone(), tile)), path))
+                })
+                .unwrap_or_default();
+            self.path_ticks = BOT_PATH_TICKS;
+        } else {
+            self.path_ticks -= 1;
+        }
+
+        while let Some(&(x, y)) = self.path.first()
+            && (x - me.x).hypot(y - me.y) < BOT_WAYPOINT_REACHED
+        {
+            self.path.remove(0);
+        }
+        self.path.first().copied()
+    }
+}
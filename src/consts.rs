@@ -5,58 +5,194 @@ use image::Rgba;
 // Window & Display
 pub const WIDTH: usize = 1024;
 pub const HEIGHT: usize = 768;
+pub const DEFAULT_FULLSCREEN: bool = false; // borderless, the frame is scaled up to fill the screen
+// Share of WIDTH x HEIGHT the world is raycast at before being stretched, picked in the menu
+pub const RENDER_SCALES: [f32; 3] = [0.5, 0.75, 1.0];
+pub const DEFAULT_RENDER_SCALE: f32 = 1.0;
 
 // Network
 pub const PORT: u16 = 8080;
+pub const IDLE_WAIT_TIMEOUT: Duration = Duration::from_secs(1);
+pub const ROUTER_POLL_INTERVAL: Duration = Duration::from_millis(100); // console and Ctrl-C checks between datagrams
+// Servers broadcast a beacon every BEACON_INTERVAL to DISCOVERY_PORT, where scanning clients listen
+pub const DISCOVERY_PORT: u16 = 8070;
+pub const BEACON_INTERVAL: Duration = Duration::from_secs(1);
+pub const DISCOVERY_SCAN_TIME: Duration = Duration::from_millis(1500);
+// Per client flood protection, clients send an input every frame the input changes
+pub const DEFAULT_RATE_LIMIT: u32 = 1000; // messages per second
+pub const RATE_LIMIT_WINDOW: Duration = Duration::from_secs(1);
+pub const RATE_LIMIT_IGNORE_FACTOR: f32 = 5.0; // clients this many times over the limit are ignored
+pub const RATE_LIMIT_IGNORE_TIME: Duration = Duration::from_secs(10);
+// Hits, leaderboard changes, leaving players and the winner are resent until the client
+// acknowledges them
+pub const RELIABLE_RESEND_INTERVAL: Duration = Duration::from_millis(100);
+pub const RELIABLE_TIMEOUT: Duration = Duration::from_secs(3); // gives up on a client after this
+pub const RELIABLE_SEEN_CAPACITY: usize = 1024; // sequence numbers a client remembers handling
+// With --time-limit the time left is sent this often, unlike the critical messages it isn't resent
+pub const ROUND_TIME_INTERVAL: Duration = Duration::from_secs(1);
 
 // Assets
 pub const FONT_PATH: &str = "assets/VT323-Regular.ttf";
 
 // Game Rules & Timing
 pub const TICK_RATE: u32 = 100;
-pub const SCORE_TO_WIN: usize = 2;
+// Ticks between full game updates, the ones between only carry players that changed. A lost
+// update is made good by the next full one at the latest.
+pub const GAME_UPDATE_KEYFRAME_TICKS: u32 = 50;
+// Largest UDP payload. The state sent on joining and the decal updates outgrow smaller buffers,
+// and a datagram cut short by the buffer can't be decoded.
+pub const MAX_DATAGRAM_SIZE: usize = 65_507;
+pub const DEFAULT_TIME_SCALE: f32 = 1.0;
+pub const DEFAULT_SCORE_TO_WIN: usize = 2;
 pub const WIN_SLEEP_TIME: Duration = Duration::from_secs(5);
 pub const RESPAWN_DELAY: Duration = Duration::from_secs(4);
+pub const PLAYER_MAX_HEALTH: u16 = 100;
 
 // Input & Mouse
 pub const MOUSE_SPEED: f32 = 0.06;
 pub const MOUSE_SENSITIVITY_MIN: f32 = 0.02;
 pub const MOUSE_SENSITIVITY_MAX: f32 = 0.20;
+pub const DEFAULT_TOGGLE_SPRINT: bool = false;
+pub const DEFAULT_INVERT_Y: bool = false;
+// Per-axis multipliers on top of mouse sensitivity
+pub const DEFAULT_AXIS_SENSITIVITY: f32 = 1.0;
+pub const AXIS_SENSITIVITY_MIN: f32 = 0.5;
+pub const AXIS_SENSITIVITY_MAX: f32 = 2.0;
+pub const AXIS_SENSITIVITY_STEP: f32 = 0.1;
+// Controller look speed, a fully pushed right stick turns this many times as fast as the arrow keys
+pub const DEFAULT_GAMEPAD_SENSITIVITY: f32 = 1.5;
+pub const GAMEPAD_SENSITIVITY_MIN: f32 = 0.5;
+pub const GAMEPAD_SENSITIVITY_MAX: f32 = 3.0;
+pub const GAMEPAD_SENSITIVITY_STEP: f32 = 0.1;
+// Stick deflection ignored around the center so a worn stick doesn't drift
+pub const GAMEPAD_DEAD_ZONE: f32 = 0.2;
+// Left stick deflection past the dead zone along an axis that counts as holding a movement key
+pub const GAMEPAD_MOVE_THRESHOLD: f32 = 0.4;
 
 // Map
 pub const DEFAULT_MAP_ID: usize = 1;
 pub const DEFAULT_MAP_SIDE: usize = 14;
 pub const DEFAULT_MAP_INCLUDE_CORNERS: bool = false;
+// Doors: the tile value in map files, how long one takes to slide open or shut, how close a player
+// has to be to use one and how long it stays open before shutting by itself
+pub const DOOR_TILE: u8 = 9;
+pub const DOOR_SLIDE_TIME: f32 = 0.6; // seconds
+pub const DOOR_USE_RANGE: f32 = 1.5; // tiles from the door's center
+pub const DOOR_OPEN_TIME: Duration = Duration::from_secs(5);
+// Health and ammo packs: how close a player has to walk to pick one up, what each gives and how
+// long until a taken pack comes back
+pub const PICKUP_RADIUS: f32 = 0.5;
+pub const HEALTH_PACK_HEAL: u16 = 25;
+pub const HEALTH_PACK_RESPAWN: Duration = Duration::from_secs(20);
+pub const AMMO_PACK_ROUNDS: u16 = 24;
+pub const AMMO_PACK_RESPAWN: Duration = Duration::from_secs(15);
+// Practice dummies placed by maps, hit with ids above the bots' and back up a while after going down
+pub const DUMMY_ID_START: u64 = 2_000_000;
+pub const DUMMY_HEALTH: u16 = 100;
+pub const DUMMY_RESPAWN: Duration = Duration::from_secs(5);
+// How long after a teleport the player can't be sent through another one
+pub const TELEPORT_COOLDOWN: Duration = Duration::from_secs(1);
 pub const DEFAULT_RANDOM_MAP_PATH_DEVIATION_CHANCE: usize = 60;
 pub const DEFAULT_RANDOM_MAP_HOLE_CHANCE: usize = 10;
+pub const MAP_WATCH_INTERVAL: Duration = Duration::from_secs(1); // how often --watch checks the map file
+pub const MOTD_MAX_CHARS: usize = 80; // longer messages of the day are cut
+pub const MOTD_DURATION: Duration = Duration::from_secs(5); // how long the client shows it on join
+// Anti-cheat replay buffer (--replay-buffer)
+pub const REPLAY_DIR: &str = "replays";
+pub const REPLAY_DUMP_COOLDOWN: Duration = Duration::from_secs(10); // at most one dump this often
+pub const DEFAULT_SUSPICIOUS_HIT_DISTANCE: f32 = 12.0; // tiles, shots reach about 14
+pub const DEFAULT_SUSPICIOUS_TURN_RATE: f32 = 60.0; // radians per second
+pub const METRICS_INTERVAL: Duration = Duration::from_secs(5); // how often --metrics prints a report
 
 // Camera
 pub const CAMERA_HEIGHT_OFFSET: f32 = 0.1;
 pub const CAMERA_HEIGHT_OFFSET_DEAD: f32 = -0.4;
-pub const CAMERA_PLANE_SCALE: f32 = 0.66;
+pub const CROUCH_CAMERA_DROP: f32 = 0.25; // how far the camera sinks when fully crouched
+// Horizontal field of view in degrees, set in the menu
+pub const DEFAULT_FOV: f32 = 65.0;
+pub const FOV_MIN: f32 = 60.0;
+pub const FOV_MAX: f32 = 110.0;
+pub const FOV_STEP: f32 = 5.0;
+pub const DEFAULT_CAMERA_BOB: bool = true;
+pub const DEFAULT_CAMERA_BOB_AMPLITUDE: f32 = 0.015;
+pub const DEFAULT_CAMERA_BOB_FREQUENCY: f32 = 1.6; // bobs per tile walked
+// Own feet and shadow at the bottom of the screen when looking down
+pub const DEFAULT_SHOW_BODY: bool = false;
+pub const BODY_SHOW_PITCH: f32 = 0.35; // share of the pitch limit looked down before the feet show
+pub const BODY_FOOT_COLOR: u32 = 0x0032_2C28;
+pub const BODY_FOOT_SIZE: (f32, f32) = (34.0, 64.0); // ellipse radii in pixels
+pub const BODY_FOOT_SPACING: f32 = 56.0; // pixels from the screen center to each foot
+pub const BODY_STRIDE: f32 = 36.0; // pixels a foot swings forward and back while walking
 
 // Player Movement
 pub const DEFAULT_PLAYER_MOVE_SPEED: f32 = 0.035;
 pub const DEFAULT_PLAYER_ROT_SPEED: f32 = 0.03;
 pub const PLAYER_JUMP_VELOCITY: f32 = 0.028;
+pub const GRAVITY: f32 = 0.0012;
+// Landing faster than FALL_DAMAGE_MIN_SPEED hurts. A plain or double jump lands below it.
+pub const FALL_DAMAGE_MIN_SPEED: f32 = 0.042; // tiles per tick
+pub const FALL_DAMAGE_PER_SPEED: f32 = 2500.0; // damage per tile per tick above the minimum
+pub const FALL_DAMAGE_MAX: u16 = 80;
+pub const JUMP_PAD_VELOCITY: f32 = 0.04; // twice the height of a jump, landing just short of fall damage
+pub const PLAYER_MAX_Z: f32 = 1.5; // ceiling for players lifted by a negative gravity zone
+pub const DOUBLE_JUMP_AIR_JUMPS: u8 = 1; // extra jumps allowed mid-air with --double-jump
+pub const BHOP_WINDOW_TICKS: f32 = 10.0; // ticks after landing when a jump still chains with --bhop
+pub const BHOP_SPEED_GAIN: f32 = 0.08; // move speed multiplier added per chained jump
+pub const BHOP_MAX_SPEED: f32 = 1.6; // cap on the chained jump move speed multiplier
 pub const PLAYER_PITCH_LIMIT: f32 = std::f32::consts::PI / 2.5;
 pub const PLAYER_SPRINT_SPEED_MULTIPLIER: f32 = 2.0;
+pub const CROUCH_SPEED_MULTIPLIER: f32 = 0.5;
+// Sprinting uses up stamina, from 1 full to 0. Once empty, sprint is ignored until it refills past
+// STAMINA_RECOVER_THRESHOLD.
+pub const STAMINA_DRAIN_RATE: f32 = 0.25; // per second of sprinting
+pub const STAMINA_REGEN_RATE: f32 = 0.2; // per second when not sprinting
+pub const STAMINA_RECOVER_THRESHOLD: f32 = 0.3;
+pub const CROUCH_RATE: f32 = 0.08; // share of the way into or out of a crouch covered per tick
+pub const FREE_CAMERA_SPEED: f32 = 3.0; // tiles per second, debug builds with --free-camera
 pub const PLAYER_RADIUS: f32 = 0.2;
+pub const WALL_COLLISION_MARGIN: f32 = 0.05; // extra gap between the player and walls
 
 // Animation
 pub const WALK_FRAME_TIME: f32 = 0.05;
+pub const WALK_FRAME_COUNT: usize = 4;
 pub const DIE_FRAME_TIME: f32 = 0.20;
+pub const DIE_FRAME_COUNT: usize = 3;
+// Time from the killing shot until the body lies still
+pub const DEATH_ANIMATION_TIME: Duration =
+    Duration::from_millis((DIE_FRAME_TIME * 1000.0) as u64 * DIE_FRAME_COUNT as u64);
 
 // Rendering Colors
 pub const CEILING_COLOR: u32 = 0x00AA_CCFF;
 pub const FLOOR_COLOR: u32 = 0x0055_5555;
+pub const LOW_GRAVITY_FLOOR_COLOR: u32 = 0x0048_5A78; // gravity zones lighter than normal
+pub const HIGH_GRAVITY_FLOOR_COLOR: u32 = 0x0070_4848; // gravity zones heavier than normal
+pub const TELEPORTER_FLOOR_COLOR: u32 = 0x0068_48A0;
+// Jump pad floors pulse between these two
+pub const JUMP_PAD_FLOOR_COLOR: u32 = 0x0040_7040;
+pub const JUMP_PAD_PULSE_COLOR: u32 = 0x0070_C060;
+pub const JUMP_PAD_PULSE_PERIOD: Duration = Duration::from_millis(800);
 pub const WALL_COLOR_PRIMARY: u32 = 0x008A_7755;
 pub const WALL_COLOR_SECONDARY: u32 = 0x0069_5A41;
+// Textured floor and ceiling instead of the flat colors, off by default as it costs a cast per pixel
+pub const DEFAULT_TEXTURED_FLOOR: bool = false;
+// Distance fog: walls, sprites, floor and ceiling blend towards FOG_COLOR with depth, fully fogged
+// at FOG_MAX_DISTANCE tiles
+pub const DEFAULT_DISTANCE_FOG: bool = false;
+pub const FOG_COLOR: u32 = 0x0098_A0A8;
+pub const FOG_MAX_DISTANCE: f32 = 14.0;
 pub const CYAN_TRANSPARENT: Rgba<u8> = Rgba([0, 255, 255, 255]);
 
 // Rendering Sprites
+pub const SPRITE_SHEET_COUNT: usize = 10; // player colour variations, blob0.png is the base sheet
+pub const SPRITE_SHEET_BASE_PATH: &str = "assets/blob0.png";
 pub const SPRITE_OTHER_PLAYER_WIDTH: f32 = 0.4;
 pub const SPRITE_OTHER_PLAYER_HEIGHT: f32 = 0.7;
+pub const CROUCH_HEIGHT_SCALE: f32 = 0.65; // sprite and hitbox height of a fully crouched player
+// Contact shadow under other players
+pub const SHADOW_RADIUS: f32 = 0.18; // world units
+pub const SHADOW_FLATTEN: f32 = 0.3; // screen height / width of the ellipse
+pub const SHADOW_ALPHA: f32 = 0.45;
+pub const SHADOW_FADE_HEIGHT: f32 = 1.0; // shadow is gone when the player is this high
 pub const SPRITE_NPC_WIDTH: f32 = 0.2;
 pub const SPRITE_NPC_HEIGHT: f32 = 0.7;
 
@@ -67,24 +203,175 @@ pub const MINIMAP_MARGIN: usize = 10;
 pub const MINIMAP_BACKGROUND_COLOR: u32 = 0x0011_1111;
 pub const MINIMAP_WALL_COLOR: u32 = 0x0044_4444;
 pub const MINIMAP_OPEN_SPACE_COLOR: u32 = 0x00AA_AAAA;
+pub const MINIMAP_DOOR_COLOR: u32 = 0x0088_5A2E;
+pub const MINIMAP_TELEPORTER_COLOR: u32 = 0x0090_60D0;
+pub const MINIMAP_JUMP_PAD_COLOR: u32 = 0x0060_B050;
 pub const MINIMAP_GRID_COLOR: u32 = 0x0022_2222;
 pub const MINIMAP_OTHER_PLAYER_COLOR: u32 = 0x00FF_0000;
 pub const MINIMAP_BORDER_COLOR: u32 = 0x00FF_FFFF;
 pub const MINIMAP_PLAYER_DOT_RADIUS: usize = 3;
 pub const MINIMAP_PLAYER_ICON_SIZE: f32 = 12.0;
+pub const DEFAULT_SMOOTH_MINIMAP: bool = true; // false draws aliased lines and dots for low-end machines
+// Fog of war: tiles stay hidden on the minimap until seen from close by
+pub const DEFAULT_FOG_OF_WAR: bool = false;
+pub const DEFAULT_RESET_EXPLORATION_ON_DEATH: bool = false;
+pub const MINIMAP_UNEXPLORED_COLOR: u32 = 0x0008_0808;
+pub const FOG_REVEAL_RADIUS: f32 = 4.0; // tiles
+pub const FOG_REVEAL_RAYS: usize = 48;
+pub const FOG_REVEAL_STEP: f32 = 0.25;
+
+// Frame time overlay (F3)
+pub const FRAME_GRAPH_SAMPLES: usize = 120;
+pub const FRAME_GRAPH_BAR_WIDTH: usize = 2;
+pub const FRAME_GRAPH_HEIGHT: usize = 60;
+pub const FRAME_GRAPH_MAX_MS: f32 = 50.0; // frame time at the top of the graph
+pub const FRAME_GRAPH_TARGET_MS: f32 = 1000.0 / 60.0;
 
 // Gun and combat
 pub const GUN_SCALE: f32 = 1.0;
 pub const GUN_X_OFFSET: usize = 190;
+// Gun sway while walking with the camera bob on, in pixels at walking speed. Sprinting swings it
+// wider in proportion to the speed.
+pub const GUN_BOB_X: f32 = 14.0;
+pub const GUN_BOB_Y: f32 = 10.0;
 pub const CROSSHAIR_SCALE: f32 = 0.5;
 pub const SHOT_TIME: Duration = Duration::from_millis(35);
 pub const SHOOT_COOLDOWN: Duration = Duration::from_millis(150);
+pub const MAGAZINE_SIZE: u16 = 12;
+pub const STARTING_RESERVE_AMMO: u16 = 48; // rounds besides the magazine, refilled on respawn
+pub const MAX_RESERVE_AMMO: u16 = 96; // ammo packs top the reserve up to this
+pub const RELOAD_TIME: Duration = Duration::from_millis(1500);
 pub const SHOT_MAX_DISTANCE: f32 = 200.0;
+pub const SHOT_DAMAGE: u16 = 20;
+// Damage falls off linearly from FALLOFF_START tiles to FALLOFF_MIN of full damage at the weapon's range
+pub const FALLOFF_START: f32 = 3.0;
+pub const FALLOFF_MIN: f32 = 0.4;
+pub const HEADSHOT_FRACTION: f32 = 0.2; // top of the body that counts as the head
+pub const HEADSHOT_MULTIPLIER: f32 = 2.0;
+// Weapons besides the pistol, which uses the shot constants above. Ranges are squared like SHOT_MAX_DISTANCE.
+pub const SHOTGUN_DAMAGE: u16 = 12; // per pellet
+pub const SHOTGUN_PELLETS: usize = 6;
+pub const SHOTGUN_SPREAD: f32 = 0.04; // radians between neighbouring pellets
+pub const SHOTGUN_COOLDOWN: Duration = Duration::from_millis(700);
+pub const SHOTGUN_MAX_DISTANCE: f32 = 64.0;
+pub const RIFLE_DAMAGE: u16 = 45;
+pub const RIFLE_COOLDOWN: Duration = Duration::from_millis(900);
+pub const RIFLE_MAX_DISTANCE: f32 = 2000.0;
+pub const PROJECTILE_SPEED: f32 = 30.0; // tiles per second with --projectiles
+pub const WEAPON_SWITCH_TIME: Duration = Duration::from_millis(400); // can't fire while switching
+// Overheating (--overheat), heat goes from 0.0 to 1.0
+pub const HEAT_PER_SHOT: f32 = 0.2;
+pub const HEAT_COOL_DELAY: Duration = Duration::from_millis(400); // slow cooling this long after a shot
+pub const HEAT_COOL_RATE_FIRING: f32 = 0.15; // per second
+pub const HEAT_COOL_RATE_IDLE: f32 = 0.6; // per second
+pub const OVERHEAT_FLASH_INTERVAL: Duration = Duration::from_millis(150);
+// Spawn protection (--spawn-protection)
+pub const SPAWN_PROTECTION_TINT: [u8; 4] = [120, 200, 255, 36]; // alpha at the peak of the pulse
+pub const SPAWN_PROTECTION_PULSE_SPEED: f32 = 4.0; // radians per second
+pub const SPAWN_PROTECTION_SHIMMER_COLOR: u32 = 0x00A0_DCFF;
+pub const SPAWN_PROTECTION_SHIMMER_STRENGTH: f32 = 0.6;
+pub const SPAWN_PROTECTION_SHIMMER_SPEED: f32 = 6.0; // radians per second
+// Instagib mode (--mode instagib)
+pub const INSTAGIB_SHOT_DAMAGE: u16 = 100; // a full health bar
+pub const INSTAGIB_RESPAWN_DELAY: Duration = Duration::from_secs(1);
+// Kill bonuses (--kill-bonuses)
+pub const FIRST_BLOOD_BONUS: usize = 1;
+pub const KILL_STREAK_STEP: usize = 3; // every this many kills in a row adds a point per kill
+pub const KILL_STREAK_TITLES: [(usize, &str); 4] = [
+    (3, "Killing Spree!"),
+    (5, "Rampage!"),
+    (7, "Dominating!"),
+    (10, "Unstoppable!"),
+];
+pub const ANNOUNCEMENT_DURATION: Duration = Duration::from_secs(2);
+// Bots added with --bots
+pub const BOT_ID_START: u64 = 1_000_000;
+pub const BOT_SIGHT_RANGE: f32 = 12.0; // tiles
+// Bot skill for each --bot-difficulty, easy, normal and hard, see bot::BotConfig
+pub const BOT_REACTION_TICKS: [u32; 3] = [60, 30, 10];
+pub const BOT_TURN_SPEED: [f32; 3] = [0.4, 1.0, 1.5];
+pub const BOT_AIM_ERROR: [f32; 3] = [0.12, 0.06, 0.015]; // radians
+pub const BOT_AIM_TOLERANCE: [f32; 3] = [0.4, 0.25, 0.1]; // tiles
+pub const BOT_MOVE_SPEED: [f32; 3] = [0.7, 0.9, 1.0];
+pub const BOT_SHOT_SPACING: [f32; 3] = [3.0, 1.8, 1.0];
+pub const BOT_WALL_LOOKAHEAD: f32 = 0.8; // tiles ahead a wandering bot checks for walls
+pub const BOT_WANDER_TICKS: u32 = 100;
+pub const BOT_WANDER_TURN: f32 = 0.3;
+pub const BOT_PATH_TICKS: u32 = 50; // ticks between a hunting bot's path searches
+pub const BOT_WAYPOINT_REACHED: f32 = 0.3; // tiles from a waypoint's center that count as there
+pub const BOT_PATH_FACING: f32 = 0.6; // radians off its next waypoint a bot still walks at
+pub const ROUND_CLOCK_WARNING: Duration = Duration::from_secs(30); // the clock turns red for the last stretch
+// Vote kick
+pub const VOTE_KICK_DURATION: Duration = Duration::from_secs(30);
+pub const VOTE_KICK_COOLDOWN: Duration = Duration::from_secs(120); // after a failed vote, per target
+pub const VOTE_KICK_MIN_VOTES: usize = 2;
 pub const HIT_MARKER_DURATION: Duration = Duration::from_millis(400);
+pub const HIT_MARKER_GRAZE_COLOR: u32 = 0x0070_7070; // faint marker for the least damaging hit
+pub const HIT_MARKER_SOLID_COLOR: u32 = 0x00FF_FFFF; // bright marker for a full damage hit
+pub const HIT_MARKER_KILL_COLOR: u32 = 0x00FF_3030;
+pub const HIT_MARKER_HEADSHOT_COLOR: u32 = 0x00FF_C020;
+// Damage numbers floating up from hit players
+pub const DAMAGE_NUMBER_DURATION: Duration = Duration::from_millis(800);
+pub const DAMAGE_NUMBER_RISE: f32 = 0.4; // world units over the whole duration
+pub const DAMAGE_NUMBER_FONT_SIZE: f32 = 28.0;
+pub const DAMAGE_NUMBER_COLOR: [u8; 3] = [255, 230, 120];
+pub const DAMAGE_NUMBER_KILL_COLOR: [u8; 3] = [255, 60, 60];
 pub const DAMAGE_FLASH_DURATION: Duration = Duration::from_millis(50);
+// White flash that fades out after the local player goes through a teleporter
+pub const TELEPORT_FLASH_DURATION: Duration = Duration::from_millis(300);
+// Muzzle flashes light walls and sprites within MUZZLE_FLASH_RADIUS tiles of the shooter for about
+// as long as the shot animation, and the middle of the view for the player's own shots
+pub const MUZZLE_FLASH_DURATION: Duration = Duration::from_millis(60);
+pub const MUZZLE_FLASH_RADIUS: f32 = 3.0;
+pub const MUZZLE_FLASH_STRENGTH: f32 = 0.45; // share of MUZZLE_FLASH_COLOR added at the shooter
+pub const MUZZLE_FLASH_COLOR: u32 = 0x00FF_C878;
+pub const MUZZLE_FLASH_GLOW_SIZE: f32 = 0.35; // radius of the own shot glow, share of the view height
+pub const DEFAULT_SHOW_TRACERS: bool = true;
+// Screen shake, in intensity from 0 to SHAKE_MAX that decays by SHAKE_DECAY per second
+pub const DEFAULT_FIRE_SHAKE: bool = true;
+pub const SHAKE_HIT_INTENSITY: f32 = 0.6;
+pub const SHAKE_FIRE_INTENSITY: f32 = 0.2;
+pub const SHAKE_MAX: f32 = 1.0;
+pub const SHAKE_DECAY: f32 = 8.0; // exponential rate, higher settles faster
+pub const SHAKE_PIXELS: f32 = 6.0; // largest offset at full intensity
+// Walls and sprites fade into the floor and ceiling over this last part of the view distance
+pub const VIEW_DISTANCE_FADE: f32 = 0.3;
+// Other players glide from their drawn state to each update, drawn about one update behind. A
+// glide lasts as long as the gap before its update, kept within these bounds.
+pub const DEFAULT_REMOTE_INTERPOLATION: bool = true;
+pub const REMOTE_INTERPOLATION_MIN: Duration = Duration::from_millis(1000 / TICK_RATE as u64);
+pub const REMOTE_INTERPOLATION_MAX: Duration = Duration::from_millis(100);
+// Moves longer than this (respawns, map changes) snap instead of sliding across the map
+pub const REMOTE_SNAP_DISTANCE: f32 = 2.0;
+// My own player moves at once on input and is eased back towards the server's updates
+pub const DEFAULT_CLIENT_PREDICTION: bool = true;
+pub const PREDICTION_HISTORY: usize = 50; // predicted ticks kept to compare updates against
+pub const PREDICTION_MAX_CATCH_UP: usize = 25; // ticks simulated at most in one frame
+pub const PREDICTION_CORRECTION: f32 = 0.2; // share of the difference to the server fixed per update
+pub const PREDICTION_SNAP_DISTANCE: f32 = 1.0; // further off than this snaps to the server
+pub const TRACER_DURATION: Duration = Duration::from_millis(120);
+pub const TRACER_COLOR: u32 = 0x00FF_EE99;
+pub const TRACER_START_OFFSET: f32 = 0.3;
+pub const TRACER_GUN_DROP: f32 = 0.08;
+pub const TRACER_SEGMENTS: usize = 24;
+pub const PROJECTILE_COLOR: u32 = 0x00FF_C040;
+pub const PROJECTILE_RADIUS: f32 = 0.05; // in world units, scaled by distance on screen
+
+// Audio
+pub const SOUND_SHOT_PATH: &str = "assets/shot.wav";
+pub const SOUND_HIT_PATH: &str = "assets/hit.wav";
+pub const SOUND_DEATH_PATH: &str = "assets/death.wav";
+pub const SOUND_FOOTSTEP_PATH: &str = "assets/footstep.wav";
+pub const FOOTSTEP_OWN_VOLUME: f32 = 0.35;
+pub const FOOTSTEP_OTHER_VOLUME: f32 = 0.7; // before distance and walls
+pub const AUDIO_MAX_RANGE: f32 = 20.0;
+pub const AUDIO_OCCLUSION_VOLUME_PER_WALL: f32 = 0.5;
+pub const AUDIO_OCCLUSION_LOW_PASS_HZ: u32 = 1600;
 
 // Effects
-pub const MAX_PUDDLES: usize = 100;
+pub const MAX_PUDDLES: usize = 100; // blood puddles and decals kept, the oldest go first
+pub const DECAL_SIZE: f32 = 0.12; // blood a shot leaves on a wall, or across on the floor
+pub const DECAL_WALL_OFFSET: f32 = 0.02; // tiles in front of the wall, so it isn't hidden in it
 
 // UI
 pub const CLOSE_MENU_ON_NEW_GAME: bool = true;
\ No newline at end of file
@@ -0,0 +1,122 @@
+use std::cell::Cell;
+use std::collections::{HashMap, VecDeque};
+use std::net::SocketAddr;
+use std::time::{Duration, Instant};
+
+use crate::consts::{FRAME_GRAPH_SAMPLES, METRICS_INTERVAL};
+
+thread_local! {
+    /// Datagrams sent from this thread, counted in utils::broadcast_message. Each arena runs on its
+    /// own thread, so its metrics only count its own traffic.
+    pub static PACKETS_SENT: Cell<u64> = const { Cell::new(0) };
+}
+
+/// Ring buffer of the most recent frame times, in milliseconds
+pub struct FrameTimes {
+    samples: VecDeque<f32>,
+}
+
+impl Default for FrameTimes {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FrameTimes {
+    pub fn new() -> Self {
+        FrameTimes {
+            samples: VecDeque::with_capacity(FRAME_GRAPH_SAMPLES),
+        }
+    }
+
+    pub fn push(&mut self, frame_time: Duration) {
+        if self.samples.len() == FRAME_GRAPH_SAMPLES {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(frame_time.as_secs_f32() * 1000.0);
+    }
+
+    /// Oldest sample first
+    pub fn samples(&self) -> impl Iterator<Item = f32> + '_ {
+        self.samples.iter().copied()
+    }
+
+    /// (min, max, average) in milliseconds, or None before the first frame
+    pub fn stats(&self) -> Option<(f32, f32, f32)> {
+        if self.samples.is_empty() {
+            return None;
+        }
+        let min = self.samples.iter().copied().fold(f32::INFINITY, f32::min);
+        let max = self.samples.iter().copied().fold(0.0, f32::max);
+        let avg = self.samples.iter().sum::<f32>() / self.samples.len() as f32;
+        Some((min, max, avg))
+    }
+}
+
+/// Server counters for --metrics, reset after every report
+pub struct ServerMetrics {
+    pub packets_in: u64,
+    tick_time: Duration,
+    ticks: u32,
+    last_report: Instant,
+}
+
+impl Default for ServerMetrics {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ServerMetrics {
+    pub fn new() -> Self {
+        PACKETS_SENT.set(0);
+        ServerMetrics {
+            packets_in: 0,
+            tick_time: Duration::ZERO,
+            ticks: 0,
+            last_report: Instant::now(),
+        }
+    }
+
+    pub fn record_tick(&mut self, duration: Duration) {
+        self.tick_time += duration;
+        self.ticks += 1;
+    }
+
+    pub fn due(&self) -> bool {
+        self.last_report.elapsed() >= METRICS_INTERVAL
+    }
+
+    /// One key=value line covering the time since the previous report. The server has no round
+    /// trip measurement, so each player's time since their last packet stands in for ping.
+    pub fn report(&mut self, clients: &HashMap<SocketAddr, (u64, String, Instant)>) -> String {
+        let avg_tick_ms = if self.ticks > 0 {
+            self.tick_time.as_secs_f32() * 1000.0 / self.ticks as f32
+        } else {
+            0.0
+        };
+        let mut line = format!(
+            "players={} interval_s={:.1} packets_in={} packets_out={} avg_tick_ms={:.3}",
+            clients.len(),
+            self.last_report.elapsed().as_secs_f32(),
+            self.packets_in,
+            PACKETS_SENT.replace(0),
+            avg_tick_ms,
+        );
+        let mut players: Vec<_> = clients.values().collect();
+        players.sort_by_key(|(id, _, _)| *id);
+        for (_, name, last_seen) in players {
+            line.push_str(&format!(
+                " last_seen_ms.{}={}",
+                name,
+                last_seen.elapsed().as_millis()
+            ));
+        }
+
+        self.packets_in = 0;
+        self.tick_time = Duration::ZERO;
+        self.ticks = 0;
+        self.last_report = Instant::now();
+        line
+    }
+}
@@ -1,18 +1,142 @@
-use crate::consts::DEFAULT_MAP_ID;
+use crate::bot::BotConfig;
+use crate::consts::{
+    BOT_AIM_ERROR, BOT_AIM_TOLERANCE, BOT_MOVE_SPEED, BOT_REACTION_TICKS, BOT_SHOT_SPACING,
+    BOT_TURN_SPEED, DEFAULT_MAP_ID, DEFAULT_RATE_LIMIT, DEFAULT_SCORE_TO_WIN,
+    DEFAULT_SUSPICIOUS_HIT_DISTANCE, DEFAULT_SUSPICIOUS_TURN_RATE, DEFAULT_TIME_SCALE,
+    HEADSHOT_MULTIPLIER, INSTAGIB_RESPAWN_DELAY, INSTAGIB_SHOT_DAMAGE, MOTD_MAX_CHARS, PORT,
+    RESPAWN_DELAY,
+};
+use crate::map::World;
+use crate::{HitKind, Weapon};
+use std::net::IpAddr;
+use std::time::Duration;
 
 #[derive(Debug, Clone)]
 pub enum MapIdentifier {
     Id(usize),
     Name(String),
+    Path(String),
     Random,
 }
 
+impl MapIdentifier {
+    /// The TOML file the map is loaded from, None for generated maps
+    pub fn file_path(&self) -> Option<String> {
+        match self {
+            MapIdentifier::Id(id) => Some(format!("maps/map{}.toml", id)),
+            MapIdentifier::Name(name) => Some(format!("maps/{}.toml", name)),
+            MapIdentifier::Path(path) => Some(path.clone()),
+            MapIdentifier::Random => None,
+        }
+    }
+
+    /// Short name for listings like the LAN beacon
+    pub fn label(&self) -> String {
+        match self {
+            MapIdentifier::Id(id) => format!("map{}", id),
+            MapIdentifier::Name(name) => name.clone(),
+            MapIdentifier::Path(path) => path.clone(),
+            MapIdentifier::Random => "random".to_string(),
+        }
+    }
+}
+
+/// Rule preset picked with --mode
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GameMode {
+    Classic,
+    Instagib,
+}
+
+impl GameMode {
+    /// Damage of one ray from the weapon after flying distance tiles, more for a headshot. Instagib
+    /// kills with anything at any range.
+    pub fn shot_damage(&self, weapon: Weapon, distance: f32, kind: HitKind) -> u16 {
+        match self {
+            GameMode::Classic => {
+                let multiplier = match kind {
+                    HitKind::Body => 1.0,
+                    HitKind::Head => HEADSHOT_MULTIPLIER,
+                };
+                (weapon.damage() as f32 * weapon.falloff(distance) * multiplier).round() as u16
+            }
+            GameMode::Instagib => INSTAGIB_SHOT_DAMAGE,
+        }
+    }
+
+    pub fn respawn_delay(&self) -> Duration {
+        match self {
+            GameMode::Classic => RESPAWN_DELAY,
+            GameMode::Instagib => INSTAGIB_RESPAWN_DELAY,
+        }
+    }
+}
+
+/// How well --bots play, picked with --bot-difficulty
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BotDifficulty {
+    Easy,
+    Normal,
+    Hard,
+}
+
+impl BotDifficulty {
+    pub fn bot_config(&self) -> BotConfig {
+        let level = *self as usize;
+        BotConfig {
+            reaction_ticks: BOT_REACTION_TICKS[level],
+            turn_speed: BOT_TURN_SPEED[level],
+            aim_error: BOT_AIM_ERROR[level],
+            aim_tolerance: BOT_AIM_TOLERANCE[level],
+            move_speed: BOT_MOVE_SPEED[level],
+            shot_spacing: BOT_SHOT_SPACING[level],
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            BotDifficulty::Easy => "easy",
+            BotDifficulty::Normal => "normal",
+            BotDifficulty::Hard => "hard",
+        }
+    }
+}
+
 pub struct Flags {
     pub map: MapIdentifier,
     pub specific_map: bool,
     pub permanent_map: bool,
     pub random_map: bool,
+    pub rotation: Vec<MapIdentifier>, // --maps, played in turn, empty without it
     pub rand_map_side: Option<usize>,
+    pub time_scale: f32,
+    pub suffix_names: bool,
+    pub double_jump: bool,
+    pub bhop: bool,
+    pub projectiles: bool,
+    pub watch: bool,
+    pub kill_bonuses: bool,
+    pub metrics: bool,
+    pub overheat: bool,
+    pub mode: GameMode,
+    pub server_name: Option<String>,
+    pub motd: Option<String>,
+    pub password: Option<String>,
+    pub replay_seconds: Option<f32>,
+    pub suspicious_distance: f32,
+    pub suspicious_turn_rate: f32,
+    pub port: u16,
+    pub bind: Option<IpAddr>,
+    pub spawn_protection: Option<Duration>,
+    pub arenas: usize,
+    pub max_players: Option<usize>, // per arena
+    pub warmup: Option<Duration>,
+    pub time_limit: Option<Duration>,
+    pub rate_limit: u32,
+    pub score_to_win: usize,
+    pub bots: usize, // per arena
+    pub bot_difficulty: BotDifficulty,
+    pub log_rate_limit: bool,
 }
 
 pub fn parse_flags<I>(args: I) -> Option<Flags>
@@ -26,7 +150,36 @@ where
     let mut specific_map = false;
     let mut permanent_map = false;
     let mut random_map = false;
+    let mut rotation = Vec::new();
     let mut rand_map_side = None;
+    let mut time_scale = DEFAULT_TIME_SCALE;
+    let mut suffix_names = false;
+    let mut double_jump = false;
+    let mut bhop = false;
+    let mut projectiles = false;
+    let mut watch = false;
+    let mut kill_bonuses = false;
+    let mut metrics = false;
+    let mut overheat = false;
+    let mut mode = GameMode::Classic;
+    let mut server_name = None;
+    let mut motd = None;
+    let mut password = None;
+    let mut replay_seconds = None;
+    let mut suspicious_distance = DEFAULT_SUSPICIOUS_HIT_DISTANCE;
+    let mut suspicious_turn_rate = DEFAULT_SUSPICIOUS_TURN_RATE;
+    let mut port = PORT;
+    let mut bind = None;
+    let mut spawn_protection = None;
+    let mut arenas = 1;
+    let mut max_players = None;
+    let mut warmup = None;
+    let mut time_limit = None;
+    let mut rate_limit = DEFAULT_RATE_LIMIT;
+    let mut score_to_win = DEFAULT_SCORE_TO_WIN;
+    let mut bots = 0;
+    let mut bot_difficulty = BotDifficulty::Normal;
+    let mut log_rate_limit = false;
     let args: Vec<String> = iter.collect();
     let mut i = 0;
     while i < args.len() {
@@ -34,21 +187,36 @@ where
             "-m" | "--map" => {
                 specific_map = true;
                 if i + 1 < args.len() {
-                    let value = &args[i + 1];
-                    // Try parsing as usize first
-                    if let Ok(id) = value.parse::<usize>() {
-                        if id > 0 {
-                            map = MapIdentifier::Id(id);
-                            i += 2;
-                            continue;
+                    match map_identifier(&args[i + 1]) {
+                        Ok(identifier) => map = identifier,
+                        Err(e) => {
+                            println!("Error: {}", e);
+                            return None;
                         }
                     }
-                    // If parsing as usize fails, treat it as a map name
-                    map = MapIdentifier::Name(value.clone());
                     i += 2;
                     continue;
                 }
             }
+            "-ms" | "--maps" => match args.get(i + 1) {
+                Some(list) if !list.trim().is_empty() => {
+                    for value in list.split(',').map(str::trim).filter(|v| !v.is_empty()) {
+                        match map_identifier(value) {
+                            Ok(identifier) => rotation.push(identifier),
+                            Err(e) => {
+                                println!("Error: {}", e);
+                                return None;
+                            }
+                        }
+                    }
+                    i += 2;
+                    continue;
+                }
+                _ => {
+                    println!("Error: --maps requires a comma separated list of maps");
+                    return None;
+                }
+            },
             "-pm" | "--permanent-map" => {
                 permanent_map = true;
                 i += 1;
@@ -89,6 +257,281 @@ where
                     continue;
                 }
             }
+            "-ts" | "--time-scale" => match args.get(i + 1).and_then(|v| v.parse::<f32>().ok()) {
+                Some(scale) if scale.is_finite() && scale > 0.0 => {
+                    time_scale = scale;
+                    i += 2;
+                    continue;
+                }
+                _ => {
+                    println!("Error: --time-scale requires a positive number");
+                    return None;
+                }
+            },
+            "-sn" | "--suffix-names" => {
+                suffix_names = true;
+                i += 1;
+                continue;
+            }
+            "-dj" | "--double-jump" => {
+                double_jump = true;
+                i += 1;
+                continue;
+            }
+            "-bh" | "--bhop" => {
+                bhop = true;
+                i += 1;
+                continue;
+            }
+            "-pj" | "--projectiles" => {
+                projectiles = true;
+                i += 1;
+                continue;
+            }
+            "-w" | "--watch" => {
+                watch = true;
+                i += 1;
+                continue;
+            }
+            "-kb" | "--kill-bonuses" => {
+                kill_bonuses = true;
+                i += 1;
+                continue;
+            }
+            "-mt" | "--metrics" => {
+                metrics = true;
+                i += 1;
+                continue;
+            }
+            "-oh" | "--overheat" => {
+                overheat = true;
+                i += 1;
+                continue;
+            }
+            "-md" | "--mode" => match args.get(i + 1).map(|v| v.as_str()) {
+                Some("classic") => {
+                    mode = GameMode::Classic;
+                    i += 2;
+                    continue;
+                }
+                Some("instagib") => {
+                    mode = GameMode::Instagib;
+                    i += 2;
+                    continue;
+                }
+                _ => {
+                    println!("Error: --mode requires 'classic' or 'instagib'");
+                    return None;
+                }
+            },
+            "-n" | "--name" => match args.get(i + 1) {
+                Some(name) if !name.trim().is_empty() => {
+                    server_name = Some(name.trim().to_string());
+                    i += 2;
+                    continue;
+                }
+                _ => {
+                    println!("Error: --name requires a server name");
+                    return None;
+                }
+            },
+            "-mo" | "--motd" => match args.get(i + 1).map(std::fs::read_to_string) {
+                Some(Ok(text)) => {
+                    let text = text.trim();
+                    if !text.is_empty() {
+                        motd = Some(truncate_motd(text));
+                    }
+                    i += 2;
+                    continue;
+                }
+                Some(Err(e)) => {
+                    println!("Error: Could not read the message of the day file: {}", e);
+                    return None;
+                }
+                None => {
+                    println!("Error: --motd requires a file path");
+                    return None;
+                }
+            },
+            "-pw" | "--password" => match args.get(i + 1) {
+                Some(text) if !text.trim().is_empty() => {
+                    password = Some(text.trim().to_string());
+                    i += 2;
+                    continue;
+                }
+                _ => {
+                    println!("Error: --password requires a password");
+                    return None;
+                }
+            },
+            "-rb" | "--replay-buffer" => match positive_number(args.get(i + 1)) {
+                Some(seconds) => {
+                    replay_seconds = Some(seconds);
+                    i += 2;
+                    continue;
+                }
+                None => {
+                    println!("Error: --replay-buffer requires a positive number of seconds");
+                    return None;
+                }
+            },
+            "-sd" | "--suspicious-distance" => match positive_number(args.get(i + 1)) {
+                Some(distance) => {
+                    suspicious_distance = distance;
+                    i += 2;
+                    continue;
+                }
+                None => {
+                    println!("Error: --suspicious-distance requires a positive number of tiles");
+                    return None;
+                }
+            },
+            "-st" | "--suspicious-turn" => match positive_number(args.get(i + 1)) {
+                Some(rate) => {
+                    suspicious_turn_rate = rate;
+                    i += 2;
+                    continue;
+                }
+                None => {
+                    println!(
+                        "Error: --suspicious-turn requires a positive number of radians per second"
+                    );
+                    return None;
+                }
+            },
+            "-sp" | "--spawn-protection" => match positive_number(args.get(i + 1)) {
+                Some(seconds) => {
+                    spawn_protection = Some(Duration::from_secs_f32(seconds));
+                    i += 2;
+                    continue;
+                }
+                None => {
+                    println!("Error: --spawn-protection requires a positive number of seconds");
+                    return None;
+                }
+            },
+            "-p" | "--port" => match args.get(i + 1).and_then(|v| v.parse::<u16>().ok()) {
+                Some(number) if number > 0 => {
+                    port = number;
+                    i += 2;
+                    continue;
+                }
+                _ => {
+                    println!("Error: --port requires a port number between 1 and 65535");
+                    return None;
+                }
+            },
+            "-b" | "--bind" => match args.get(i + 1).and_then(|v| v.parse::<IpAddr>().ok()) {
+                Some(address) => {
+                    bind = Some(address);
+                    i += 2;
+                    continue;
+                }
+                None => {
+                    println!("Error: --bind requires an IP address, e.g. 0.0.0.0");
+                    return None;
+                }
+            },
+            "-wu" | "--warmup" => match positive_number(args.get(i + 1)) {
+                Some(seconds) => {
+                    warmup = Some(Duration::from_secs_f32(seconds));
+                    i += 2;
+                    continue;
+                }
+                None => {
+                    println!("Error: --warmup requires a positive number of seconds");
+                    return None;
+                }
+            },
+            "-tl" | "--time-limit" => match positive_number(args.get(i + 1)) {
+                Some(seconds) => {
+                    time_limit = Some(Duration::from_secs_f32(seconds));
+                    i += 2;
+                    continue;
+                }
+                None => {
+                    println!("Error: --time-limit requires a positive number of seconds");
+                    return None;
+                }
+            },
+            "-rl" | "--rate-limit" => match args.get(i + 1).and_then(|v| v.parse::<u32>().ok()) {
+                Some(limit) if limit > 0 => {
+                    rate_limit = limit;
+                    i += 2;
+                    continue;
+                }
+                _ => {
+                    println!(
+                        "Error: --rate-limit requires a positive number of messages per second"
+                    );
+                    return None;
+                }
+            },
+            "-lr" | "--log-rate-limit" => {
+                log_rate_limit = true;
+                i += 1;
+                continue;
+            }
+            "-sw" | "--score-to-win" => match args.get(i + 1).and_then(|v| v.parse::<usize>().ok())
+            {
+                Some(score) if score > 0 => {
+                    score_to_win = score;
+                    i += 2;
+                    continue;
+                }
+                _ => {
+                    println!("Error: --score-to-win requires a number of points, at least 1");
+                    return None;
+                }
+            },
+            "-bt" | "--bots" => match args.get(i + 1).and_then(|v| v.parse::<usize>().ok()) {
+                Some(count) if count > 0 => {
+                    bots = count;
+                    i += 2;
+                    continue;
+                }
+                _ => {
+                    println!("Error: --bots requires a positive number of bots");
+                    return None;
+                }
+            },
+            "-bd" | "--bot-difficulty" => {
+                bot_difficulty = match args.get(i + 1).map(|v| v.as_str()) {
+                    Some("easy") => BotDifficulty::Easy,
+                    Some("normal") => BotDifficulty::Normal,
+                    Some("hard") => BotDifficulty::Hard,
+                    _ => {
+                        println!("Error: --bot-difficulty requires 'easy', 'normal' or 'hard'");
+                        return None;
+                    }
+                };
+                i += 2;
+                continue;
+            }
+            "-a" | "--arenas" => match args.get(i + 1).and_then(|v| v.parse::<usize>().ok()) {
+                Some(count) if count > 0 => {
+                    arenas = count;
+                    i += 2;
+                    continue;
+                }
+                _ => {
+                    println!("Error: --arenas requires a positive number of arenas");
+                    return None;
+                }
+            },
+            "-mp" | "--max-players" => {
+                match args.get(i + 1).and_then(|v| v.parse::<usize>().ok()) {
+                    Some(count) if count > 0 => {
+                        max_players = Some(count);
+                        i += 2;
+                        continue;
+                    }
+                    _ => {
+                        println!("Error: --max-players requires a positive number of players");
+                        return None;
+                    }
+                }
+            }
             _ => {}
         }
         i += 1;
@@ -98,12 +541,76 @@ where
         println!("Error: Cannot use both --random-map and --map at the same time");
         return None;
     }
+    if !rotation.is_empty() && (specific_map || random_map || permanent_map) {
+        println!("Error: --maps can't be combined with --map, --random-map or --permanent-map");
+        return None;
+    }
 
     Some(Flags {
         map,
         specific_map,
         permanent_map,
         random_map,
+        rotation,
         rand_map_side,
+        time_scale,
+        suffix_names,
+        double_jump,
+        bhop,
+        projectiles,
+        watch,
+        kill_bonuses,
+        metrics,
+        overheat,
+        mode,
+        server_name,
+        motd,
+        password,
+        replay_seconds,
+        suspicious_distance,
+        suspicious_turn_rate,
+        port,
+        bind,
+        spawn_protection,
+        arenas,
+        max_players,
+        warmup,
+        time_limit,
+        rate_limit,
+        score_to_win,
+        bots,
+        bot_difficulty,
+        log_rate_limit,
     })
 }
+
+fn positive_number(arg: Option<&String>) -> Option<f32> {
+    arg.and_then(|v| v.parse::<f32>().ok())
+        .filter(|v| v.is_finite() && *v > 0.0)
+}
+
+/// Reads a map given on the command line: a number is a premade map, a file path is loaded from
+/// that path directly, and anything else is a map name
+fn map_identifier(value: &str) -> Result<MapIdentifier, String> {
+    if let Ok(id) = value.parse::<usize>()
+        && id > 0
+    {
+        return Ok(MapIdentifier::Id(id));
+    }
+    if value.contains('/') || value.contains('\\') || value.ends_with(".toml") {
+        World::try_parse_from_file(value)?;
+        return Ok(MapIdentifier::Path(value.to_string()));
+    }
+    Ok(MapIdentifier::Name(value.to_string()))
+}
+
+/// Joins the file's lines and cuts the result to MOTD_MAX_CHARS
+fn truncate_motd(text: &str) -> String {
+    let joined = text.lines().map(str::trim).collect::<Vec<_>>().join(" ");
+    if joined.chars().count() <= MOTD_MAX_CHARS {
+        return joined;
+    }
+    let mut cut: String = joined.chars().take(MOTD_MAX_CHARS - 3).collect();
+    cut.push_str("...");
+    cut
+}
@@ -1,12 +1,27 @@
 use crate::AnimationState;
+use crate::HitKind;
 use crate::Input;
+use crate::Projectile;
+use crate::Score;
 use crate::Sprite;
-use crate::consts::MAX_PUDDLES;
+use crate::Tracer;
+use crate::Weapon;
 use crate::consts::{
-    CAMERA_HEIGHT_OFFSET, SHOT_MAX_DISTANCE, SPRITE_OTHER_PLAYER_HEIGHT, SPRITE_OTHER_PLAYER_WIDTH,
+    AMMO_PACK_RESPAWN, AMMO_PACK_ROUNDS, HEADSHOT_FRACTION, HEALTH_PACK_HEAL, HEALTH_PACK_RESPAWN,
+    MAX_PUDDLES, PICKUP_RADIUS, PROJECTILE_SPEED, TICK_RATE, TRACER_GUN_DROP, TRACER_START_OFFSET,
+};
+use crate::consts::{
+    DECAL_SIZE, DECAL_WALL_OFFSET, DUMMY_HEALTH, DUMMY_ID_START, DUMMY_RESPAWN, SPRITE_NPC_HEIGHT,
+    SPRITE_NPC_WIDTH,
+};
+use crate::consts::{
+    PLAYER_RADIUS, SPRITE_OTHER_PLAYER_HEIGHT, SPRITE_OTHER_PLAYER_WIDTH, WALL_COLLISION_MARGIN,
 };
 use crate::player::Player;
-use crate::{consts::RESPAWN_DELAY, map::World};
+use crate::{
+    consts::RESPAWN_DELAY,
+    map::{PickupSpot, World},
+};
 use serde::{Deserialize, Serialize};
 use std::{collections::HashMap, f32::MAX, time::Duration};
 
@@ -17,7 +32,111 @@ pub struct GameState {
     floor_sprite_id: u32,
     pub floor_sprites: HashMap<u32, Sprite>,
     pub winner: Option<String>,
-    pub leaderboard: HashMap<String, usize>,
+    pub leaderboard: HashMap<String, Score>,
+    pub max_air_jumps: u8,
+    pub bhop: bool,
+    pub respawn_delay: Duration,
+    pub spawn_protection: Duration,
+    pub projectiles: Vec<Projectile>, // only with --projectiles
+    pub pickups: Vec<Pickup>,
+    pub dummies: Vec<Dummy>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum PickupKind {
+    Health,
+    Ammo,
+}
+
+impl PickupKind {
+    fn texture(self) -> &'static str {
+        match self {
+            PickupKind::Health => "healthpack",
+            PickupKind::Ammo => "ammopack",
+        }
+    }
+
+    fn respawn_time(self) -> Duration {
+        match self {
+            PickupKind::Health => HEALTH_PACK_RESPAWN,
+            PickupKind::Ammo => AMMO_PACK_RESPAWN,
+        }
+    }
+
+    /// Gives the pack to a player. Returns false if they have no use for it.
+    fn apply(self, player: &mut Player) -> bool {
+        match self {
+            PickupKind::Health => player.heal(HEALTH_PACK_HEAL),
+            PickupKind::Ammo => player.add_reserve_ammo(AMMO_PACK_ROUNDS),
+        }
+    }
+}
+
+/// A health or ammo pack from the map. While it lies on the floor it has a sprite, once taken it
+/// counts down to coming back.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Pickup {
+    pub kind: PickupKind,
+    pub x: f32,
+    pub y: f32,
+    sprite_id: Option<u32>,
+    respawn_timer: Duration,
+}
+
+/// A practice dummy from the map. It stands still and takes shots without firing back, and once
+/// shot down it stands up again after DUMMY_RESPAWN.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Dummy {
+    pub x: f32,
+    pub y: f32,
+    pub health: u16,
+    respawn_timer: Duration,
+}
+
+impl Dummy {
+    /// The id shots hitting the dummy at this index name as their target
+    pub fn id(index: usize) -> u64 {
+        DUMMY_ID_START + index as u64
+    }
+
+    /// Takes a hit. Returns true if it brought the dummy down.
+    pub fn take_damage(&mut self, damage: u16) -> bool {
+        if self.health == 0 {
+            return false;
+        }
+        self.health = self.health.saturating_sub(damage);
+        if self.health == 0 {
+            self.respawn_timer = DUMMY_RESPAWN;
+        }
+        self.health == 0
+    }
+
+    /// Where a shot passing the dummy at a squared distance from its middle and a height above
+    /// the floor hits it, None if it misses or the dummy is down
+    fn hit_kind(&self, perp_dist_sq: f32, height: f32) -> Option<HitKind> {
+        let radius = SPRITE_NPC_WIDTH * 0.5;
+        if self.health == 0
+            || perp_dist_sq >= radius * radius
+            || height <= 0.0
+            || height >= SPRITE_NPC_HEIGHT
+        {
+            return None;
+        }
+        if height > SPRITE_NPC_HEIGHT * (1.0 - HEADSHOT_FRACTION) {
+            Some(HitKind::Head)
+        } else {
+            Some(HitKind::Body)
+        }
+    }
+}
+
+/// A projectile that flew into a player
+pub struct ProjectileHit {
+    pub owner_id: u64,
+    pub target_id: u64,
+    pub weapon: Weapon,
+    pub distance: f32, // tiles flown
+    pub kind: HitKind,
 }
 
 impl GameState {
@@ -32,51 +151,212 @@ impl GameState {
             Some(crate::flags::MapIdentifier::Name(name)) => {
                 World::new(Some(0), Some(&name), false, None)
             }
+            Some(crate::flags::MapIdentifier::Path(path)) => World::parse_from_file(&path),
             Some(crate::flags::MapIdentifier::Random) => {
                 World::new(None, None, true, rand_side)
             }
             None => World::new(Some(1), None, false, None),
         };
 
-        GameState {
+        let mut game_state = GameState {
             players: HashMap::new(),
             world,
             floor_sprite_id: 0,
             floor_sprites: HashMap::new(),
             winner: None,
             leaderboard: HashMap::new(),
-        }
+            max_air_jumps: 0,
+            bhop: false,
+            respawn_delay: RESPAWN_DELAY,
+            spawn_protection: Duration::ZERO,
+            projectiles: Vec::new(),
+            pickups: Vec::new(),
+            dummies: Vec::new(),
+        };
+        game_state.place_pickups();
+        game_state.place_dummies();
+        game_state
     }
 
     pub fn add_puddle(&mut self, x: f32, y: f32) {
-        let puddle = Sprite {
+        self.add_decal(x, y, -0.0325, 0.3, 0.075);
+    }
+
+    /// Leaves blood where a shot landed, (x, y, height above floor). On the floor it lies flat
+    /// like a small puddle, on a wall it's a splat at the height of the hit.
+    pub fn add_blood(&mut self, (x, y, z): (f32, f32, f32)) {
+        if z <= 0.0 {
+            self.add_decal(x, y, -0.0325, DECAL_SIZE, DECAL_SIZE * 0.25);
+        } else {
+            self.add_decal(x, y, z - DECAL_SIZE * 0.5, DECAL_SIZE, DECAL_SIZE);
+        }
+    }
+
+    /// Blood puddles and decals share one sprite, and limit_sprites keeps the newest MAX_PUDDLES
+    fn add_decal(&mut self, x: f32, y: f32, z: f32, width: f32, height: f32) {
+        let decal = Sprite {
             x,
             y,
-            z: -0.0325,
+            z,
             texture: "puddle".to_string(),
+            width,
+            height,
+        };
+
+        self.floor_sprites.insert(self.floor_sprite_id, decal);
+        self.floor_sprite_id += 1;
+    }
+
+    fn add_pickup_sprite(&mut self, kind: PickupKind, x: f32, y: f32) -> u32 {
+        let pack = Sprite {
+            x,
+            y,
+            z: 0.0,
+            texture: kind.texture().to_string(),
             width: 0.3,
-            height: 0.075,
+            height: 0.2,
         };
 
-        self.floor_sprites.insert(self.floor_sprite_id, puddle);
+        let id = self.floor_sprite_id;
+        self.floor_sprites.insert(id, pack);
         self.floor_sprite_id += 1;
+        id
     }
 
-    pub fn limit_sprites(&mut self) -> bool {
+    /// Lays a pack on every health and ammo spot the map lists
+    fn place_pickups(&mut self) {
+        self.pickups.clear();
+        let spots = [
+            (PickupKind::Health, self.world.health_packs.clone()),
+            (PickupKind::Ammo, self.world.ammo_packs.clone()),
+        ];
+        for (kind, spots) in spots {
+            for PickupSpot { x, y } in spots {
+                let sprite_id = Some(self.add_pickup_sprite(kind, x, y));
+                self.pickups.push(Pickup {
+                    kind,
+                    x,
+                    y,
+                    sprite_id,
+                    respawn_timer: Duration::ZERO,
+                });
+            }
+        }
+    }
+
+    /// Lets living players pick up packs they walk over and have use for, and brings taken packs
+    /// back once their cooldown runs out. Returns true if any sprite changed.
+    pub fn update_pickups(&mut self, dt: Duration) -> bool {
         let mut changed = false;
+        let mut respawned = Vec::new();
 
-        while self.floor_sprites.len() > MAX_PUDDLES {
-            if let Some(min_key) = self.floor_sprites.keys().min().cloned() {
-                self.floor_sprites.remove(&min_key);
+        for (index, pack) in self.pickups.iter_mut().enumerate() {
+            let Some(sprite_id) = pack.sprite_id else {
+                pack.respawn_timer = pack.respawn_timer.saturating_sub(dt);
+                if pack.respawn_timer.is_zero() {
+                    respawned.push(index);
+                }
+                continue;
+            };
+            // a player with full health or ammo walks over it and leaves it for someone else
+            let taken = self.players.values_mut().any(|player| {
+                (player.x - pack.x).hypot(player.y - pack.y) <= PICKUP_RADIUS
+                    && pack.kind.apply(player)
+            });
+            if taken {
+                self.floor_sprites.remove(&sprite_id);
+                pack.sprite_id = None;
+                pack.respawn_timer = pack.kind.respawn_time();
                 changed = true;
-            } else {
-                break;
             }
         }
 
+        for index in respawned {
+            let Pickup { kind, x, y, .. } = self.pickups[index];
+            self.pickups[index].sprite_id = Some(self.add_pickup_sprite(kind, x, y));
+            changed = true;
+        }
+
+        changed
+    }
+
+    /// Stands a dummy on every spot the map lists
+    fn place_dummies(&mut self) {
+        self.dummies = self
+            .world
+            .dummies
+            .iter()
+            .map(|spot| Dummy {
+                x: spot.x,
+                y: spot.y,
+                health: DUMMY_HEALTH,
+                respawn_timer: Duration::ZERO,
+            })
+            .collect();
+    }
+
+    /// Index in dummies of the dummy a hit target id belongs to, None for players
+    pub fn dummy_index(&self, id: u64) -> Option<usize> {
+        let index = id.checked_sub(DUMMY_ID_START)? as usize;
+        (index < self.dummies.len()).then_some(index)
+    }
+
+    /// Where a player or dummy hit target stands
+    pub fn target_position(&self, id: u64) -> Option<(f32, f32)> {
+        match self.dummy_index(id) {
+            Some(index) => Some((self.dummies[index].x, self.dummies[index].y)),
+            None => self
+                .players
+                .get(&id.to_string())
+                .map(|target| (target.x, target.y)),
+        }
+    }
+
+    /// Counts down the dummies that were shot down and stands them up again. Returns true if any
+    /// stood up.
+    pub fn update_dummies(&mut self, dt: Duration) -> bool {
+        let mut changed = false;
+        for dummy in self.dummies.iter_mut().filter(|dummy| dummy.health == 0) {
+            dummy.respawn_timer = dummy.respawn_timer.saturating_sub(dt);
+            if dummy.respawn_timer.is_zero() {
+                dummy.health = DUMMY_HEALTH;
+                changed = true;
+            }
+        }
+        changed
+    }
+
+    pub fn limit_sprites(&mut self) -> bool {
+        let mut changed = false;
+
+        // only puddles and decals count towards the limit, health packs stay put
+        let mut puddles: Vec<u32> = self
+            .floor_sprites
+            .iter()
+            .filter(|(_, sprite)| sprite.texture == "puddle")
+            .map(|(id, _)| *id)
+            .collect();
+        if puddles.len() > MAX_PUDDLES {
+            puddles.sort_unstable();
+            for id in &puddles[..puddles.len() - MAX_PUDDLES] {
+                self.floor_sprites.remove(id);
+            }
+            changed = true;
+        }
+
         changed
     }
 
+    /// Advances every player's weapon timers. Runs once a tick, as update can run more than once
+    /// a tick for the same player.
+    pub fn update_weapons(&mut self, dt: Duration) {
+        for player in self.players.values_mut() {
+            player.cool_weapon(dt);
+            player.update_reload(dt);
+            player.update_switch(dt);
+        }
+    }
+
     pub fn update(&mut self, id: String, input: &Input, dt: Duration) -> bool {
         // generate respawn position before mutable borrow
         let respawn_pos = if self
@@ -85,7 +365,8 @@ impl GameState {
             .map(|p| p.health == 0 && p.death_timer.is_zero())
             .unwrap_or(false)
         {
-            Some(Player::get_random_spawn_point(&self.world))
+            let (x, y) = Player::get_random_spawn_point(&self.world);
+            Some((x, y, Player::spawn_facing(&self.world, x, y)))
         } else {
             None
         };
@@ -93,12 +374,22 @@ impl GameState {
         let mut puddle_coordiantes = (0.0, 0.0);
 
         if let Some(player) = self.players.get_mut(&id) {
-            player.take_input(input, &self.world);
+            // Movement is tuned per nominal tick, so a scaled dt scales movement with it
+            let time_scale = dt.as_secs_f32() * TICK_RATE as f32;
+            player.take_input(
+                input,
+                &self.world,
+                time_scale,
+                self.max_air_jumps,
+                self.bhop,
+            );
+            player.update_protection(dt);
+            player.update_teleport_cooldown(dt);
 
             if player.dying {
                 player.animation_state = AnimationState::Dying;
                 player.death_timer = player.death_timer.saturating_sub(dt);
-                if player.death_timer < RESPAWN_DELAY {
+                if player.death_timer < self.respawn_delay {
                     player.dying = false;
                     puddle_coordiantes = (player.x, player.y);
                 }
@@ -106,8 +397,9 @@ impl GameState {
                 player.animation_state = AnimationState::Dead;
                 player.death_timer = player.death_timer.saturating_sub(dt);
                 if player.death_timer.is_zero() {
-                    if let Some((map_x, map_y)) = respawn_pos {
-                        player.respawn(map_x, map_y);
+                    if let Some((map_x, map_y, angle)) = respawn_pos {
+                        player.respawn(map_x, map_y, angle);
+                        player.protect(self.spawn_protection);
                     }
                 }
             } else if player.shooting {
@@ -131,26 +423,240 @@ impl GameState {
         false
     }
 
-    pub fn measure_shot(&self, shooter_id: &u64) -> Option<u64> {
-        if let Some(shooter) = self.players.get(&shooter_id.to_string()) {
-            if shooter.health == 0 {
-                return None;
+    /// Swaps in a reloaded map. Players move to new spawn points, puddles are cleared and the new
+    /// map's health and ammo packs and dummies laid out.
+    pub fn replace_world(&mut self, world: World) {
+        self.world = world;
+        self.floor_sprites.clear();
+        self.projectiles.clear();
+        self.place_pickups();
+        self.place_dummies();
+        for player in self.players.values_mut() {
+            let (x, y) = Player::get_random_spawn_point(&self.world);
+            player.x = x;
+            player.y = y;
+            player.z = 0.0;
+            player.angle = Player::spawn_facing(&self.world, x, y);
+        }
+    }
+
+    /// Whether any player overlaps a tile, counting the same margin as wall collisions. A door
+    /// can't shut on someone standing in it.
+    pub fn tile_occupied(&self, x: usize, y: usize) -> bool {
+        let radius = PLAYER_RADIUS + WALL_COLLISION_MARGIN;
+        let covers = |position: f32, tile: usize| {
+            (position - radius).floor() <= tile as f32 && (position + radius).floor() >= tile as f32
+        };
+        self.players
+            .values()
+            .any(|player| covers(player.x, x) && covers(player.y, y))
+    }
+
+    /// Who the shooter's current weapon hits, from how far and where, one entry per ray that hit
+    /// someone. A shotgun fans its pellets out around the aim, so the same player can be in the
+    /// list more than once.
+    pub fn measure_shot(&self, shooter_id: &u64) -> Vec<(u64, f32, HitKind)> {
+        let Some(shooter) = self.players.get(&shooter_id.to_string()) else {
+            return Vec::new();
+        };
+        if shooter.health == 0 {
+            return Vec::new();
+        }
+
+        let weapon = shooter.current_weapon;
+        (0..weapon.pellets())
+            .filter_map(|pellet| {
+                let angle = shooter.angle + weapon.pellet_offset(pellet);
+                self.measure_ray(shooter_id, angle, weapon.max_distance())
+            })
+            .collect()
+    }
+
+    /// Fires the shooter's current weapon with --projectiles, one projectile per pellet. They start
+    /// at eye height and climb or drop with the pitch.
+    pub fn spawn_projectiles(&mut self, shooter_id: &u64) {
+        let Some(shooter) = self.players.get(&shooter_id.to_string()) else {
+            return;
+        };
+        if shooter.health == 0 {
+            return;
+        }
+
+        let weapon = shooter.current_weapon;
+        let eye_z = shooter.z + shooter.camera_height() + 0.5;
+        let new_projectiles: Vec<Projectile> = (0..weapon.pellets())
+            .map(|pellet| {
+                let angle = shooter.angle + weapon.pellet_offset(pellet);
+                Projectile {
+                    position: (shooter.x, shooter.y, eye_z),
+                    velocity: (
+                        angle.cos() * PROJECTILE_SPEED,
+                        angle.sin() * PROJECTILE_SPEED,
+                        // pitch is height per distance, halved like in measure_ray
+                        shooter.pitch * 0.5 * PROJECTILE_SPEED,
+                    ),
+                    owner_id: *shooter_id,
+                    weapon,
+                    distance: 0.0,
+                }
+            })
+            .collect();
+        self.projectiles.extend(new_projectiles);
+    }
+
+    /// Moves projectiles along by dt. Ones that reach a wall, the floor or the end of their range
+    /// are dropped, leaving blood where they land, and ones that pass through a player are dropped
+    /// and returned.
+    pub fn advance_projectiles(&mut self, dt: Duration) -> Vec<ProjectileHit> {
+        let mut hits = Vec::new();
+        for mut projectile in std::mem::take(&mut self.projectiles) {
+            let (x, y, z) = projectile.position;
+            let (vx, vy, vz) = projectile.velocity;
+            let speed = vx.hypot(vy);
+            let dir = (vx / speed, vy / speed);
+            let climb = vz / speed; // height change per tile flown
+            let range_left = projectile.weapon.max_distance().sqrt() - projectile.distance;
+            let step = (speed * dt.as_secs_f32()).min(range_left);
+            let wall = self
+                .nearest_wall_distance_squared((x, y), dir.0, dir.1)
+                .sqrt();
+            let travel = step.min(wall);
+
+            if let Some((target_id, along, kind)) =
+                self.projectile_hit(&projectile, dir, climb, travel)
+            {
+                hits.push(ProjectileHit {
+                    owner_id: projectile.owner_id,
+                    target_id,
+                    weapon: projectile.weapon,
+                    distance: projectile.distance + along,
+                    kind,
+                });
+                continue;
+            }
+
+            let end_z = z + climb * travel;
+            if end_z < 0.0 {
+                let along = z / -climb;
+                self.add_blood((x + dir.0 * along, y + dir.1 * along, 0.0));
+                continue;
+            }
+            if travel < step {
+                let along = travel - DECAL_WALL_OFFSET;
+                if end_z < 1.0 {
+                    self.add_blood((x + dir.0 * along, y + dir.1 * along, end_z));
+                }
+                continue;
+            }
+            if travel >= range_left {
+                continue;
+            }
+            projectile.position = (x + dir.0 * travel, y + dir.1 * travel, end_z);
+            projectile.distance += travel;
+            self.projectiles.push(projectile);
+        }
+        hits
+    }
+
+    /// Closest player the projectile passes through within the next travel tiles, how far along and
+    /// where it hits them
+    fn projectile_hit(
+        &self,
+        projectile: &Projectile,
+        dir: (f32, f32),
+        climb: f32,
+        travel: f32,
+    ) -> Option<(u64, f32, HitKind)> {
+        let (x, y, z) = projectile.position;
+        let target_width = SPRITE_OTHER_PLAYER_WIDTH * 0.5;
+        let mut closest: Option<(u64, f32, HitKind)> = None;
+
+        for (target_id_str, target) in &self.players {
+            let Ok(target_id) = target_id_str.parse::<u64>() else {
+                continue;
+            };
+            // spawn protected players can't be hit, shots pass through them
+            if target_id == projectile.owner_id || target.protected {
+                continue;
+            }
+            let dx = target.x - x;
+            let dy = target.y - y;
+            let along = dx * dir.0 + dy * dir.1;
+            if along < 0.0 || along > travel {
+                continue;
+            }
+            let perp_dist_sq = dx * dx + dy * dy - along * along;
+            let height = z + climb * along;
+            if perp_dist_sq < target_width * target_width
+                && height > target.z
+                && height < target.z + Self::hitbox_height(target)
+                && closest.is_none_or(|(_, distance, _)| along < distance)
+            {
+                closest = Some((target_id, along, Self::hit_kind(target, height - target.z)));
+            }
+        }
+
+        for (index, dummy) in self.dummies.iter().enumerate() {
+            let dx = dummy.x - x;
+            let dy = dummy.y - y;
+            let along = dx * dir.0 + dy * dir.1;
+            if along < 0.0
+                || along > travel
+                || closest.is_some_and(|(_, distance, _)| along >= distance)
+            {
+                continue;
             }
+            let perp_dist_sq = dx * dx + dy * dy - along * along;
+            if let Some(kind) = dummy.hit_kind(perp_dist_sq, z + climb * along) {
+                closest = Some((Dummy::id(index), along, kind));
+            }
+        }
+        closest
+    }
+
+    /// Headshot if the shot passes through the top of a living player, crouched or not. height is
+    /// measured from the target's feet. Corpses lie low and never take headshots.
+    fn hit_kind(target: &Player, height: f32) -> HitKind {
+        if target.health > 0 && height > target.body_height() * (1.0 - HEADSHOT_FRACTION) {
+            HitKind::Head
+        } else {
+            HitKind::Body
+        }
+    }
+
+    /// Height a shot has to pass under to hit the player
+    fn hitbox_height(target: &Player) -> f32 {
+        // Corpse lies low
+        if target.health == 0 {
+            SPRITE_OTHER_PLAYER_HEIGHT * 0.4
+        } else {
+            target.body_height()
+        }
+    }
 
-            let shot_dir_x = shooter.angle.cos();
-            let shot_dir_y = shooter.angle.sin();
+    /// Closest player hit by a ray from the shooter at the given angle within max_distance (squared),
+    /// the distance to them and where they were hit
+    fn measure_ray(
+        &self,
+        shooter_id: &u64,
+        angle: f32,
+        max_distance: f32,
+    ) -> Option<(u64, f32, HitKind)> {
+        if let Some(shooter) = self.players.get(&shooter_id.to_string()) {
+            let shot_dir_x = angle.cos();
+            let shot_dir_y = angle.sin();
 
-            let wall_dist_sq = self.nearest_wall_distance_squared(shooter, shot_dir_x, shot_dir_y);
             let mut closest_hit_distance: f32 = MAX;
             let mut target_id_opt = None;
 
             for (target_id_str, target) in &self.players {
-                if &shooter_id.to_string() != target_id_str {
+                // spawn protected players can't be hit, shots pass through them
+                if &shooter_id.to_string() != target_id_str && !target.protected {
                     let dx = target.x - shooter.x;
                     let dy = target.y - shooter.y;
                     let dist_sq = dx * dx + dy * dy;
 
-                    if dist_sq < wall_dist_sq && dist_sq < SHOT_MAX_DISTANCE {
+                    if dist_sq < max_distance {
                         // Calculate the dot product of the vector from shooter to target and the shot direction.
                         // A positive dot product means the target is generally in front of the shooter.
                         let dot = dx * shot_dir_x + dy * shot_dir_y;
@@ -163,19 +669,25 @@ impl GameState {
                             // Squared perpendicular distance from the target to the shot ray: how far off-axis the target is from the shot's line of fire.
                             let perp_dist_sq = dist_sq - proj_len_sq;
 
+                            // Point where the shot passes the target. The renderer draws the sprite at the
+                            // crosshair only if no wall is closer along this same ray, so check the same.
+                            let proj_len = proj_len_sq.sqrt();
+                            let hit_point = (
+                                shooter.x + shot_dir_x * proj_len,
+                                shooter.y + shot_dir_y * proj_len,
+                            );
+                            let line_of_sight =
+                                self.world.walls_between((shooter.x, shooter.y), hit_point) == 0;
+
                             let target_width = SPRITE_OTHER_PLAYER_WIDTH * 0.5; // Player hitbox width
-                            if perp_dist_sq < target_width * target_width {
+                            if line_of_sight && perp_dist_sq < target_width * target_width {
                                 // Vertical check
                                 let dist = dist_sq.sqrt();
-                                let shot_height_at_target =
-                                    shooter.z + CAMERA_HEIGHT_OFFSET + shooter.pitch * dist * 0.5; // pitch is a vertical offset, not an angle
+                                let shot_height_at_target = shooter.z
+                                    + shooter.camera_height()
+                                    + shooter.pitch * dist * 0.5; // pitch is a vertical offset, not an angle
 
-                                // Corpse lies low
-                                let target_height = if target.health == 0 {
-                                    SPRITE_OTHER_PLAYER_HEIGHT * 0.4
-                                } else {
-                                    SPRITE_OTHER_PLAYER_HEIGHT
-                                };
+                                let target_height = Self::hitbox_height(target);
 
                                 // Shot hits someone
                                 if shot_height_at_target > target.z - 0.5
@@ -186,7 +698,12 @@ impl GameState {
                                     // Update closest hit so far
                                     if dist < closest_hit_distance {
                                         closest_hit_distance = dist;
-                                        target_id_opt = Some(target_id);
+                                        // hitscan heights sit half a unit below the floor based ones
+                                        let kind = Self::hit_kind(
+                                            target,
+                                            shot_height_at_target - target.z + 0.5,
+                                        );
+                                        target_id_opt = Some((target_id, dist, kind));
                                     }
                                 }
                             }
@@ -195,15 +712,114 @@ impl GameState {
                 }
             }
 
+            // dummies stand on the floor, so their heights are measured from it, half a unit above
+            // the hitscan ones
+            for (index, dummy) in self.dummies.iter().enumerate() {
+                let dx = dummy.x - shooter.x;
+                let dy = dummy.y - shooter.y;
+                let dist = dx.hypot(dy);
+                let along = dx * shot_dir_x + dy * shot_dir_y;
+                if dist * dist >= max_distance || along <= 0.0 || dist >= closest_hit_distance {
+                    continue;
+                }
+                let height = shooter.z + shooter.camera_height() + shooter.pitch * dist * 0.5 + 0.5;
+                let hit_point = (
+                    shooter.x + shot_dir_x * along,
+                    shooter.y + shot_dir_y * along,
+                );
+                if let Some(kind) = dummy.hit_kind(dist * dist - along * along, height)
+                    && self.world.walls_between((shooter.x, shooter.y), hit_point) == 0
+                {
+                    closest_hit_distance = dist;
+                    target_id_opt = Some((Dummy::id(index), dist, kind));
+                }
+            }
+
             return target_id_opt;
         }
         None
     }
 
-    fn nearest_wall_distance_squared(&self, player: &Player, dir_x: f32, dir_y: f32) -> f32 {
+    /// Path of a shot for rendering tracers, ending at the target if one was hit or else at the
+    /// first wall or maximum range. Dead players don't shoot.
+    pub fn shot_tracer(&self, shooter_id: &u64, target_id: Option<u64>) -> Option<Tracer> {
+        let shooter = self.players.get(&shooter_id.to_string())?;
+        if shooter.health == 0 {
+            return None;
+        }
+        let dir_x = shooter.angle.cos();
+        let dir_y = shooter.angle.sin();
+
+        let length = match target_id.and_then(|id| self.target_position(id)) {
+            Some((x, y)) => {
+                let dx = x - shooter.x;
+                let dy = y - shooter.y;
+                (dx * dx + dy * dy).sqrt()
+            }
+            None => self
+                .nearest_wall_distance_squared((shooter.x, shooter.y), dir_x, dir_y)
+                .min(shooter.current_weapon.max_distance())
+                .sqrt(),
+        };
+
+        // Heights are measured from the floor like sprites, the camera sits half a unit up
+        let eye_z = shooter.z + shooter.camera_height() + 0.5;
+        Some(Tracer {
+            shooter_id: *shooter_id,
+            start: (
+                shooter.x + dir_x * TRACER_START_OFFSET,
+                shooter.y + dir_y * TRACER_START_OFFSET,
+                eye_z - TRACER_GUN_DROP + shooter.pitch * TRACER_START_OFFSET * 0.5,
+            ),
+            end: (
+                shooter.x + dir_x * length,
+                shooter.y + dir_y * length,
+                eye_z + shooter.pitch * length * 0.5,
+            ),
+        })
+    }
+
+    /// Where the shooter's shot meets a wall or the floor as (x, y, height above floor), None if it
+    /// runs out of range or flies over the walls first, or if the shooter is dead
+    pub fn shot_impact(&self, shooter_id: &u64) -> Option<(f32, f32, f32)> {
+        let shooter = self.players.get(&shooter_id.to_string())?;
+        if shooter.health == 0 {
+            return None;
+        }
+        let dir_x = shooter.angle.cos();
+        let dir_y = shooter.angle.sin();
+        let eye_z = shooter.z + shooter.camera_height() + 0.5;
+        let climb = shooter.pitch * 0.5; // height per tile, like in measure_ray
+
+        let wall = self
+            .nearest_wall_distance_squared((shooter.x, shooter.y), dir_x, dir_y)
+            .sqrt();
+        // looking down, the shot may reach the floor before the wall
+        let floor = if climb < 0.0 {
+            eye_z / -climb
+        } else {
+            f32::INFINITY
+        };
+        let distance = if floor < wall {
+            floor
+        } else {
+            wall - DECAL_WALL_OFFSET
+        };
+        let z = (eye_z + climb * distance).max(0.0);
+        if distance * distance > shooter.current_weapon.max_distance() || z >= 1.0 {
+            return None;
+        }
+        Some((
+            shooter.x + dir_x * distance,
+            shooter.y + dir_y * distance,
+            z,
+        ))
+    }
+
+    fn nearest_wall_distance_squared(&self, (x, y): (f32, f32), dir_x: f32, dir_y: f32) -> f32 {
         // Map position
-        let mut map_x = player.x as isize;
-        let mut map_y = player.y as isize;
+        let mut map_x = x as isize;
+        let mut map_y = y as isize;
 
         // Delta distance for each step
         let delta_dist_x = if dir_x == 0.0 {
@@ -219,15 +835,15 @@ impl GameState {
 
         // Step and initial sideDist
         let (step_x, mut side_dist_x) = if dir_x < 0.0 {
-            (-1, (player.x - map_x as f32) * delta_dist_x)
+            (-1, (x - map_x as f32) * delta_dist_x)
         } else {
-            (1, (map_x as f32 + 1.0 - player.x) * delta_dist_x)
+            (1, (map_x as f32 + 1.0 - x) * delta_dist_x)
         };
 
         let (step_y, mut side_dist_y) = if dir_y < 0.0 {
-            (-1, (player.y - map_y as f32) * delta_dist_y)
+            (-1, (y - map_y as f32) * delta_dist_y)
         } else {
-            (1, (map_y as f32 + 1.0 - player.y) * delta_dist_y)
+            (1, (map_y as f32 + 1.0 - y) * delta_dist_y)
         };
 
         // Perform Digital Differential Analyzer
@@ -244,7 +860,7 @@ impl GameState {
                 wall_type = 1;
             }
 
-            if self.world.get_tile(map_x as usize, map_y as usize) > 0 {
+            if self.world.is_solid(map_x as usize, map_y as usize) {
                 hit = true;
             }
         }
@@ -259,3 +875,54 @@ impl GameState {
         distance * distance
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A room with a single wall tile standing in it at (4, 2), the shooter in one corner and the
+    // target half hidden behind the wall, so only rays passing under the wall's corner reach it
+    fn corner_shot() -> GameState {
+        let mut game_state = GameState::new(None, None);
+        game_state.world = toml::from_str(
+            "map = [
+                [1, 1, 1, 1, 1, 1],
+                [1, 0, 0, 0, 0, 1],
+                [1, 0, 0, 0, 0, 1],
+                [1, 0, 0, 0, 0, 1],
+                [1, 0, 1, 0, 0, 1],
+                [1, 0, 0, 0, 0, 1],
+                [1, 0, 0, 0, 0, 1],
+                [1, 0, 0, 0, 0, 1],
+                [1, 1, 1, 1, 1, 1],
+            ]",
+        )
+        .unwrap();
+        game_state.pickups.clear();
+        game_state.dummies.clear();
+        for (id, x, y) in [("1", 1.5, 1.5), ("2", 6.5, 2.3)] {
+            let mut player = Player::new("character".to_string(), &game_state.world);
+            player.x = x;
+            player.y = y;
+            game_state.players.insert(id.to_string(), player);
+        }
+        game_state
+    }
+
+    #[test]
+    fn ray_clipping_a_wall_corner_misses() {
+        let game_state = corner_shot();
+        // straight at the target's middle, which passes through the wall's corner on the way
+        let angle = 0.16f32.atan();
+        assert!(game_state.measure_ray(&1, angle, f32::MAX).is_none());
+    }
+
+    #[test]
+    fn ray_clearing_a_wall_corner_hits() {
+        let game_state = corner_shot();
+        // passes just under the wall's corner and still catches the edge of the target
+        let angle = 0.135f32.atan();
+        let (target_id, _, _) = game_state.measure_ray(&1, angle, f32::MAX).unwrap();
+        assert_eq!(target_id, 2);
+    }
+}
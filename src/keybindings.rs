@@ -0,0 +1,280 @@
+use std::collections::{BTreeMap, HashMap};
+
+use serde::{Deserialize, Serialize};
+use winit::keyboard::KeyCode;
+use winit_input_helper::WinitInputHelper;
+
+/// Things the player does with a key that can be rebound in the menu
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Action {
+    Forward,
+    Back,
+    StrafeLeft,
+    StrafeRight,
+    TurnLeft,
+    TurnRight,
+    Jump,
+    Sprint,
+    Crouch,
+    Reload,
+    Use,
+    Pistol,
+    Shotgun,
+    Rifle,
+}
+
+impl Action {
+    pub const ALL: [Action; 14] = [
+        Action::Forward,
+        Action::Back,
+        Action::StrafeLeft,
+        Action::StrafeRight,
+        Action::TurnLeft,
+        Action::TurnRight,
+        Action::Jump,
+        Action::Sprint,
+        Action::Crouch,
+        Action::Reload,
+        Action::Use,
+        Action::Pistol,
+        Action::Shotgun,
+        Action::Rifle,
+    ];
+
+    /// Key name in the config file
+    fn name(self) -> &'static str {
+        match self {
+            Action::Forward => "forward",
+            Action::Back => "back",
+            Action::StrafeLeft => "strafe_left",
+            Action::StrafeRight => "strafe_right",
+            Action::TurnLeft => "turn_left",
+            Action::TurnRight => "turn_right",
+            Action::Jump => "jump",
+            Action::Sprint => "sprint",
+            Action::Crouch => "crouch",
+            Action::Reload => "reload",
+            Action::Use => "use",
+            Action::Pistol => "pistol",
+            Action::Shotgun => "shotgun",
+            Action::Rifle => "rifle",
+        }
+    }
+
+    /// Name shown in the menu
+    pub fn label(self) -> &'static str {
+        match self {
+            Action::Forward => "Forward",
+            Action::Back => "Back",
+            Action::StrafeLeft => "Strafe Left",
+            Action::StrafeRight => "Strafe Right",
+            Action::TurnLeft => "Turn Left",
+            Action::TurnRight => "Turn Right",
+            Action::Jump => "Jump",
+            Action::Sprint => "Sprint",
+            Action::Crouch => "Crouch",
+            Action::Reload => "Reload",
+            Action::Use => "Use",
+            Action::Pistol => "Pistol",
+            Action::Shotgun => "Shotgun",
+            Action::Rifle => "Rifle",
+        }
+    }
+
+    fn default_key(self) -> KeyCode {
+        match self {
+            Action::Forward => KeyCode::KeyW,
+            Action::Back => KeyCode::KeyS,
+            Action::StrafeLeft => KeyCode::KeyA,
+            Action::StrafeRight => KeyCode::KeyD,
+            Action::TurnLeft => KeyCode::ArrowLeft,
+            Action::TurnRight => KeyCode::ArrowRight,
+            Action::Jump => KeyCode::Space,
+            Action::Sprint => KeyCode::ShiftLeft,
+            Action::Crouch => KeyCode::ControlLeft,
+            Action::Reload => KeyCode::KeyR,
+            Action::Use => KeyCode::KeyE,
+            Action::Pistol => KeyCode::Digit1,
+            Action::Shotgun => KeyCode::Digit2,
+            Action::Rifle => KeyCode::Digit3,
+        }
+    }
+}
+
+/// Keys an action can be bound to. Escape, Enter, Tab and the function keys already do something
+/// in the client and are left out.
+const BINDABLE_KEYS: [KeyCode; 58] = [
+    KeyCode::KeyA,
+    KeyCode::KeyB,
+    KeyCode::KeyC,
+    KeyCode::KeyD,
+    KeyCode::KeyE,
+    KeyCode::KeyF,
+    KeyCode::KeyG,
+    KeyCode::KeyH,
+    KeyCode::KeyI,
+    KeyCode::KeyJ,
+    KeyCode::KeyK,
+    KeyCode::KeyL,
+    KeyCode::KeyM,
+    KeyCode::KeyN,
+    KeyCode::KeyO,
+    KeyCode::KeyP,
+    KeyCode::KeyQ,
+    KeyCode::KeyR,
+    KeyCode::KeyS,
+    KeyCode::KeyT,
+    KeyCode::KeyU,
+    KeyCode::KeyV,
+    KeyCode::KeyW,
+    KeyCode::KeyX,
+    KeyCode::KeyY,
+    KeyCode::KeyZ,
+    KeyCode::Digit0,
+    KeyCode::Digit1,
+    KeyCode::Digit2,
+    KeyCode::Digit3,
+    KeyCode::Digit4,
+    KeyCode::Digit5,
+    KeyCode::Digit6,
+    KeyCode::Digit7,
+    KeyCode::Digit8,
+    KeyCode::Digit9,
+    KeyCode::Space,
+    KeyCode::ShiftLeft,
+    KeyCode::ShiftRight,
+    KeyCode::ControlLeft,
+    KeyCode::ControlRight,
+    KeyCode::AltLeft,
+    KeyCode::AltRight,
+    KeyCode::CapsLock,
+    KeyCode::Backquote,
+    KeyCode::Minus,
+    KeyCode::Equal,
+    KeyCode::BracketLeft,
+    KeyCode::BracketRight,
+    KeyCode::Backslash,
+    KeyCode::Semicolon,
+    KeyCode::Quote,
+    KeyCode::Comma,
+    KeyCode::Period,
+    KeyCode::ArrowUp,
+    KeyCode::ArrowDown,
+    KeyCode::ArrowLeft,
+    KeyCode::ArrowRight,
+];
+
+/// Name of a key in the config file, like KeyW or ShiftLeft
+fn key_name(key: KeyCode) -> String {
+    format!("{:?}", key)
+}
+
+fn key_from_name(name: &str) -> Option<KeyCode> {
+    BINDABLE_KEYS.into_iter().find(|key| key_name(*key) == name)
+}
+
+/// Short name of a key for the menu, W rather than KeyW
+pub fn key_label(key: KeyCode) -> String {
+    let name = key_name(key);
+    name.strip_prefix("Key")
+        .or_else(|| name.strip_prefix("Digit"))
+        .unwrap_or(&name)
+        .to_string()
+}
+
+/// The first bindable key pressed this frame, for rebinding
+pub fn pressed_key(input: &WinitInputHelper) -> Option<KeyCode> {
+    BINDABLE_KEYS
+        .into_iter()
+        .find(|key| input.key_pressed(*key))
+}
+
+/// The key each action is bound to. Saved in the client config as a table of action names to key
+/// names; actions missing from it keep their default key and an empty name leaves one unbound.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(from = "BTreeMap<String, String>", into = "BTreeMap<String, String>")]
+pub struct KeyBindings {
+    keys: HashMap<Action, KeyCode>,
+}
+
+impl Default for KeyBindings {
+    fn default() -> Self {
+        KeyBindings {
+            keys: Action::ALL
+                .into_iter()
+                .map(|action| (action, action.default_key()))
+                .collect(),
+        }
+    }
+}
+
+impl KeyBindings {
+    pub fn key(&self, action: Action) -> Option<KeyCode> {
+        self.keys.get(&action).copied()
+    }
+
+    pub fn held(&self, input: &WinitInputHelper, action: Action) -> bool {
+        self.key(action).is_some_and(|key| input.key_held(key))
+    }
+
+    pub fn pressed(&self, input: &WinitInputHelper, action: Action) -> bool {
+        self.key(action).is_some_and(|key| input.key_pressed(key))
+    }
+
+    /// Binds the key to the action. Any other action on the same key loses it and is returned,
+    /// so the caller can warn that it's now unbound.
+    pub fn bind(&mut self, action: Action, key: KeyCode) -> Option<Action> {
+        let previous = self
+            .keys
+            .iter()
+            .find(|(other, bound)| **other != action && **bound == key)
+            .map(|(other, _)| *other);
+        if let Some(previous) = previous {
+            self.keys.remove(&previous);
+        }
+        self.keys.insert(action, key);
+        previous
+    }
+}
+
+impl From<BTreeMap<String, String>> for KeyBindings {
+    fn from(names: BTreeMap<String, String>) -> Self {
+        let mut bindings = KeyBindings::default();
+        // in a fixed order, so the same conflicting file always resolves the same way
+        for action in Action::ALL {
+            let Some(name) = names.get(action.name()) else {
+                continue;
+            };
+            if name.is_empty() {
+                bindings.keys.remove(&action);
+                continue;
+            }
+            let Some(key) = key_from_name(name) else {
+                eprintln!("Unknown key '{}' for {} in config", name, action.name());
+                continue;
+            };
+            if let Some(previous) = bindings.bind(action, key) {
+                eprintln!(
+                    "Warning: {} and {} are both bound to {}, keeping it for {}",
+                    previous.name(),
+                    action.name(),
+                    name,
+                    action.name()
+                );
+            }
+        }
+        bindings
+    }
+}
+
+impl From<KeyBindings> for BTreeMap<String, String> {
+    fn from(bindings: KeyBindings) -> Self {
+        Action::ALL
+            .into_iter()
+            .map(|action| {
+                let name = bindings.key(action).map(key_name).unwrap_or_default();
+                (action.name().to_string(), name)
+            })
+            .collect()
+    }
+}
@@ -1,52 +1,173 @@
-use crate::gamestate::GameState;
+use crate::consts::{
+    FALLOFF_MIN, FALLOFF_START, RIFLE_COOLDOWN, RIFLE_DAMAGE, RIFLE_MAX_DISTANCE, SHOOT_COOLDOWN, SHOT_DAMAGE,
+    SHOT_MAX_DISTANCE, SHOTGUN_COOLDOWN, SHOTGUN_DAMAGE, SHOTGUN_MAX_DISTANCE, SHOTGUN_PELLETS,
+    SHOTGUN_SPREAD,
+};
+use crate::gamestate::{Dummy, GameState};
 use crate::map::World;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::time::Duration;
 
+pub mod audio;
+pub mod bot;
 pub mod consts;
+pub mod diagnostics;
 pub mod flags;
 pub mod gamestate;
+pub mod keybindings;
 pub mod map;
 pub mod minimap;
 pub mod player;
+pub mod ratelimit;
+pub mod reliable;
 pub mod renderer;
+pub mod replay;
 pub mod spritesheet;
 pub mod text;
 pub mod textures;
 pub mod utils;
+pub mod votekick;
 
 #[derive(Serialize, Deserialize, Debug)]
 pub enum ClientMessage {
-    Connect(String),
+    Connect(String, Option<String>), // username and the server password, if the player has one
     Input(Input),
     Ping,
     Shot,
+    VoteKick(String), // name of the player to kick
+    Reload,
+    SwitchWeapon(u8), // slot of the weapon, 1 to 3
+    Interact,         // use the nearest door
+    Ack(u32),         // sequence number of a ServerMessage::Reliable that arrived
 }
 
 #[derive(Serialize, Deserialize, Debug)]
 pub enum ServerMessage {
     Welcome(Welcome),
     GameUpdate(HashMap<String, PlayerUpdate>),
-    LeaderboardUpdate(HashMap<String, usize>),
+    GameDelta(HashMap<String, PlayerUpdate>), // only the players that changed since the last update
+    LeaderboardUpdate(HashMap<String, Score>),
     SpriteUpdate(HashMap<u32, Sprite>),
-    InitialState(GameState),
+    InitialState(Box<GameState>),
     UsernameRejected(String),
+    PasswordRejected(String), // missing or wrong password, the client asks for one
     PlayerLeft(u64),
     ShotHit(Hit),
     Winner(String),
+    MatchEnd(MatchStats),
+    Tracer(Tracer),
+    Projectiles(Vec<Projectile>),
+    ShotFired(u64), // id of the shooter, for the sound
+    ServerShutdown,
+    KillStreak(KillStreak),
+    VoteKick(VoteKickStatus),
+    VoteKickRejected(String),
+    Kicked(String),
+    Warmup(Option<Duration>), // time left in the warmup, None once the match is live
+    RoundTime(Duration), // time left with --time-limit, zero once a tie went to sudden death
+    DoorUpdate((usize, usize), bool), // door tile and whether it's now open
+    Reliable(u32, Box<ServerMessage>), // numbered critical message, see reliable.rs
+    Beacon(Beacon),
+    DummyUpdate(Vec<Dummy>), // every practice dummy, sent when one goes down or stands up
 }
 
+/// A player taking damage. Fall damage comes with the faller as their own shooter.
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Hit {
     pub shooter_id: u64,
     pub shooter_name: String,
     pub target_id: u64,
     pub target_name: String,
+    pub damage: u16,
+    pub kind: HitKind,
+    pub killed: bool,
+    pub target_pos: (f32, f32, f32), // where the target was hit, for the damage number
+}
+
+/// Where a shot hit. Shots in the top HEADSHOT_FRACTION of a standing player are headshots.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum HitKind {
+    Body,
+    Head,
+}
+
+/// Announces first blood or a notable kill streak
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct KillStreak {
+    pub name: String,
+    pub streak: usize,
+    pub first_blood: bool,
+}
+
+/// Tally of a vote to kick a player, sent whenever it changes
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct VoteKickStatus {
+    pub target: String,
+    pub votes: usize,
+    pub required: usize,
+    pub remaining: Duration,
+    pub passed: Option<bool>, // None while the vote runs
+}
+
+/// A fired shot's path, start and end points are (x, y, height above floor)
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Tracer {
+    pub shooter_id: u64,
+    pub start: (f32, f32, f32),
+    pub end: (f32, f32, f32),
+}
+
+/// A shot in flight with --projectiles. Position is (x, y, height above floor) like a Tracer's
+/// points, velocity is in tiles per second.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Projectile {
+    pub position: (f32, f32, f32),
+    pub velocity: (f32, f32, f32),
+    pub owner_id: u64,
+    pub weapon: Weapon,
+    pub distance: f32, // tiles flown so far
+}
+
+/// A player's line on the leaderboard. kills counts points, so kill bonuses can add more than one.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq)]
+pub struct Score {
+    pub kills: usize,
+    pub deaths: usize,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct MatchStats {
+    pub winner: String,
+    pub duration: Duration,
+    pub players: Vec<PlayerStats>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct PlayerStats {
+    pub name: String,
+    pub kills: usize,
+    pub deaths: usize,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
 pub struct Welcome {
     pub id: u64,
+    pub name: String, // may differ from the requested name if the server added a suffix
+    pub server_name: Option<String>,
+    pub motd: Option<String>,
+    pub spawn_protection: Duration, // zero when the server has it off
+    pub score_to_win: usize,
+}
+
+/// Broadcast on the LAN so clients can find the server without knowing its address
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Beacon {
+    pub name: Option<String>,
+    pub port: u16, // the game port, beacons are sent from another one
+    pub players: usize,
+    pub map: String, // the map a new player would join
+    pub password: bool, // whether joining needs a password
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
@@ -70,7 +191,7 @@ pub enum Direction {
     FrontLeft,
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub struct PlayerUpdate {
     pub x: f32,
     pub y: f32,
@@ -82,6 +203,109 @@ pub struct PlayerUpdate {
     pub shooting: bool,
     pub health: u16,
     pub score: usize,
+    pub heat: f32,
+    pub overheated: bool,
+    pub protected: bool,
+    pub ammo: u16,
+    pub reserve_ammo: u16,
+    pub reloading: bool,
+    pub current_weapon: Weapon,
+    pub switching: bool,
+    pub teleports: u32,
+    pub crouch: f32,
+    pub stamina: f32,
+    pub exhausted: bool,
+}
+
+/// Guns a player can switch between. They all load from the same magazine.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+pub enum Weapon {
+    #[default]
+    Pistol,
+    Shotgun,
+    Rifle,
+}
+
+impl Weapon {
+    /// Weapon on a number key slot, 1 to 3
+    pub fn from_slot(slot: u8) -> Option<Weapon> {
+        match slot {
+            1 => Some(Weapon::Pistol),
+            2 => Some(Weapon::Shotgun),
+            3 => Some(Weapon::Rifle),
+            _ => None,
+        }
+    }
+
+    /// Damage of one ray, a shotgun pellet for the shotgun
+    pub fn damage(self) -> u16 {
+        match self {
+            Weapon::Pistol => SHOT_DAMAGE,
+            Weapon::Shotgun => SHOTGUN_DAMAGE,
+            Weapon::Rifle => RIFLE_DAMAGE,
+        }
+    }
+
+    pub fn cooldown(self) -> Duration {
+        match self {
+            Weapon::Pistol => SHOOT_COOLDOWN,
+            Weapon::Shotgun => SHOTGUN_COOLDOWN,
+            Weapon::Rifle => RIFLE_COOLDOWN,
+        }
+    }
+
+    /// Rays fired per shot
+    pub fn pellets(self) -> usize {
+        match self {
+            Weapon::Shotgun => SHOTGUN_PELLETS,
+            Weapon::Pistol | Weapon::Rifle => 1,
+        }
+    }
+
+    /// Angle in radians between neighbouring pellets
+    pub fn spread(self) -> f32 {
+        match self {
+            Weapon::Shotgun => SHOTGUN_SPREAD,
+            Weapon::Pistol | Weapon::Rifle => 0.0,
+        }
+    }
+
+    /// Share of the damage left after the shot has gone distance tiles: all of it up close, then
+    /// less and less until FALLOFF_MIN at the end of the range
+    pub fn falloff(self, distance: f32) -> f32 {
+        let range = self.max_distance().sqrt();
+        if distance <= FALLOFF_START || range <= FALLOFF_START {
+            return 1.0;
+        }
+        let t = ((distance - FALLOFF_START) / (range - FALLOFF_START)).min(1.0);
+        1.0 - t * (1.0 - FALLOFF_MIN)
+    }
+
+    /// Angle of a pellet from the aim, the pellets fan out evenly on both sides
+    pub fn pellet_offset(self, pellet: usize) -> f32 {
+        (pellet as f32 - (self.pellets() - 1) as f32 / 2.0) * self.spread()
+    }
+
+    /// Squared distance past which a shot doesn't hit
+    pub fn max_distance(self) -> f32 {
+        match self {
+            Weapon::Pistol => SHOT_MAX_DISTANCE,
+            Weapon::Shotgun => SHOTGUN_MAX_DISTANCE,
+            Weapon::Rifle => RIFLE_MAX_DISTANCE,
+        }
+    }
+
+    /// Texture of the gun in hand. Weapons without their own art use the pistol's.
+    pub fn texture(self, shooting: bool) -> &'static str {
+        match (self, shooting) {
+            (Weapon::Pistol, false) => "gun",
+            (Weapon::Pistol, true) => "gunshot",
+            (Weapon::Shotgun, false) => "shotgun",
+            (Weapon::Shotgun, true) => "shotgunshot",
+            (Weapon::Rifle, false) => "rifle",
+            (Weapon::Rifle, true) => "rifleshot",
+        }
+    }
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
@@ -94,6 +318,7 @@ pub struct Input {
     pub pitch: f32,
     pub jump: bool,
     pub sprint: bool,
+    pub crouching: bool,
     pub shoot: bool,
 }
 
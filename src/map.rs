@@ -1,12 +1,76 @@
-use crate::consts::{DEFAULT_MAP_SIDE, DEFAULT_MAP_INCLUDE_CORNERS};
+use crate::consts::{DEFAULT_MAP_SIDE, DEFAULT_MAP_INCLUDE_CORNERS, DOOR_SLIDE_TIME, DOOR_TILE};
 use crate::utils::carve_path;
 use rand::Rng;
 use serde::{Deserialize, Serialize};
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap};
 use std::fs;
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct World {
     pub map: Vec<Vec<u8>>,
+    #[serde(default)]
+    pub gravity_zones: Vec<GravityZone>,
+    // Texture file for each wall tile value, keyed by the value as text since TOML keys are
+    // strings. Tiles not listed keep the default texture for their value.
+    #[serde(default)]
+    pub textures: HashMap<String, String>,
+    // State of every DOOR_TILE, found when the map is loaded
+    #[serde(default)]
+    pub doors: Vec<Door>,
+    #[serde(default)]
+    pub health_packs: Vec<PickupSpot>,
+    #[serde(default)]
+    pub ammo_packs: Vec<PickupSpot>,
+    #[serde(default)]
+    pub teleporters: Vec<Teleporter>,
+    // Open tiles that launch anyone standing on them into the air
+    #[serde(default)]
+    pub jump_pads: Vec<(usize, usize)>,
+    // Where practice dummies stand to be shot at
+    #[serde(default)]
+    pub dummies: Vec<PickupSpot>,
+}
+
+/// Rectangle of tiles where gravity is multiplied, e.g. 0.3 for floaty jumps or negative to lift
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct GravityZone {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+    pub multiplier: f32,
+}
+
+/// A door tile. It slides sideways over DOOR_SLIDE_TIME and only lets players through once fully
+/// open, closed it blocks players, shots and sight like a wall.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Door {
+    pub x: usize,
+    pub y: usize,
+    pub open: bool,
+    pub openness: f32, // 0.0 closed to 1.0 open
+}
+
+/// Where a health or ammo pack lies on the floor or a dummy stands, in tiles from the top left
+/// corner
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct PickupSpot {
+    pub x: f32,
+    pub y: f32,
+}
+
+/// Two linked open tiles, stepping onto either one sends the player to the other
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Teleporter {
+    pub a: (usize, usize),
+    pub b: (usize, usize),
+}
+
+impl GravityZone {
+    pub fn contains(&self, x: f32, y: f32) -> bool {
+        x >= self.x && x < self.x + self.width && y >= self.y && y < self.y + self.height
+    }
 }
 
 impl World {
@@ -34,15 +98,131 @@ impl World {
     }
 
     pub fn parse_from_file(path: &str) -> Self {
+        Self::try_parse_from_file(path).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    pub fn try_parse_from_file(path: &str) -> Result<Self, String> {
         let contents = fs::read_to_string(path)
-            .unwrap_or_else(|e| panic!("Failed to read map file {}: {}", path, e));
-        let world: Self = toml::from_str(&contents)
-            .unwrap_or_else(|e| panic!("Failed to parse TOML map file {}: {}", path, e));
-        world
+            .map_err(|e| format!("Failed to read map file {}: {}", path, e))?;
+        let mut world: World = toml::from_str(&contents)
+            .map_err(|e| format!("Failed to parse TOML map file {}: {}", path, e))?;
+        world.find_doors();
+        Ok(world)
+    }
+
+    /// Starts every door tile closed
+    fn find_doors(&mut self) {
+        self.doors = self
+            .map
+            .iter()
+            .enumerate()
+            .flat_map(|(x, column)| {
+                column
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, tile)| **tile == DOOR_TILE)
+                    .map(move |(y, _)| Door {
+                        x,
+                        y,
+                        open: false,
+                        openness: 0.0,
+                    })
+            })
+            .collect();
+    }
+
+    /// Checks that a loaded map is playable: rectangular, closed in by walls and with room to spawn
+    pub fn validate(&self) -> Result<(), String> {
+        let width = self.map.first().map_or(0, |row| row.len());
+        if width == 0 {
+            return Err("Map is empty".to_string());
+        }
+        if self.map.iter().any(|row| row.len() != width) {
+            return Err("Map rows have different lengths".to_string());
+        }
+        let last_row = self.map.len() - 1;
+        let open_edge = self.map.iter().enumerate().any(|(y, row)| {
+            row.iter().enumerate().any(|(x, &tile)| {
+                tile == 0 && (y == 0 || y == last_row || x == 0 || x == width - 1)
+            })
+        });
+        if open_edge {
+            return Err("Map edges must all be walls".to_string());
+        }
+        if !self.map.iter().flatten().any(|&tile| tile == 0) {
+            return Err("Map has no open tiles to spawn on".to_string());
+        }
+        for zone in &self.gravity_zones {
+            if !(zone.width > 0.0 && zone.height > 0.0) {
+                return Err("Gravity zones need a positive width and height".to_string());
+            }
+            if !zone.multiplier.is_finite() {
+                return Err("Gravity zone multiplier must be a number".to_string());
+            }
+        }
+        for spot in self.health_packs.iter().chain(&self.ammo_packs) {
+            if !(spot.x >= 0.0 && spot.y >= 0.0)
+                || self.get_tile(spot.x as usize, spot.y as usize) != 0
+            {
+                return Err(format!(
+                    "Pack at ({}, {}) isn't on an open tile",
+                    spot.x, spot.y
+                ));
+            }
+        }
+        for spot in &self.dummies {
+            if !(spot.x >= 0.0 && spot.y >= 0.0)
+                || self.get_tile(spot.x as usize, spot.y as usize) != 0
+            {
+                return Err(format!(
+                    "Dummy at ({}, {}) isn't on an open tile",
+                    spot.x, spot.y
+                ));
+            }
+        }
+        for teleporter in &self.teleporters {
+            if teleporter.a == teleporter.b {
+                return Err("Teleporter ends must be different tiles".to_string());
+            }
+            for (x, y) in [teleporter.a, teleporter.b] {
+                if self.get_tile(x, y) != 0 {
+                    return Err(format!(
+                        "Teleporter at ({}, {}) isn't on an open tile",
+                        x, y
+                    ));
+                }
+            }
+        }
+        for &(x, y) in &self.jump_pads {
+            if self.get_tile(x, y) != 0 {
+                return Err(format!("Jump pad at ({}, {}) isn't on an open tile", x, y));
+            }
+        }
+        for tile in self.textures.keys() {
+            if !tile.parse::<u8>().is_ok_and(|tile| tile > 0) {
+                return Err(format!("Texture key '{}' isn't a wall tile value", tile));
+            }
+        }
+        Ok(())
+    }
+
+    /// The texture file the map sets for a tile value, if any
+    pub fn texture_for(&self, tile: u8) -> Option<&str> {
+        self.textures.get(&tile.to_string()).map(String::as_str)
     }
 
     pub fn generate_random_map(x_size: usize, y_size: usize) -> Self {
-        let mut world: World = World { map: vec![vec![1; x_size]; y_size] };
+        let mut world: World = World {
+            map: vec![vec![1; x_size]; y_size],
+            gravity_zones: Vec::new(),
+            textures: HashMap::new(),
+            doors: Vec::new(),
+            health_packs: Vec::new(),
+            ammo_packs: Vec::new(),
+            dummies: Vec::new(),
+            teleporters: Vec::new(),
+            jump_pads: Vec::new(),
+        };
         // Randomly select textures for the walls
         for y in 0..y_size {
             for x in 0..x_size {
@@ -65,6 +245,219 @@ impl World {
         world
     }
 
+    /// Counts the wall tiles a straight line between two points passes through
+    pub fn walls_between(&self, from: (f32, f32), to: (f32, f32)) -> usize {
+        let dir_x = to.0 - from.0;
+        let dir_y = to.1 - from.1;
+        let length = (dir_x * dir_x + dir_y * dir_y).sqrt();
+        if length == 0.0 {
+            return 0;
+        }
+
+        let mut map_x = from.0.floor() as isize;
+        let mut map_y = from.1.floor() as isize;
+        let end_x = to.0.floor() as isize;
+        let end_y = to.1.floor() as isize;
+
+        // Distances along the line are measured in the same units as length
+        let delta_dist_x = if dir_x == 0.0 {
+            f32::INFINITY
+        } else {
+            (length / dir_x).abs()
+        };
+        let delta_dist_y = if dir_y == 0.0 {
+            f32::INFINITY
+        } else {
+            (length / dir_y).abs()
+        };
+
+        let (step_x, mut side_dist_x) = if dir_x < 0.0 {
+            (-1, (from.0 - map_x as f32) * delta_dist_x)
+        } else {
+            (1, (map_x as f32 + 1.0 - from.0) * delta_dist_x)
+        };
+        let (step_y, mut side_dist_y) = if dir_y < 0.0 {
+            (-1, (from.1 - map_y as f32) * delta_dist_y)
+        } else {
+            (1, (map_y as f32 + 1.0 - from.1) * delta_dist_y)
+        };
+
+        let mut walls = 0;
+        while (map_x, map_y) != (end_x, end_y) {
+            if side_dist_x < side_dist_y {
+                if side_dist_x > length {
+                    break;
+                }
+                side_dist_x += delta_dist_x;
+                map_x += step_x;
+            } else {
+                if side_dist_y > length {
+                    break;
+                }
+                side_dist_y += delta_dist_y;
+                map_y += step_y;
+            }
+
+            if (map_x, map_y) != (end_x, end_y) && self.is_solid(map_x as usize, map_y as usize) {
+                walls += 1;
+            }
+        }
+
+        walls
+    }
+
+    /// Shortest way between two open tiles with A*, one tile up, down, left or right at a time.
+    /// Returns the centers of the tiles after `from` up to `to`, None if walls cut them off. Every
+    /// non-zero tile counts as a wall, doors too.
+    pub fn find_path(&self, from: (usize, usize), to: (usize, usize)) -> Option<Vec<(f32, f32)>> {
+        let open = |(x, y): (usize, usize)| self.get_tile(x, y) == 0;
+        if !open(from) || !open(to) {
+            return None;
+        }
+        let estimate = |(x, y): (usize, usize)| x.abs_diff(to.0) + y.abs_diff(to.1);
+
+        let mut queue = BinaryHeap::from([Reverse((estimate(from), from))]);
+        let mut cost = HashMap::from([(from, 0)]);
+        let mut came_from = HashMap::new();
+        while let Some(Reverse((_, tile))) = queue.pop() {
+            if tile == to {
+                let mut path = Vec::new();
+                let mut step = to;
+                while step != from {
+                    path.push((step.0 as f32 + 0.5, step.1 as f32 + 0.5));
+                    step = came_from[&step];
+                }
+                path.reverse();
+                return Some(path);
+            }
+
+            let next_cost = cost[&tile] + 1;
+            let (x, y) = tile;
+            // off the top or left edge wraps around to a huge index, which get_tile calls a wall
+            for next in [
+                (x + 1, y),
+                (x.wrapping_sub(1), y),
+                (x, y + 1),
+                (x, y.wrapping_sub(1)),
+            ] {
+                if open(next) && cost.get(&next).is_none_or(|&known| next_cost < known) {
+                    cost.insert(next, next_cost);
+                    came_from.insert(next, tile);
+                    queue.push(Reverse((next_cost + estimate(next), next)));
+                }
+            }
+        }
+        None
+    }
+
+    /// Whether a tile blocks players and shots: walls, and doors that aren't fully open
+    pub fn is_solid(&self, x: usize, y: usize) -> bool {
+        match self.get_tile(x, y) {
+            0 => false,
+            DOOR_TILE => self
+                .door_openness(x, y)
+                .is_none_or(|openness| openness < 1.0),
+            _ => true,
+        }
+    }
+
+    /// How far the door on a tile has slid open, None if the tile isn't a door
+    pub fn door_openness(&self, x: usize, y: usize) -> Option<f32> {
+        self.doors
+            .iter()
+            .find(|door| (door.x, door.y) == (x, y))
+            .map(|door| door.openness)
+    }
+
+    /// Opens or closes the door on a tile. Returns false if there's no door there.
+    pub fn set_door(&mut self, x: usize, y: usize, open: bool) -> bool {
+        match self
+            .doors
+            .iter_mut()
+            .find(|door| (door.x, door.y) == (x, y))
+        {
+            Some(door) => {
+                door.open = open;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// The door whose center is closest to a position, if any is within range
+    pub fn nearest_door(&self, x: f32, y: f32, range: f32) -> Option<&Door> {
+        self.doors
+            .iter()
+            .map(|door| {
+                let distance = (door.x as f32 + 0.5 - x).hypot(door.y as f32 + 0.5 - y);
+                (door, distance)
+            })
+            .filter(|(_, distance)| *distance <= range)
+            .min_by(|(_, a), (_, b)| a.total_cmp(b))
+            .map(|(door, _)| door)
+    }
+
+    /// Slides the doors towards open or shut
+    pub fn step_doors(&mut self, dt: f32) {
+        let step = dt / DOOR_SLIDE_TIME;
+        for door in &mut self.doors {
+            door.openness = if door.open {
+                (door.openness + step).min(1.0)
+            } else {
+                (door.openness - step).max(0.0)
+            };
+        }
+    }
+
+    /// The tile a teleporter on this tile leads to, if there is one
+    pub fn teleport_destination(&self, x: usize, y: usize) -> Option<(usize, usize)> {
+        self.teleporters.iter().find_map(|teleporter| {
+            if teleporter.a == (x, y) {
+                Some(teleporter.b)
+            } else if teleporter.b == (x, y) {
+                Some(teleporter.a)
+            } else {
+                None
+            }
+        })
+    }
+
+    pub fn jump_pad_at(&self, x: usize, y: usize) -> bool {
+        self.jump_pads.contains(&(x, y))
+    }
+
+    /// Gravity multiplier at a position, from the first zone containing it
+    pub fn gravity_at(&self, x: f32, y: f32) -> f32 {
+        self.gravity_zones
+            .iter()
+            .find(|zone| zone.contains(x, y))
+            .map_or(1.0, |zone| zone.multiplier)
+    }
+
+    /// The map as text for debugging, '#' for walls and '.' for open tiles, laid out like the
+    /// minimap. Markers are drawn over the tiles their positions fall in. Every line has the
+    /// same width, missing tiles of ragged rows show as walls.
+    pub fn to_ascii(&self, markers: &[(char, f32, f32)]) -> String {
+        let width = self.map.len();
+        let height = self.map.iter().map(|row| row.len()).max().unwrap_or(0);
+        let mut text = String::with_capacity((width + 1) * height);
+
+        for tile_y in 0..height {
+            for tile_x in 0..width {
+                let marker = markers.iter().find(|(_, x, y)| {
+                    *x >= 0.0 && *y >= 0.0 && (*x as usize, *y as usize) == (tile_x, tile_y)
+                });
+                text.push(match marker {
+                    Some((c, _, _)) => *c,
+                    None if self.get_tile(tile_x, tile_y) > 0 => '#',
+                    None => '.',
+                });
+            }
+            text.push('\n');
+        }
+        text
+    }
+
     pub fn get_tile(&self, y: usize, x: usize) -> u8 {
         if self.map.is_empty() {
             return 1;
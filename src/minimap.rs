@@ -1,3 +1,7 @@
+use crate::consts::{
+    DOOR_TILE, FOG_REVEAL_RADIUS, FOG_REVEAL_RAYS, FOG_REVEAL_STEP, MINIMAP_DOOR_COLOR,
+    MINIMAP_JUMP_PAD_COLOR, MINIMAP_TELEPORTER_COLOR, MINIMAP_UNEXPLORED_COLOR,
+};
 use crate::renderer::Renderer;
 use crate::{
     GameState, consts::HEIGHT, consts::MINIMAP_BACKGROUND_COLOR, consts::MINIMAP_BORDER_COLOR,
@@ -7,6 +11,43 @@ use crate::{
     consts::MINIMAP_WALL_COLOR, consts::MINIMAP_WIDTH, consts::WIDTH,
 };
 
+/// Visits every pixel on a line between two points (Bresenham's line algorithm)
+pub(crate) fn line_points(x0: i32, y0: i32, x1: i32, y1: i32, mut plot: impl FnMut(i32, i32)) {
+    let dx = (x1 - x0).abs();
+    let dy = (y1 - y0).abs();
+    let sx = if x1 > x0 { 1 } else { -1 };
+    let sy = if y1 > y0 { 1 } else { -1 };
+    let mut err = dx - dy;
+    let mut x = x0;
+    let mut y = y0;
+
+    // Safety limit to prevent infinite loops in degenerate cases
+    let max_steps = (dx as i64 + dy as i64 + 1) as usize + 100;
+    let mut step_count = 0usize;
+
+    loop {
+        if step_count > max_steps {
+            break;
+        }
+        step_count += 1;
+        plot(x, y);
+
+        if x == x1 && y == y1 {
+            break;
+        }
+        // Update error term and coordinates
+        let e2 = err * 2;
+        if e2 > -dy {
+            err -= dy;
+            x += sx;
+        }
+        if e2 < dx {
+            err += dx;
+            y += sy;
+        }
+    }
+}
+
 impl<'a> Renderer<'a> {
     // ===== Minimap Helper Functions =====
 
@@ -45,47 +86,124 @@ impl<'a> Renderer<'a> {
         }
     }
 
+    // ===== Fog of War =====
+
+    /// Forget every explored tile, e.g. for a new map
+    pub fn reset_exploration(&mut self) {
+        self.explored.clear();
+        self.explored_width = 0;
+    }
+
+    fn is_explored(&self, tile_x: usize, tile_y: usize) -> bool {
+        !self.fog_of_war
+            || self
+                .explored
+                .get(tile_y * self.explored_width + tile_x)
+                .copied()
+                .unwrap_or(false)
+    }
+
+    /// Cast short rays around the player and mark every tile they reach, including the walls
+    /// that stop them
+    fn reveal_around(&mut self, game_state: &GameState, x: f32, y: f32) {
+        let map_height = game_state.world.map.len();
+        let map_width = game_state.world.map.first().map_or(0, |row| row.len());
+        // a map of another size means the map changed under us
+        if self.explored.len() != map_width * map_height || self.explored_width != map_width {
+            self.explored = vec![false; map_width * map_height];
+            self.explored_width = map_width;
+        }
+
+        for ray in 0..FOG_REVEAL_RAYS {
+            let angle = ray as f32 / FOG_REVEAL_RAYS as f32 * std::f32::consts::TAU;
+            let (dir_y, dir_x) = angle.sin_cos();
+            let mut distance = 0.0;
+            while distance <= FOG_REVEAL_RADIUS {
+                let ray_x = x + dir_x * distance;
+                let ray_y = y + dir_y * distance;
+                if ray_x < 0.0 || ray_y < 0.0 {
+                    break;
+                }
+                let (tile_x, tile_y) = (ray_x as usize, ray_y as usize);
+                if tile_x >= map_width || tile_y >= map_height {
+                    break;
+                }
+                self.explored[tile_y * map_width + tile_x] = true;
+                if game_state.world.is_solid(tile_x, tile_y) {
+                    break;
+                }
+                distance += FOG_REVEAL_STEP;
+            }
+        }
+    }
+
     // Draw a line between two points (simple Bresenham-ish approach)
     pub fn draw_line(&mut self, x0: i32, y0: i32, x1: i32, y1: i32, color: u32) {
-        // Bresenham's line algorithm with i32 coords and clipping checks.
-        let dx = (x1 - x0).abs();
-        let dy = (y1 - y0).abs();
-        let sx = if x1 > x0 { 1 } else { -1 };
-        let sy = if y1 > y0 { 1 } else { -1 };
-        let mut err = dx - dy;
-        let mut x = x0;
-        let mut y = y0;
-
-        // Safety limit to prevent infinite loops in degenerate cases
-        let max_steps = (dx as i64 + dy as i64 + 1) as usize + 100;
-        let mut step_count = 0usize;
-
-        loop {
-            if step_count > max_steps {
-                break;
-            }
-            step_count += 1;
+        line_points(x0, y0, x1, y1, |x, y| {
             // Plot the pixel if within bounds
             if x >= 0 && x < WIDTH as i32 && y >= 0 && y < HEIGHT as i32 {
                 self.buffer[y as usize * WIDTH + x as usize] = color;
             }
+        });
+    }
 
-            if x == x1 && y == y1 {
-                break;
-            }
-            // Update error term and coordinates
-            let e2 = err * 2;
-            if e2 > -dy {
-                err -= dy;
-                x += sx;
-            }
-            if e2 < dx {
-                err += dx;
-                y += sy;
+    /// Anti-aliased line (Xiaolin Wu's algorithm), the two pixels straddling the line share its color
+    pub fn draw_line_smooth(&mut self, x0: f32, y0: f32, x1: f32, y1: f32, color: u32) {
+        let steep = (y1 - y0).abs() > (x1 - x0).abs();
+        let (x0, y0, x1, y1) = if steep {
+            (y0, x0, y1, x1)
+        } else {
+            (x0, y0, x1, y1)
+        };
+        let (x0, y0, x1, y1) = if x0 > x1 {
+            (x1, y1, x0, y0)
+        } else {
+            (x0, y0, x1, y1)
+        };
+        let gradient = if x1 == x0 { 1.0 } else { (y1 - y0) / (x1 - x0) };
+
+        let mut y = y0 + gradient * (x0.round() - x0);
+        for x in x0.round() as i32..=x1.round() as i32 {
+            let below = y.floor();
+            let fraction = y - below;
+            let (px, py) = if steep {
+                ((below as i32, x), (below as i32 + 1, x))
+            } else {
+                ((x, below as i32), (x, below as i32 + 1))
+            };
+            self.blend_pixel(px.0, px.1, color, 1.0 - fraction);
+            self.blend_pixel(py.0, py.1, color, fraction);
+            y += gradient;
+        }
+    }
+
+    /// Anti-aliased filled circle, edge pixels are blended by how much of them is inside
+    pub fn draw_circle_smooth(&mut self, cx: f32, cy: f32, radius: f32, color: u32) {
+        let min_x = (cx - radius - 1.0).floor() as i32;
+        let max_x = (cx + radius + 1.0).ceil() as i32;
+        let min_y = (cy - radius - 1.0).floor() as i32;
+        let max_y = (cy + radius + 1.0).ceil() as i32;
+        for y in min_y..=max_y {
+            for x in min_x..=max_x {
+                // distance from the pixel center to the edge, half a pixel either way is partial
+                let distance = (x as f32 + 0.5 - cx).hypot(y as f32 + 0.5 - cy) - radius;
+                let coverage = (0.5 - distance).clamp(0.0, 1.0);
+                if coverage > 0.0 {
+                    self.blend_pixel(x, y, color, coverage);
+                }
             }
         }
     }
 
+    /// Minimap line in the configured quality
+    fn minimap_line(&mut self, x0: i32, y0: i32, x1: i32, y1: i32, color: u32) {
+        if self.smooth_minimap {
+            self.draw_line_smooth(x0 as f32, y0 as f32, x1 as f32, y1 as f32, color);
+        } else {
+            self.draw_line(x0, y0, x1, y1, color);
+        }
+    }
+
     /// Render the minimap in the top-right corner
     pub fn render_minimap(&mut self, game_state: &GameState, my_id: u64) {
         let minimap_width = MINIMAP_WIDTH;
@@ -118,6 +236,12 @@ impl<'a> Renderer<'a> {
         let base_x = start_x as f32 + offset_x;
         let base_y = start_y as f32 + offset_y;
 
+        if self.fog_of_war
+            && let Some(player) = game_state.players.get(&my_id.to_string())
+        {
+            self.reveal_around(game_state, player.x, player.y);
+        }
+
         // Draw background first
         self.fill_rect_minimap(
             start_x,
@@ -149,8 +273,20 @@ impl<'a> Renderer<'a> {
                 }
 
                 let tile = game_state.world.get_tile(tile_x, tile_y);
-                let tile_color = if tile > 0 {
+                let tile_color = if !self.is_explored(tile_x, tile_y) {
+                    MINIMAP_UNEXPLORED_COLOR
+                } else if tile == DOOR_TILE {
+                    MINIMAP_DOOR_COLOR
+                } else if tile > 0 {
                     MINIMAP_WALL_COLOR
+                } else if game_state
+                    .world
+                    .teleport_destination(tile_x, tile_y)
+                    .is_some()
+                {
+                    MINIMAP_TELEPORTER_COLOR
+                } else if game_state.world.jump_pad_at(tile_x, tile_y) {
+                    MINIMAP_JUMP_PAD_COLOR
                 } else {
                     MINIMAP_OPEN_SPACE_COLOR
                 };
@@ -158,14 +294,14 @@ impl<'a> Renderer<'a> {
                 self.fill_rect_minimap(px0, py0, px1 - px0, py1 - py0, tile_color);
 
                 // Draw grid lines at tile boundaries
-                self.draw_line(
+                self.minimap_line(
                     px0 as i32,
                     py0 as i32,
                     px1 as i32,
                     py0 as i32,
                     MINIMAP_GRID_COLOR,
                 );
-                self.draw_line(
+                self.minimap_line(
                     px0 as i32,
                     py0 as i32,
                     px0 as i32,
@@ -175,17 +311,28 @@ impl<'a> Renderer<'a> {
             }
         }
 
-        // Draw all other players using the dynamic coordinate system
+        // Draw all other players using the dynamic coordinate system, except in unexplored tiles
         for (id, player) in &game_state.players {
-            if id != &my_id.to_string() {
-                let px = (base_x + player.x * tile_size_f).round() as usize;
-                let py = (base_y + player.y * tile_size_f).round() as usize;
-                self.draw_circle(
-                    px,
-                    py,
-                    MINIMAP_PLAYER_DOT_RADIUS,
-                    MINIMAP_OTHER_PLAYER_COLOR,
-                );
+            if id != &my_id.to_string()
+                && self.is_explored(player.x.max(0.0) as usize, player.y.max(0.0) as usize)
+            {
+                let px = base_x + player.x * tile_size_f;
+                let py = base_y + player.y * tile_size_f;
+                if self.smooth_minimap {
+                    self.draw_circle_smooth(
+                        px,
+                        py,
+                        MINIMAP_PLAYER_DOT_RADIUS as f32,
+                        MINIMAP_OTHER_PLAYER_COLOR,
+                    );
+                } else {
+                    self.draw_circle(
+                        px.round() as usize,
+                        py.round() as usize,
+                        MINIMAP_PLAYER_DOT_RADIUS,
+                        MINIMAP_OTHER_PLAYER_COLOR,
+                    );
+                }
             }
         }
 
@@ -244,28 +391,28 @@ impl<'a> Renderer<'a> {
         }
 
         // Draw minimap border
-        self.draw_line(
+        self.minimap_line(
             start_x as i32,
             start_y as i32,
             (start_x + minimap_width) as i32,
             start_y as i32,
             MINIMAP_BORDER_COLOR,
         );
-        self.draw_line(
+        self.minimap_line(
             (start_x + minimap_width) as i32,
             start_y as i32,
             (start_x + minimap_width) as i32,
             (start_y + minimap_height) as i32,
             MINIMAP_BORDER_COLOR,
         );
-        self.draw_line(
+        self.minimap_line(
             (start_x + minimap_width) as i32,
             (start_y + minimap_height) as i32,
             start_x as i32,
             (start_y + minimap_height) as i32,
             MINIMAP_BORDER_COLOR,
         );
-        self.draw_line(
+        self.minimap_line(
             start_x as i32,
             (start_y + minimap_height) as i32,
             start_x as i32,
@@ -2,13 +2,21 @@ use rand::Rng;
 use std::time::Duration;
 
 use crate::consts::{
-    DEFAULT_PLAYER_MOVE_SPEED, DEFAULT_PLAYER_ROT_SPEED, DIE_FRAME_TIME, PLAYER_JUMP_VELOCITY,
-    PLAYER_PITCH_LIMIT, PLAYER_RADIUS, PLAYER_SPRINT_SPEED_MULTIPLIER, RESPAWN_DELAY, SHOT_TIME,
+    BHOP_MAX_SPEED, BHOP_SPEED_GAIN, BHOP_WINDOW_TICKS, CAMERA_HEIGHT_OFFSET, CROUCH_CAMERA_DROP,
+    CROUCH_HEIGHT_SCALE, CROUCH_RATE, CROUCH_SPEED_MULTIPLIER, DEATH_ANIMATION_TIME,
+    DEFAULT_PLAYER_MOVE_SPEED, DEFAULT_PLAYER_ROT_SPEED, DIE_FRAME_COUNT, DIE_FRAME_TIME,
+    FALL_DAMAGE_MAX, FALL_DAMAGE_MIN_SPEED, FALL_DAMAGE_PER_SPEED, GRAVITY, HEAT_COOL_DELAY,
+    HEAT_COOL_RATE_FIRING, HEAT_COOL_RATE_IDLE, HEAT_PER_SHOT, JUMP_PAD_VELOCITY, MAGAZINE_SIZE,
+    MAX_RESERVE_AMMO, PLAYER_JUMP_VELOCITY, PLAYER_MAX_HEALTH, PLAYER_MAX_Z, PLAYER_PITCH_LIMIT,
+    PLAYER_RADIUS, PLAYER_SPRINT_SPEED_MULTIPLIER, RELOAD_TIME, SHOT_TIME,
+    SPRITE_OTHER_PLAYER_HEIGHT, STAMINA_DRAIN_RATE, STAMINA_RECOVER_THRESHOLD, STAMINA_REGEN_RATE,
+    STARTING_RESERVE_AMMO, TELEPORT_COOLDOWN, TICK_RATE, WALL_COLLISION_MARGIN, WEAPON_SWITCH_TIME,
 };
 
 use crate::AnimationState;
 use crate::Direction;
 use crate::Input;
+use crate::Weapon;
 use crate::World;
 
 use serde::{Deserialize, Serialize};
@@ -21,6 +29,11 @@ pub struct Player {
     pub angle: f32,
     pub pitch: f32,
     pub velocity_z: f32,
+    pub air_jumps: u8, // jumps left before landing
+    pub jump_held: bool,
+    // Move speed multiplier built up by chaining jumps with --bhop, and ticks since landing
+    bhop_speed: f32,
+    ground_ticks: f32,
     pub move_speed: f32,
     pub rot_speed: f32,
     pub texture: String,
@@ -34,6 +47,33 @@ pub struct Player {
     pub dying: bool,
     pub death_timer: Duration,
     pub score: usize,
+    pub kills: usize,
+    pub deaths: usize,
+    pub current_streak: usize, // kills since last death
+    // Weapon heat with --overheat, an overheated weapon can't fire until it has fully cooled
+    pub heat: f32,
+    pub overheated: bool,
+    heat_cool_delay: Duration,
+    // Rounds in the magazine and in reserve. Reloading takes RELOAD_TIME and stops the gun firing.
+    pub ammo: u16,
+    pub reserve_ammo: u16,
+    pub reloading: bool,
+    reload_timer: Duration,
+    // Weapon in hand. Switching takes WEAPON_SWITCH_TIME and stops the gun firing meanwhile.
+    pub current_weapon: Weapon,
+    pub switching: bool,
+    switch_timer: Duration,
+    // Shots pass through a player this long after respawning with --spawn-protection
+    pub protected: bool,
+    spawn_protection_timer: Duration,
+    // Counts trips through teleporters so clients can tell one happened between updates
+    pub teleports: u32,
+    teleport_cooldown: Duration,
+    // How far into a crouch, from 0 standing to 1 fully down. Eases so speed and height change smoothly.
+    pub crouch: f32,
+    // Sprint stamina, an exhausted player can't sprint until it has partly refilled
+    pub stamina: f32,
+    pub exhausted: bool,
 }
 
 impl Player {
@@ -43,9 +83,13 @@ impl Player {
             x,
             y,
             z: 0.0,
-            angle: std::f32::consts::PI / 2.0,
+            angle: Player::spawn_facing(world, x, y),
             pitch: 0.0,
             velocity_z: 0.0,
+            air_jumps: 0,
+            jump_held: false,
+            bhop_speed: 1.0,
+            ground_ticks: f32::MAX,
             move_speed: DEFAULT_PLAYER_MOVE_SPEED,
             rot_speed: DEFAULT_PLAYER_ROT_SPEED,
             texture: texturename,
@@ -55,15 +99,65 @@ impl Player {
             frame_timer: 0.0,
             shooting: false,
             shoot_timer: Duration::ZERO,
-            health: 100,
+            health: PLAYER_MAX_HEALTH,
             dying: false,
             death_timer: Duration::ZERO,
             score: 0,
+            kills: 0,
+            deaths: 0,
+            current_streak: 0,
+            heat: 0.0,
+            overheated: false,
+            heat_cool_delay: Duration::ZERO,
+            ammo: MAGAZINE_SIZE,
+            reserve_ammo: STARTING_RESERVE_AMMO,
+            reloading: false,
+            reload_timer: Duration::ZERO,
+            current_weapon: Weapon::Pistol,
+            switching: false,
+            switch_timer: Duration::ZERO,
+            protected: false,
+            spawn_protection_timer: Duration::ZERO,
+            teleports: 0,
+            teleport_cooldown: Duration::ZERO,
+            crouch: 0.0,
+            stamina: 1.0,
+            exhausted: false,
         }
     }
 
-    pub fn take_input(&mut self, input: &Input, world: &World) {
+    /// Applies one tick of input. time_scale stretches movement for slowed down or sped up simulation.
+    /// max_air_jumps is how many extra jumps are allowed before landing. With bhop, jumping again
+    /// right after landing keeps the speed from the previous jump and adds a little, up to a cap.
+    pub fn take_input(
+        &mut self,
+        input: &Input,
+        world: &World,
+        time_scale: f32,
+        max_air_jumps: u8,
+        bhop: bool,
+    ) {
+        // the dead stand back up so the corpse isn't squashed
+        let target = if input.crouching && self.health > 0 {
+            1.0
+        } else {
+            0.0
+        };
+        let step = CROUCH_RATE * time_scale;
+        self.crouch += (target - self.crouch).clamp(-step, step);
+
         if self.health > 0 {
+            let moving = input.forth || input.back || input.left || input.right;
+            if self.z == 0.0 {
+                self.ground_ticks += time_scale;
+                // standing around or stopping ends the chain
+                if self.ground_ticks > BHOP_WINDOW_TICKS || !moving {
+                    self.bhop_speed = 1.0;
+                }
+            } else {
+                self.ground_ticks = 0.0;
+            }
+
             let mut new_x = self.x;
             let mut new_y = self.y;
 
@@ -72,10 +166,12 @@ impl Player {
                 slower = 0.707;
             }
 
-            let mut sprint_mult = 1.0;
-            if input.sprint {
-                sprint_mult = PLAYER_SPRINT_SPEED_MULTIPLIER;
+            let sprinting = self.use_stamina(input.sprint && moving, time_scale);
+            let mut sprint_mult = time_scale * self.bhop_speed;
+            if sprinting {
+                sprint_mult *= PLAYER_SPRINT_SPEED_MULTIPLIER;
             }
+            sprint_mult *= 1.0 + (CROUCH_SPEED_MULTIPLIER - 1.0) * self.crouch;
 
             if input.forth {
                 new_x += self.angle.cos() * self.move_speed * slower * sprint_mult;
@@ -100,13 +196,31 @@ impl Player {
                 new_y -= strafe_y * self.move_speed * slower * sprint_mult;
             }
 
+            let from_tile = (self.x as usize, self.y as usize);
             self.check_collision_and_move(new_x, new_y, world);
+            self.use_teleporter(from_tile, world);
 
-            if input.jump && self.z == 0.0 {
-                self.velocity_z = PLAYER_JUMP_VELOCITY;
+            // Only a fresh press jumps, an input that stays in place for several ticks counts once
+            let jump_pressed = input.jump && !self.jump_held;
+            self.jump_held = input.jump;
+            if self.z == 0.0 {
+                self.air_jumps = max_air_jumps;
+            }
+            if jump_pressed {
+                if self.z == 0.0 {
+                    self.velocity_z = PLAYER_JUMP_VELOCITY;
+                    if bhop && moving && self.ground_ticks <= BHOP_WINDOW_TICKS {
+                        self.bhop_speed = (self.bhop_speed + BHOP_SPEED_GAIN).min(BHOP_MAX_SPEED);
+                    }
+                } else if self.air_jumps > 0 {
+                    self.air_jumps -= 1;
+                    self.velocity_z = PLAYER_JUMP_VELOCITY;
+                }
             }
 
-            if input.shoot {
+            // the shot message arrives first and may have used the last round, so only a reload
+            // stops the shot animation here
+            if input.shoot && !self.overheated && !self.reloading && !self.switching {
                 self.shooting = true;
                 self.shoot_timer = SHOT_TIME;
             }
@@ -121,6 +235,8 @@ impl Player {
     fn check_collision_and_move(&mut self, new_x: f32, new_y: f32, world: &World) {
         let dx = new_x - self.x;
         let dy = new_y - self.y;
+        // the margin keeps the camera from getting close enough to a corner to see past it
+        let radius = PLAYER_RADIUS + WALL_COLLISION_MARGIN;
 
         let mut clear_x = true;
         let mut clear_y = true;
@@ -128,72 +244,199 @@ impl Player {
         // --- Horizontal movement ---
         if dx < 0.0 {
             // Moving left: check left-side corners
-            let cx = new_x - PLAYER_RADIUS;
-            let top_y = self.y + PLAYER_RADIUS;
-            let bottom_y = self.y - PLAYER_RADIUS;
+            let cx = new_x - radius;
+            let top_y = self.y + radius;
+            let bottom_y = self.y - radius;
 
-            if world.get_tile(cx.floor() as usize, top_y.floor() as usize) != 0
-                || world.get_tile(cx.floor() as usize, bottom_y.floor() as usize) != 0
+            if world.is_solid(cx.floor() as usize, top_y.floor() as usize)
+                || world.is_solid(cx.floor() as usize, bottom_y.floor() as usize)
             {
                 clear_x = false;
             }
         } else if dx > 0.0 {
             // Moving right: check right-side corners
-            let cx = new_x + PLAYER_RADIUS;
-            let top_y = self.y + PLAYER_RADIUS;
-            let bottom_y = self.y - PLAYER_RADIUS;
+            let cx = new_x + radius;
+            let top_y = self.y + radius;
+            let bottom_y = self.y - radius;
 
-            if world.get_tile(cx.floor() as usize, top_y.floor() as usize) != 0
-                || world.get_tile(cx.floor() as usize, bottom_y.floor() as usize) != 0
+            if world.is_solid(cx.floor() as usize, top_y.floor() as usize)
+                || world.is_solid(cx.floor() as usize, bottom_y.floor() as usize)
             {
                 clear_x = false;
             }
         }
 
-        // --- Vertical movement ---
+        if clear_x {
+            self.x += dx;
+        }
+
+        // --- Vertical movement, checked from the new x so diagonal moves can't cut a corner ---
         if dy < 0.0 {
             // Moving down: check bottom corners
-            let cy = new_y - PLAYER_RADIUS;
-            let left_x = self.x - PLAYER_RADIUS;
-            let right_x = self.x + PLAYER_RADIUS;
+            let cy = new_y - radius;
+            let left_x = self.x - radius;
+            let right_x = self.x + radius;
 
-            if world.get_tile(left_x.floor() as usize, cy.floor() as usize) != 0
-                || world.get_tile(right_x.floor() as usize, cy.floor() as usize) != 0
+            if world.is_solid(left_x.floor() as usize, cy.floor() as usize)
+                || world.is_solid(right_x.floor() as usize, cy.floor() as usize)
             {
                 clear_y = false;
             }
         } else if dy > 0.0 {
             // Moving up: check top corners
-            let cy = new_y + PLAYER_RADIUS;
-            let left_x = self.x - PLAYER_RADIUS;
-            let right_x = self.x + PLAYER_RADIUS;
+            let cy = new_y + radius;
+            let left_x = self.x - radius;
+            let right_x = self.x + radius;
 
-            if world.get_tile(left_x.floor() as usize, cy.floor() as usize) != 0
-                || world.get_tile(right_x.floor() as usize, cy.floor() as usize) != 0
+            if world.is_solid(left_x.floor() as usize, cy.floor() as usize)
+                || world.is_solid(right_x.floor() as usize, cy.floor() as usize)
             {
                 clear_y = false;
             }
         }
 
-        // --- Apply movement ---
-        if clear_x {
-            self.x += dx;
-        }
         if clear_y {
             self.y += dy;
         }
     }
 
+    /// Sends the player to the other end of a teleporter they just stepped onto, keeping their
+    /// facing. Arriving doesn't count as stepping on, so standing still never sends them back.
+    fn use_teleporter(&mut self, from_tile: (usize, usize), world: &World) {
+        let tile = (self.x as usize, self.y as usize);
+        if tile == from_tile || !self.teleport_cooldown.is_zero() {
+            return;
+        }
+        if let Some((x, y)) = world.teleport_destination(tile.0, tile.1) {
+            self.x = x as f32 + 0.5;
+            self.y = y as f32 + 0.5;
+            self.teleports = self.teleports.wrapping_add(1);
+            self.teleport_cooldown = TELEPORT_COOLDOWN;
+        }
+    }
+
+    pub fn update_teleport_cooldown(&mut self, dt: Duration) {
+        self.teleport_cooldown = self.teleport_cooldown.saturating_sub(dt);
+    }
+
+    /// How far along the dying animation is, from 0.0 at the moment of death to 1.0 when lying still
+    pub fn death_progress(&self) -> f32 {
+        match self.animation_state {
+            AnimationState::Dying => ((self.frame as f32 + self.frame_timer / DIE_FRAME_TIME)
+                / DIE_FRAME_COUNT as f32)
+                .min(1.0),
+            AnimationState::Dead => 1.0,
+            _ => 0.0,
+        }
+    }
+
+    /// Heats the weapon for a shot. Returns false if it is overheated and can't fire.
+    pub fn add_heat(&mut self) -> bool {
+        if self.overheated {
+            return false;
+        }
+        self.heat = (self.heat + HEAT_PER_SHOT).min(1.0);
+        self.heat_cool_delay = HEAT_COOL_DELAY;
+        if self.heat >= 1.0 {
+            self.overheated = true;
+        }
+        true
+    }
+
+    /// Cools the weapon, slowly right after a shot and faster once the player stops firing
+    pub fn cool_weapon(&mut self, dt: Duration) {
+        let rate = if self.heat_cool_delay.is_zero() {
+            HEAT_COOL_RATE_IDLE
+        } else {
+            HEAT_COOL_RATE_FIRING
+        };
+        self.heat_cool_delay = self.heat_cool_delay.saturating_sub(dt);
+        self.heat = (self.heat - rate * dt.as_secs_f32()).max(0.0);
+        if self.heat == 0.0 {
+            self.overheated = false;
+        }
+    }
+
+    /// Alive, a round is loaded and no reload or weapon switch is under way
+    pub fn can_fire(&self) -> bool {
+        self.health > 0 && self.ammo > 0 && !self.reloading && !self.switching
+    }
+
+    /// Takes the weapon in hand, which can't fire until WEAPON_SWITCH_TIME has passed. Picking the
+    /// weapon already in hand does nothing.
+    pub fn switch_weapon(&mut self, weapon: Weapon) {
+        if weapon == self.current_weapon {
+            return;
+        }
+        self.current_weapon = weapon;
+        self.switching = true;
+        self.switch_timer = WEAPON_SWITCH_TIME;
+    }
+
+    pub fn update_switch(&mut self, dt: Duration) {
+        self.switch_timer = self.switch_timer.saturating_sub(dt);
+        self.switching = !self.switch_timer.is_zero();
+    }
+
+    /// Starts refilling the magazine from reserve. Does nothing with a full magazine, an empty
+    /// reserve, a reload already going or a dead player.
+    pub fn start_reload(&mut self) {
+        if self.reloading
+            || self.health == 0
+            || self.ammo >= MAGAZINE_SIZE
+            || self.reserve_ammo == 0
+        {
+            return;
+        }
+        self.reloading = true;
+        self.reload_timer = RELOAD_TIME;
+    }
+
+    /// Finishes a reload once its time is up, moving as many rounds as fit from reserve
+    pub fn update_reload(&mut self, dt: Duration) {
+        if !self.reloading {
+            return;
+        }
+        self.reload_timer = self.reload_timer.saturating_sub(dt);
+        if self.reload_timer.is_zero() {
+            let loaded = (MAGAZINE_SIZE - self.ammo).min(self.reserve_ammo);
+            self.ammo += loaded;
+            self.reserve_ammo -= loaded;
+            self.reloading = false;
+        }
+    }
+
+    /// Makes the player immune to shots for a while, a zero duration does nothing
+    pub fn protect(&mut self, duration: Duration) {
+        self.spawn_protection_timer = duration;
+        self.protected = !duration.is_zero();
+    }
+
+    pub fn update_protection(&mut self, dt: Duration) {
+        self.spawn_protection_timer = self.spawn_protection_timer.saturating_sub(dt);
+        self.protected = !self.spawn_protection_timer.is_zero();
+    }
+
+    /// Counts a kill towards the player's total and current streak. Returns the streak.
+    pub fn add_kill(&mut self) -> usize {
+        self.kills += 1;
+        self.current_streak += 1;
+        self.current_streak
+    }
+
     /// Returns true if the player died due to that instance of damage
-    pub fn take_damage(&mut self, damage: u16) -> bool {
+    pub fn take_damage(&mut self, damage: u16, respawn_delay: Duration) -> bool {
         if self.health > damage {
             self.health -= damage;
         } else if self.health > 0 {
             self.dying = true;
             self.health = 0;
-            // Three frames, at 0,2 seconds. 3000 * 0.2 milliseconds = 0.6 seconds?
-            self.death_timer =
-                Duration::from_millis((DIE_FRAME_TIME * 3000.0) as u64) + RESPAWN_DELAY;
+            self.deaths += 1;
+            self.current_streak = 0;
+            self.death_timer = DEATH_ANIMATION_TIME + respawn_delay;
+            // dying drops a reload that was under way
+            self.reloading = false;
+            self.reload_timer = Duration::ZERO;
             return true;
         } else {
             self.health = 0;
@@ -201,10 +444,96 @@ impl Player {
         false
     }
 
-    pub fn respawn(&mut self, map_x: f32, map_y: f32) {
-        self.health = 100;
+    /// Damage for hitting the floor at landing_speed, growing with the speed above the minimum
+    pub fn fall_damage(landing_speed: f32) -> u16 {
+        let excess = landing_speed - FALL_DAMAGE_MIN_SPEED;
+        if excess <= 0.0 {
+            return 0;
+        }
+        ((excess * FALL_DAMAGE_PER_SPEED).ceil() as u16).min(FALL_DAMAGE_MAX)
+    }
+
+    /// Heals a living player up to full health. Returns false if there was nothing to heal.
+    pub fn heal(&mut self, amount: u16) -> bool {
+        if self.health == 0 || self.health >= PLAYER_MAX_HEALTH {
+            return false;
+        }
+        self.health = (self.health + amount).min(PLAYER_MAX_HEALTH);
+        true
+    }
+
+    /// Adds rounds to a living player's reserve, up to the most they can carry. Returns false if
+    /// the reserve was already full.
+    pub fn add_reserve_ammo(&mut self, rounds: u16) -> bool {
+        if self.health == 0 || self.reserve_ammo >= MAX_RESERVE_AMMO {
+            return false;
+        }
+        self.reserve_ammo = (self.reserve_ammo + rounds).min(MAX_RESERVE_AMMO);
+        true
+    }
+
+    /// One tick of rising or falling. Gravity zones scale the pull and jump pads launch whoever
+    /// stands on them, again on every landing. Returns how fast the player hit the floor if they
+    /// landed this tick, otherwise 0.
+    pub fn apply_gravity(&mut self, world: &World, time_scale: f32) -> f32 {
+        if self.z == 0.0 && self.health > 0 && world.jump_pad_at(self.x as usize, self.y as usize) {
+            self.velocity_z = JUMP_PAD_VELOCITY;
+        }
+        self.z += self.velocity_z * time_scale;
+        if self.z > PLAYER_MAX_Z {
+            self.z = PLAYER_MAX_Z;
+            self.velocity_z = self.velocity_z.min(0.0);
+        }
+        if self.z > 0.0 {
+            self.velocity_z -= GRAVITY * world.gravity_at(self.x, self.y) * time_scale;
+            return 0.0;
+        }
+        let landing_speed = -self.velocity_z;
+        self.velocity_z = 0.0;
+        self.z = 0.0;
+        landing_speed
+    }
+
+    /// Drains stamina for a tick of sprinting or refills it otherwise. Returns whether the player
+    /// gets to sprint this tick.
+    fn use_stamina(&mut self, wants_sprint: bool, time_scale: f32) -> bool {
+        let dt = time_scale / TICK_RATE as f32;
+        if self.exhausted && self.stamina >= STAMINA_RECOVER_THRESHOLD {
+            self.exhausted = false;
+        }
+        if wants_sprint && !self.exhausted {
+            self.stamina = (self.stamina - STAMINA_DRAIN_RATE * dt).max(0.0);
+            if self.stamina == 0.0 {
+                self.exhausted = true;
+            }
+            true
+        } else {
+            self.stamina = (self.stamina + STAMINA_REGEN_RATE * dt).min(1.0);
+            false
+        }
+    }
+
+    /// Camera height above the middle of the wall, lowered by crouching
+    pub fn camera_height(&self) -> f32 {
+        CAMERA_HEIGHT_OFFSET - CROUCH_CAMERA_DROP * self.crouch
+    }
+
+    /// Height of the standing body, crouching shrinks it
+    pub fn body_height(&self) -> f32 {
+        SPRITE_OTHER_PLAYER_HEIGHT * (1.0 - (1.0 - CROUCH_HEIGHT_SCALE) * self.crouch)
+    }
+
+    pub fn respawn(&mut self, map_x: f32, map_y: f32, angle: f32) {
+        self.health = PLAYER_MAX_HEALTH;
         self.x = map_x;
         self.y = map_y;
+        self.angle = angle;
+        self.bhop_speed = 1.0;
+        self.crouch = 0.0;
+        self.stamina = 1.0;
+        self.exhausted = false;
+        self.ammo = MAGAZINE_SIZE;
+        self.reserve_ammo = STARTING_RESERVE_AMMO;
         self.animation_state = AnimationState::Idle;
     }
 
@@ -226,4 +555,159 @@ impl Player {
         // + 0.5 to center the player on the tile
         (x as f32 + 0.5, y as f32 + 0.5)
     }
+
+    /// Picks the cardinal direction with the most open tiles in front of a spawn point,
+    /// so players don't spawn staring at a wall
+    pub fn spawn_facing(world: &World, x: f32, y: f32) -> f32 {
+        let directions = [
+            (0.0, (1, 0)),
+            (std::f32::consts::FRAC_PI_2, (0, 1)),
+            (std::f32::consts::PI, (-1, 0)),
+            (3.0 * std::f32::consts::FRAC_PI_2, (0, -1)),
+        ];
+
+        let mut best_angle = std::f32::consts::FRAC_PI_2;
+        let mut best_open = 0;
+        for (angle, (step_x, step_y)) in directions {
+            let mut tile_x = x.floor() as isize + step_x;
+            let mut tile_y = y.floor() as isize + step_y;
+            let mut open = 0;
+            while tile_x >= 0
+                && tile_y >= 0
+                && world.get_tile(tile_x as usize, tile_y as usize) == 0
+            {
+                open += 1;
+                tile_x += step_x;
+                tile_y += step_y;
+            }
+            if open > best_open {
+                best_open = open;
+                best_angle = angle;
+            }
+        }
+        best_angle
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_player() -> Player {
+        let world = World::new(Some(1), None, false, None);
+        Player::new("character".to_string(), &world)
+    }
+
+    // Map rows are indexed by x, so each inner list here is a column of the map
+    fn tiny_world(map: &str) -> World {
+        toml::from_str(&format!("map = {}", map)).unwrap()
+    }
+
+    // A one tile wide corridor along x, with the player at its start facing down it
+    fn corridor(length: usize) -> (World, Player) {
+        let columns = [vec!["[1, 0, 1]"; length], vec!["[1, 1, 1]"]].concat();
+        let world = tiny_world(&format!("[[1, 1, 1], {}]", columns.join(", ")));
+        let mut player = Player::new("character".to_string(), &world);
+        player.x = 1.5;
+        player.y = 1.5;
+        player.angle = 0.0;
+        (world, player)
+    }
+
+    #[test]
+    fn sustained_fire_overheats_until_cooled() {
+        let mut player = test_player();
+        let shots = (1.0 / HEAT_PER_SHOT).ceil() as usize;
+        for _ in 0..shots {
+            assert!(player.add_heat());
+        }
+        assert!(player.overheated);
+        assert!(!player.add_heat());
+
+        // Still locked while the heat is only partly gone
+        player.cool_weapon(HEAT_COOL_DELAY);
+        assert!(player.overheated);
+        assert!(!player.add_heat());
+
+        player.cool_weapon(Duration::from_secs_f32(1.0 / HEAT_COOL_RATE_IDLE));
+        assert_eq!(player.heat, 0.0);
+        assert!(!player.overheated);
+        assert!(player.add_heat());
+    }
+
+    #[test]
+    fn spawn_facing_turns_away_from_walls() {
+        // A corridor running along x, three tiles long, with walls all around it
+        let world = tiny_world("[[1, 1, 1], [1, 0, 1], [1, 0, 1], [1, 0, 1], [1, 1, 1]]");
+        assert_eq!(Player::spawn_facing(&world, 1.5, 1.5), 0.0);
+        assert_eq!(Player::spawn_facing(&world, 3.5, 1.5), std::f32::consts::PI);
+    }
+
+    #[test]
+    fn double_jump_allows_one_jump_in_the_air() {
+        let (world, mut player) = corridor(3);
+        let jump = Input {
+            jump: true,
+            ..Input::default()
+        };
+        let idle = Input::default();
+
+        // A jump off the ground, then two more presses before landing with one air jump allowed
+        let mut impulses = 0;
+        for input in [&jump, &idle, &idle, &jump, &idle, &jump, &idle] {
+            let before = player.velocity_z;
+            player.take_input(input, &world, 1.0, 1, false);
+            if player.velocity_z > before {
+                impulses += 1;
+            }
+            player.apply_gravity(&world, 1.0);
+        }
+        assert!(player.z > 0.0);
+        assert_eq!(impulses, 2);
+    }
+
+    // How far the player gets down a corridor in a number of ticks, jumping on every landing or not
+    fn distance_covered(ticks: usize, hop: bool) -> f32 {
+        let (world, mut player) = corridor(40);
+        for _ in 0..ticks {
+            let input = Input {
+                forth: true,
+                jump: hop && player.z == 0.0,
+                ..Input::default()
+            };
+            player.take_input(&input, &world, 1.0, 0, true);
+            player.apply_gravity(&world, 1.0);
+        }
+        player.x - 1.5
+    }
+
+    #[test]
+    fn chained_jumps_outrun_walking() {
+        let ticks = 300;
+        let walked = distance_covered(ticks, false);
+        let hopped = distance_covered(ticks, true);
+        assert!(walked > 0.0);
+        assert!(hopped > walked * 1.1);
+    }
+
+    #[test]
+    fn streak_grows_with_kills_and_ends_on_death() {
+        let (_, mut player) = corridor(3);
+        assert_eq!(player.add_kill(), 1);
+        assert_eq!(player.add_kill(), 2);
+        assert_eq!(player.add_kill(), 3);
+        assert_eq!(player.kills, 3);
+
+        // Wounds don't end the streak, dying does
+        assert!(!player.take_damage(1, Duration::ZERO));
+        assert_eq!(player.current_streak, 3);
+        assert!(player.take_damage(PLAYER_MAX_HEALTH, Duration::ZERO));
+        assert_eq!(player.current_streak, 0);
+        assert_eq!(player.kills, 3);
+        assert_eq!(player.deaths, 1);
+
+        // Already dead, so no second death
+        assert!(!player.take_damage(PLAYER_MAX_HEALTH, Duration::ZERO));
+        assert_eq!(player.deaths, 1);
+    }
 }
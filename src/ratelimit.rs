@@ -0,0 +1,106 @@
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::time::Instant;
+
+use crate::consts::{RATE_LIMIT_IGNORE_FACTOR, RATE_LIMIT_IGNORE_TIME, RATE_LIMIT_WINDOW};
+
+/// What to do with a datagram that came in
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RateVerdict {
+    Allow,
+    /// Over the limit. first is set on the first drop in a window, for logging.
+    Drop {
+        first: bool,
+    },
+    /// Far over the limit, so everything from the sender is ignored for a while. started is set
+    /// on the datagram that started it.
+    Ignore {
+        started: bool,
+    },
+}
+
+struct Window {
+    start: Instant,
+    count: u32,
+    previous: u32,
+    dropping: bool,
+    ignored_until: Option<Instant>,
+}
+
+/// Messages per second from each address, counted over a sliding window
+pub struct RateLimiter {
+    limit: u32,
+    clients: HashMap<SocketAddr, Window>,
+    last_prune: Instant,
+}
+
+impl RateLimiter {
+    pub fn new(limit: u32) -> Self {
+        RateLimiter {
+            limit,
+            clients: HashMap::new(),
+            last_prune: Instant::now(),
+        }
+    }
+
+    /// Counts a datagram from the address and decides whether to pass it on. Dropped datagrams
+    /// count too, so a client that keeps flooding ends up ignored.
+    pub fn check(&mut self, addr: SocketAddr) -> RateVerdict {
+        let now = Instant::now();
+        if now.duration_since(self.last_prune) >= RATE_LIMIT_WINDOW {
+            self.prune(now);
+        }
+
+        let window = self.clients.entry(addr).or_insert(Window {
+            start: now,
+            count: 0,
+            previous: 0,
+            dropping: false,
+            ignored_until: None,
+        });
+        if let Some(until) = window.ignored_until {
+            if now < until {
+                return RateVerdict::Ignore { started: false };
+            }
+            window.ignored_until = None;
+        }
+
+        let elapsed = now.duration_since(window.start);
+        if elapsed >= RATE_LIMIT_WINDOW {
+            // after a quiet window the old count no longer overlaps the sliding one
+            window.previous = if elapsed < RATE_LIMIT_WINDOW * 2 {
+                window.count
+            } else {
+                0
+            };
+            window.start = now;
+            window.count = 0;
+            window.dropping = false;
+        }
+        window.count += 1;
+
+        // the previous window counts for the part of it the last second still covers
+        let overlap =
+            1.0 - now.duration_since(window.start).as_secs_f32() / RATE_LIMIT_WINDOW.as_secs_f32();
+        let rate = window.count as f32 + window.previous as f32 * overlap;
+        if rate > self.limit as f32 * RATE_LIMIT_IGNORE_FACTOR {
+            window.ignored_until = Some(now + RATE_LIMIT_IGNORE_TIME);
+            RateVerdict::Ignore { started: true }
+        } else if rate > self.limit as f32 {
+            let first = !window.dropping;
+            window.dropping = true;
+            RateVerdict::Drop { first }
+        } else {
+            RateVerdict::Allow
+        }
+    }
+
+    /// Forgets addresses that have gone quiet, so the table doesn't grow with every sender
+    fn prune(&mut self, now: Instant) {
+        self.last_prune = now;
+        self.clients.retain(|_, window| {
+            window.ignored_until.is_some_and(|until| now < until)
+                || now.duration_since(window.start) < RATE_LIMIT_WINDOW * 2
+        });
+    }
+}
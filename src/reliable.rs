@@ -0,0 +1,175 @@
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::mem::Discriminant;
+use std::net::{SocketAddr, UdpSocket};
+use std::rc::Rc;
+use std::time::Instant;
+
+use crate::ServerMessage;
+use crate::consts::{RELIABLE_RESEND_INTERVAL, RELIABLE_SEEN_CAPACITY, RELIABLE_TIMEOUT};
+use crate::diagnostics::PACKETS_SENT;
+
+thread_local! {
+    /// Critical messages sent from this thread that a client hasn't acknowledged yet, filled in
+    /// utils::broadcast_message. Each arena runs on its own thread with its own clients.
+    pub static OUTBOX: RefCell<Outbox> = RefCell::new(Outbox::default());
+}
+
+/// Messages that must arrive. Position updates are sent again every tick, so losing one doesn't
+/// matter, but a lost hit or leaderboard update would never be made good.
+pub fn is_critical(message: &ServerMessage) -> bool {
+    match message {
+        ServerMessage::ShotHit(_)
+        | ServerMessage::PlayerLeft(_)
+        | ServerMessage::Winner(_)
+        | ServerMessage::MatchEnd(_)
+        | ServerMessage::LeaderboardUpdate(_)
+        | ServerMessage::DummyUpdate(_) => true,
+        // the time left goes out again every ROUND_TIME_INTERVAL, a resent copy would only be
+        // late, but the call for sudden death is sent once
+        ServerMessage::RoundTime(remaining) => remaining.is_zero(),
+        _ => false,
+    }
+}
+
+/// Critical messages that carry the whole of some state, so a resent older copy that arrives
+/// after a newer one mustn't replace it
+fn is_snapshot(message: &ServerMessage) -> bool {
+    matches!(
+        message,
+        ServerMessage::LeaderboardUpdate(_) | ServerMessage::DummyUpdate(_)
+    )
+}
+
+struct Pending {
+    encoded: Rc<Vec<u8>>, // shared by every recipient of the same message
+    first_sent: Instant,
+    last_sent: Instant,
+    match_result: bool, // the winner and stats still matter once the next match starts
+}
+
+/// Numbered critical messages waiting for each recipient's acknowledgement
+#[derive(Default)]
+pub struct Outbox {
+    next_seq: u32,
+    pending: HashMap<(SocketAddr, u32), Pending>,
+}
+
+impl Outbox {
+    /// Numbers a message and encodes it for sending, keeping it for every recipient until they
+    /// acknowledge it
+    pub fn wrap(&mut self, message: ServerMessage, recipients: &[SocketAddr]) -> Rc<Vec<u8>> {
+        let seq = self.next_seq;
+        self.next_seq = self.next_seq.wrapping_add(1);
+        let match_result = matches!(
+            message,
+            ServerMessage::Winner(_) | ServerMessage::MatchEnd(_)
+        );
+        let encoded =
+            Rc::new(bincode::serialize(&ServerMessage::Reliable(seq, Box::new(message))).unwrap());
+        let now = Instant::now();
+        for addr in recipients {
+            self.pending.insert(
+                (*addr, seq),
+                Pending {
+                    encoded: encoded.clone(),
+                    first_sent: now,
+                    last_sent: now,
+                    match_result,
+                },
+            );
+        }
+        encoded
+    }
+
+    pub fn acknowledge(&mut self, addr: SocketAddr, seq: u32) {
+        self.pending.remove(&(addr, seq));
+    }
+
+    /// Sends again whatever hasn't been acknowledged within RELIABLE_RESEND_INTERVAL. After
+    /// RELIABLE_TIMEOUT the client has most likely left and the message is dropped.
+    pub fn resend(&mut self, socket: &UdpSocket) -> std::io::Result<()> {
+        let now = Instant::now();
+        self.pending
+            .retain(|_, pending| now.duration_since(pending.first_sent) < RELIABLE_TIMEOUT);
+        for ((addr, _), pending) in self.pending.iter_mut() {
+            if now.duration_since(pending.last_sent) >= RELIABLE_RESEND_INTERVAL {
+                socket.send_to(&pending.encoded, addr)?;
+                pending.last_sent = now;
+                PACKETS_SENT.set(PACKETS_SENT.get() + 1);
+            }
+        }
+        Ok(())
+    }
+
+    /// Drops everything unacknowledged but the last match's result, a new match makes the rest
+    /// of its messages stale
+    pub fn clear(&mut self) {
+        self.pending.retain(|_, pending| pending.match_result);
+    }
+}
+
+/// Sequence numbers of the critical messages a client has handled lately, so resent copies of
+/// them are only acknowledged
+#[derive(Default)]
+pub struct Received {
+    seen: HashSet<u32>,
+    order: VecDeque<u32>, // oldest first, forgotten beyond RELIABLE_SEEN_CAPACITY
+    latest: HashMap<Discriminant<ServerMessage>, u32>, // newest snapshot handled of each kind
+}
+
+impl Received {
+    /// True the first time a sequence number comes in
+    pub fn first_time(&mut self, seq: u32) -> bool {
+        if !self.seen.insert(seq) {
+            return false;
+        }
+        self.order.push_back(seq);
+        if self.order.len() > RELIABLE_SEEN_CAPACITY
+            && let Some(oldest) = self.order.pop_front()
+        {
+            self.seen.remove(&oldest);
+        }
+        true
+    }
+
+    /// True for a snapshot older than the last one of its kind that was handled
+    pub fn outdated(&mut self, seq: u32, message: &ServerMessage) -> bool {
+        if !is_snapshot(message) {
+            return false;
+        }
+        let kind = std::mem::discriminant(message);
+        // sequence numbers wrap, so newer means less than half the range ahead
+        if let Some(latest) = self.latest.get(&kind)
+            && (seq.wrapping_sub(*latest) as i32) < 0
+        {
+            return true;
+        }
+        self.latest.insert(kind, seq);
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn older_snapshots_are_outdated() {
+        let mut received = Received::default();
+        let leaderboard = ServerMessage::LeaderboardUpdate(HashMap::new());
+        let dummies = ServerMessage::DummyUpdate(Vec::new());
+        assert!(!received.outdated(5, &leaderboard));
+        assert!(received.outdated(4, &leaderboard));
+        // kinds are ordered apart, and other messages never are
+        assert!(!received.outdated(3, &dummies));
+        assert!(!received.outdated(2, &ServerMessage::PlayerLeft(1)));
+        assert!(!received.outdated(6, &leaderboard));
+
+        // 0 follows u32::MAX once the numbers wrap
+        let mut received = Received::default();
+        assert!(!received.outdated(u32::MAX, &leaderboard));
+        assert!(!received.outdated(0, &leaderboard));
+        assert!(received.outdated(u32::MAX, &leaderboard));
+    }
+}
@@ -1,27 +1,93 @@
-use std::collections::HashMap;
+use rand::Rng;
+use std::collections::{HashMap, HashSet};
 use std::time::{Duration, Instant};
 
-use crate::consts::FONT_PATH;
-use crate::text::draw_text;
+use crate::consts::{
+    ANNOUNCEMENT_DURATION, DAMAGE_NUMBER_COLOR, DAMAGE_NUMBER_DURATION, DAMAGE_NUMBER_FONT_SIZE,
+    DAMAGE_NUMBER_KILL_COLOR, DAMAGE_NUMBER_RISE, DEFAULT_DISTANCE_FOG, DEFAULT_FIRE_SHAKE,
+    DEFAULT_FOG_OF_WAR, DEFAULT_FOV, DEFAULT_PLAYER_MOVE_SPEED, DEFAULT_SCORE_TO_WIN,
+    DEFAULT_SMOOTH_MINIMAP, DEFAULT_TEXTURED_FLOOR, FOG_COLOR, FOG_MAX_DISTANCE, FONT_PATH,
+    FOV_MAX, FOV_MIN, FRAME_GRAPH_BAR_WIDTH, FRAME_GRAPH_HEIGHT, FRAME_GRAPH_MAX_MS,
+    FRAME_GRAPH_SAMPLES, FRAME_GRAPH_TARGET_MS, GUN_BOB_X, GUN_BOB_Y, JUMP_PAD_FLOOR_COLOR,
+    JUMP_PAD_PULSE_COLOR, JUMP_PAD_PULSE_PERIOD, MUZZLE_FLASH_COLOR, MUZZLE_FLASH_DURATION,
+    MUZZLE_FLASH_GLOW_SIZE, MUZZLE_FLASH_RADIUS, MUZZLE_FLASH_STRENGTH, OVERHEAT_FLASH_INTERVAL,
+    PLAYER_SPRINT_SPEED_MULTIPLIER, ROUND_CLOCK_WARNING, SHADOW_ALPHA, SHADOW_FADE_HEIGHT,
+    SHADOW_FLATTEN, SHADOW_RADIUS, SHAKE_DECAY, SHAKE_FIRE_INTENSITY, SHAKE_HIT_INTENSITY,
+    SHAKE_MAX, SHAKE_PIXELS, SPAWN_PROTECTION_PULSE_SPEED, SPAWN_PROTECTION_SHIMMER_COLOR,
+    SPAWN_PROTECTION_SHIMMER_SPEED, SPAWN_PROTECTION_SHIMMER_STRENGTH, SPAWN_PROTECTION_TINT,
+    TELEPORT_FLASH_DURATION, TELEPORTER_FLOOR_COLOR, TICK_RATE, TRACER_COLOR, TRACER_DURATION,
+    TRACER_SEGMENTS, VIEW_DISTANCE_FADE,
+};
+use crate::diagnostics::FrameTimes;
+use crate::keybindings::Action;
+use crate::minimap::line_points;
+use crate::text::{GlyphCache, draw_text};
 use crate::textures::{self};
 use crate::{
     AnimationState::{Dead, Dying, Idle, Shooting, Walking},
-    Direction, GameState,
+    Direction, GameState, HitKind, MatchStats, Projectile, Tracer, VoteKickStatus,
     consts::{
-        CAMERA_HEIGHT_OFFSET, CAMERA_HEIGHT_OFFSET_DEAD, CAMERA_PLANE_SCALE, CEILING_COLOR,
-        CROSSHAIR_SCALE, DAMAGE_FLASH_DURATION, FLOOR_COLOR, GUN_SCALE, GUN_X_OFFSET, HEIGHT,
-        HIT_MARKER_DURATION, MINIMAP_HEIGHT, MINIMAP_MARGIN, SPRITE_OTHER_PLAYER_HEIGHT,
-        SPRITE_OTHER_PLAYER_WIDTH, WALL_COLOR_PRIMARY, WALL_COLOR_SECONDARY, WIDTH,
+        BODY_FOOT_COLOR, BODY_FOOT_SIZE, BODY_FOOT_SPACING, BODY_SHOW_PITCH, BODY_STRIDE,
+        CAMERA_HEIGHT_OFFSET, CAMERA_HEIGHT_OFFSET_DEAD, CEILING_COLOR, CROSSHAIR_SCALE,
+        DAMAGE_FLASH_DURATION, DEFAULT_CAMERA_BOB, DEFAULT_CAMERA_BOB_AMPLITUDE,
+        DEFAULT_CAMERA_BOB_FREQUENCY, DEFAULT_SHOW_BODY, DEFAULT_SHOW_TRACERS, FLOOR_COLOR,
+        GUN_SCALE, GUN_X_OFFSET, HEIGHT, HIGH_GRAVITY_FLOOR_COLOR, HIT_MARKER_DURATION,
+        HIT_MARKER_GRAZE_COLOR, HIT_MARKER_HEADSHOT_COLOR, HIT_MARKER_KILL_COLOR,
+        HIT_MARKER_SOLID_COLOR, LOW_GRAVITY_FLOOR_COLOR, MINIMAP_HEIGHT, MINIMAP_MARGIN,
+        PLAYER_PITCH_LIMIT, PROJECTILE_COLOR, PROJECTILE_RADIUS, SHOT_DAMAGE, SPRITE_NPC_HEIGHT,
+        SPRITE_NPC_WIDTH, SPRITE_OTHER_PLAYER_HEIGHT, SPRITE_OTHER_PLAYER_WIDTH,
+        WALL_COLOR_PRIMARY, WALL_COLOR_SECONDARY, WIDTH,
     },
-    spritesheet::SpriteSheet,
+    player::Player,
+    spritesheet::{SpriteSheet, hue_variation, sprite_sheet_path},
     textures::TextureManager,
 };
-use rusttype::{Font, Scale, point};
+use rusttype::Font;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum MenuHover {
     Quit,
     MouseSensitivity,
+    HorizontalSensitivity,
+    VerticalSensitivity,
+    GamepadSensitivity,
+    InvertY,
+    SprintMode,
+    CameraBob,
+    Tracers,
+    FireShake,
+    RenderScale,
+    Fov,
+    TexturedFloor,
+    DistanceFog,
+    KeyBindings,
+    // items on the key bindings screen
+    Back,
+    Binding(Action),
+    VoteKick,
+}
+
+impl MenuHover {
+    /// Items with a value that left/right (or right click) steps down/up
+    pub fn is_adjustable(&self) -> bool {
+        matches!(
+            self,
+            MenuHover::MouseSensitivity
+                | MenuHover::HorizontalSensitivity
+                | MenuHover::VerticalSensitivity
+                | MenuHover::GamepadSensitivity
+                | MenuHover::RenderScale
+                | MenuHover::Fov
+        )
+    }
+}
+
+/// Whose eyes the game is seen through. A dead player can watch someone still alive until respawn.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Camera {
+    #[default]
+    Own,
+    Spectating(u64),
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -58,19 +124,158 @@ fn get_direction(player_angle: f32, camera_angle: f32) -> Direction {
     }
 }
 
+/// Where a ray entering a door tile through the given side meets the door, a panel across the
+/// middle of the tile. Returns the perpendicular distance, or None if the ray leaves the tile
+/// through a side first or passes the part of the panel that has slid open.
+fn door_panel_hit(
+    from: (f32, f32),
+    ray_dir: (f32, f32),
+    tile: (usize, usize),
+    side: i32,
+    openness: f32,
+) -> Option<f32> {
+    let (distance, along, tile_along) = if side == 0 {
+        let distance = (tile.0 as f32 + 0.5 - from.0) / ray_dir.0;
+        (distance, from.1 + distance * ray_dir.1, tile.1)
+    } else {
+        let distance = (tile.1 as f32 + 0.5 - from.1) / ray_dir.1;
+        (distance, from.0 + distance * ray_dir.0, tile.0)
+    };
+    if along.floor() as isize != tile_along as isize || along - along.floor() < openness {
+        return None;
+    }
+    Some(distance)
+}
+
+/// Blends a sprite pixel towards the shimmer color in diagonal bands that move with the phase
+/// Mixes a color towards the background, fully at t = 1.0
+fn fade_towards(color: u32, background: u32, t: f32) -> u32 {
+    let channel = |shift: u32| {
+        let from = ((color >> shift) & 0xFF) as f32;
+        let to = ((background >> shift) & 0xFF) as f32;
+        ((from + (to - from) * t) as u32) << shift
+    };
+    (color & 0xFF00_0000) | channel(16) | channel(8) | channel(0)
+}
+
+/// Adds muzzle flash light to a color, light is 0.0 to 1.0
+fn light_with_flash(color: u32, light: f32) -> u32 {
+    let channel = |shift: u32| {
+        let base = ((color >> shift) & 0xFF) as f32;
+        let added = ((MUZZLE_FLASH_COLOR >> shift) & 0xFF) as f32 * light * MUZZLE_FLASH_STRENGTH;
+        ((base + added).min(255.0) as u32) << shift
+    };
+    (color & 0xFF00_0000) | channel(16) | channel(8) | channel(0)
+}
+
+/// How brightly muzzle flashes at (x, y, strength) light a map position
+fn muzzle_light(flashes: &[(f32, f32, f32)], x: f32, y: f32) -> f32 {
+    flashes
+        .iter()
+        .map(|(flash_x, flash_y, strength)| {
+            let distance = (x - flash_x).hypot(y - flash_y);
+            strength * (1.0 - distance / MUZZLE_FLASH_RADIUS).max(0.0)
+        })
+        .sum::<f32>()
+        .min(1.0)
+}
+
+fn shimmer_pixel(color: u32, diagonal: usize, phase: f32) -> u32 {
+    let t =
+        ((diagonal as f32 * 0.08 - phase).sin() * 0.5 + 0.5) * SPAWN_PROTECTION_SHIMMER_STRENGTH;
+    let channel = |shift: u32| {
+        let from = ((color >> shift) & 0xFF) as f32;
+        let to = ((SPAWN_PROTECTION_SHIMMER_COLOR >> shift) & 0xFF) as f32;
+        ((from + (to - from) * t) as u32) << shift
+    };
+    (color & 0xFF00_0000) | channel(16) | channel(8) | channel(0)
+}
+
 pub struct Renderer<'a> {
     pub buffer: Vec<u32>,
     pub z_buffer: Vec<f32>,
+    // Size the world is raycast at, in the top left of the buffer before being stretched over it.
+    // The HUD is always drawn at the full WIDTH x HEIGHT.
+    render_width: usize,
+    render_height: usize,
+    // Half the width of the camera plane one unit in front of the eye, tan(fov / 2)
+    camera_plane_scale: f32,
     pub texture_manager: TextureManager,
     pub sprite_sheets: HashMap<String, SpriteSheet>,
+    failed_sprite_sheets: HashSet<String>,
     // Transient hit marker state: when set, renderer will flash a marker at screen center
     hit_marker_start: Option<Instant>,
     hit_marker_color: u32,
-    hit_marker_duration: Duration,
+    pub hit_marker_duration: Duration,
     // Transient damage flash state: when set, renderer will flash a red overlay
     damage_flash_start: Option<Instant>,
     damage_flash_duration: Duration,
-    font: Font<'a>,
+    // White flash fading out after the local player goes through a teleporter
+    teleport_flash_start: Option<Instant>,
+    // When each player last fired, for lighting up their surroundings
+    muzzle_flashes: HashMap<u64, Instant>,
+    font: GlyphCache<'a>,
+    // Camera bob while walking
+    pub camera_bob: bool,
+    pub camera_bob_amplitude: f32,
+    pub camera_bob_frequency: f32,
+    bob_distance: f32,
+    bob_strength: f32,
+    bob_last_pos: Option<(f32, f32)>,
+    // Smoothed walking speed in tiles per second, for swinging the gun wider while sprinting
+    bob_speed: f32,
+    bob_updated: Instant,
+    // Own feet and shadow when looking down, stepping along with the bob
+    pub show_body: bool,
+    announcement: Option<(String, Instant, Duration)>,
+    // Running vote to kick a player, with when its tally arrived
+    vote_kick: Option<(VoteKickStatus, Instant)>,
+    // Time left in the warmup when the server last said, and when that was
+    warmup: Option<(Duration, Instant)>,
+    // Time left in the match with a time limit, likewise
+    round_time: Option<(Duration, Instant)>,
+    // Recent shot tracers with the time they arrived
+    pub show_tracers: bool,
+    tracers: Vec<(Tracer, Instant)>,
+    // Shots in flight with --projectiles, as of the server's last update
+    projectiles: Vec<Projectile>,
+    // Damage dealt by this player, floating up from where the target was hit
+    damage_numbers: Vec<DamageNumber>,
+    // Screen shake from taking hits and, if fire_shake is on, from firing
+    pub fire_shake: bool,
+    shake_intensity: f32,
+    shake_updated: Instant,
+    // Camera height and pitch of the last rendered frame, for projecting overlays
+    last_camera: (f32, isize),
+    // Floor and ceiling textures instead of the flat colors
+    pub textured_floor: bool,
+    // Blend everything towards FOG_COLOR with distance
+    pub distance_fog: bool,
+    // Walls and sprites farther than this are not drawn, None draws everything
+    pub view_distance: Option<f32>,
+    // Anti-aliased minimap lines and dots, off keeps the cheaper aliased drawing
+    pub smooth_minimap: bool,
+    // Minimap fog of war, one flag per tile set once the player has seen it
+    pub fog_of_war: bool,
+    pub(crate) explored: Vec<bool>,
+    pub(crate) explored_width: usize,
+    // Spawn protection length announced by the server and when the local player's started
+    pub spawn_protection: Duration,
+    protection_start: Option<Instant>,
+    // Points the server plays to, shown next to each player's on the leaderboard
+    pub score_to_win: usize,
+    // Debug free camera, drawn from instead of the player's own view while set
+    pub free_camera: Option<Player>,
+    pub camera: Camera,
+    // Reference time for blinking HUD elements
+    created: Instant,
+}
+
+struct DamageNumber {
+    damage: u16,
+    killed: bool,
+    position: (f32, f32, f32),
+    start: Instant,
 }
 
 struct SpriteInfo<'a> {
@@ -82,6 +287,7 @@ struct SpriteInfo<'a> {
     height: f32,
     dist_sq: f32,
     frame: Option<&'a textures::Texture>,
+    shimmer: bool,
 }
 
 impl<'a> Renderer<'a> {
@@ -95,21 +301,118 @@ impl<'a> Renderer<'a> {
         Renderer {
             buffer: vec![0; WIDTH * HEIGHT],
             z_buffer: vec![0.0; WIDTH],
+            render_width: WIDTH,
+            render_height: HEIGHT,
+            camera_plane_scale: (DEFAULT_FOV.to_radians() / 2.0).tan(),
             texture_manager,
             sprite_sheets,
+            failed_sprite_sheets: HashSet::new(),
             hit_marker_start: None,
             hit_marker_color: 0x00FFFFFF,
             hit_marker_duration: HIT_MARKER_DURATION,
             damage_flash_start: None,
             damage_flash_duration: DAMAGE_FLASH_DURATION,
-            font,
+            teleport_flash_start: None,
+            muzzle_flashes: HashMap::new(),
+            font: GlyphCache::new(font),
+            camera_bob: DEFAULT_CAMERA_BOB,
+            camera_bob_amplitude: DEFAULT_CAMERA_BOB_AMPLITUDE,
+            camera_bob_frequency: DEFAULT_CAMERA_BOB_FREQUENCY,
+            bob_distance: 0.0,
+            bob_strength: 0.0,
+            bob_last_pos: None,
+            bob_speed: 0.0,
+            bob_updated: Instant::now(),
+            show_body: DEFAULT_SHOW_BODY,
+            announcement: None,
+            vote_kick: None,
+            warmup: None,
+            round_time: None,
+            show_tracers: DEFAULT_SHOW_TRACERS,
+            tracers: Vec::new(),
+            projectiles: Vec::new(),
+            damage_numbers: Vec::new(),
+            fire_shake: DEFAULT_FIRE_SHAKE,
+            shake_intensity: 0.0,
+            shake_updated: Instant::now(),
+            last_camera: (0.0, 0),
+            textured_floor: DEFAULT_TEXTURED_FLOOR,
+            distance_fog: DEFAULT_DISTANCE_FOG,
+            view_distance: None,
+            smooth_minimap: DEFAULT_SMOOTH_MINIMAP,
+            fog_of_war: DEFAULT_FOG_OF_WAR,
+            explored: Vec::new(),
+            explored_width: 0,
+            spawn_protection: Duration::ZERO,
+            score_to_win: DEFAULT_SCORE_TO_WIN,
+            protection_start: None,
+            free_camera: None,
+            camera: Camera::Own,
+            created: Instant::now(),
         }
     }
 
     // Trigger a transient hit marker flash (caller decides color).
-    pub fn show_hit_marker(&mut self, color: u32) {
+    /// Flash the hit marker, brighter the more damage the shot did and in their own colors for a
+    /// kill or a headshot
+    pub fn show_hit_marker(&mut self, damage: u16, killed: bool, kind: HitKind) {
         self.hit_marker_start = Some(Instant::now());
-        self.hit_marker_color = color;
+        self.hit_marker_color = if killed {
+            HIT_MARKER_KILL_COLOR
+        } else if kind == HitKind::Head {
+            HIT_MARKER_HEADSHOT_COLOR
+        } else {
+            let t = (damage as f32 / SHOT_DAMAGE as f32).clamp(0.0, 1.0);
+            let channel = |shift: u32| {
+                let graze = ((HIT_MARKER_GRAZE_COLOR >> shift) & 0xFF) as f32;
+                let solid = ((HIT_MARKER_SOLID_COLOR >> shift) & 0xFF) as f32;
+                ((graze + (solid - graze) * t) as u32) << shift
+            };
+            channel(16) | channel(8) | channel(0)
+        };
+    }
+
+    // Load and cache the sprite sheet for a player texture, if it isn't loaded yet.
+    pub fn load_sprite_sheet(&mut self, texture: &str) {
+        if self.sprite_sheets.contains_key(texture) || self.failed_sprite_sheets.contains(texture) {
+            return;
+        }
+        let sheet =
+            hue_variation(texture).and_then(|_| SpriteSheet::new(&sprite_sheet_path(texture)));
+        match sheet {
+            Ok(sheet) => {
+                self.sprite_sheets.insert(texture.to_string(), sheet);
+            }
+            Err(e) => {
+                eprintln!("Failed to load sprite sheet for texture {texture}: {e}");
+                self.failed_sprite_sheets.insert(texture.to_string());
+            }
+        }
+    }
+
+    pub fn add_damage_number(&mut self, damage: u16, killed: bool, position: (f32, f32, f32)) {
+        self.damage_numbers.push(DamageNumber {
+            damage,
+            killed,
+            position,
+            start: Instant::now(),
+        });
+    }
+
+    // Show a banner (e.g. a kill streak) near the top of the screen for a moment.
+    pub fn show_announcement(&mut self, text: String, duration: Duration) {
+        self.announcement = Some((text, Instant::now(), duration));
+    }
+
+    // Queue a shot tracer to be drawn for a moment.
+    pub fn add_tracer(&mut self, tracer: Tracer) {
+        if self.show_tracers {
+            self.tracers.push((tracer, Instant::now()));
+        }
+    }
+
+    pub fn set_projectiles(&mut self, projectiles: Vec<Projectile>) {
+        self.projectiles = projectiles;
     }
 
     // Trigger a transient damage flash (red overlay).
@@ -117,6 +420,540 @@ impl<'a> Renderer<'a> {
         self.damage_flash_start = Some(Instant::now());
     }
 
+    pub fn show_teleport_flash(&mut self) {
+        self.teleport_flash_start = Some(Instant::now());
+    }
+
+    pub fn show_muzzle_flash(&mut self, shooter_id: u64) {
+        self.muzzle_flashes.insert(shooter_id, Instant::now());
+    }
+
+    /// Positions of the muzzle flashes still lighting their surroundings, with how bright they
+    /// still are from 1.0 when fired down to 0.0
+    fn active_muzzle_flashes(&mut self, game_state: &GameState) -> Vec<(f32, f32, f32)> {
+        self.muzzle_flashes
+            .retain(|_, fired| fired.elapsed() < MUZZLE_FLASH_DURATION);
+        self.muzzle_flashes
+            .iter()
+            .filter_map(|(id, fired)| {
+                let shooter = game_state.players.get(&id.to_string())?;
+                let strength =
+                    1.0 - fired.elapsed().as_secs_f32() / MUZZLE_FLASH_DURATION.as_secs_f32();
+                Some((shooter.x, shooter.y, strength))
+            })
+            .collect()
+    }
+
+    /// Brightens the middle of the view around the gun's muzzle after the player's own shot
+    fn render_own_muzzle_glow(&mut self, my_id: u64) {
+        let Some(fired) = self.muzzle_flashes.get(&my_id) else {
+            return;
+        };
+        let strength = 1.0 - fired.elapsed().as_secs_f32() / MUZZLE_FLASH_DURATION.as_secs_f32();
+        if strength <= 0.0 {
+            return;
+        }
+        let (w, h) = (self.render_width, self.render_height);
+        let radius = h as f32 * MUZZLE_FLASH_GLOW_SIZE;
+        let (center_x, center_y) = (w as f32 / 2.0, h as f32 / 2.0);
+        let start_y = (center_y - radius).max(0.0) as usize;
+        let end_y = ((center_y + radius) as usize).min(h);
+        let start_x = (center_x - radius).max(0.0) as usize;
+        let end_x = ((center_x + radius) as usize).min(w);
+        for y in start_y..end_y {
+            for x in start_x..end_x {
+                let distance = (x as f32 - center_x).hypot(y as f32 - center_y);
+                let light = strength * (1.0 - distance / radius);
+                if light > 0.0 {
+                    let pixel = &mut self.buffer[y * WIDTH + x];
+                    *pixel = light_with_flash(*pixel, light);
+                }
+            }
+        }
+    }
+
+    /// Raycasts the world at this share of the window's resolution, 1.0 for full resolution
+    pub fn set_render_scale(&mut self, scale: f32) {
+        self.render_width = ((WIDTH as f32 * scale).round() as usize).clamp(1, WIDTH);
+        self.render_height = ((HEIGHT as f32 * scale).round() as usize).clamp(1, HEIGHT);
+    }
+
+    /// Horizontal field of view in degrees, kept within FOV_MIN..=FOV_MAX
+    pub fn set_fov(&mut self, degrees: f32) {
+        self.camera_plane_scale = (degrees.clamp(FOV_MIN, FOV_MAX).to_radians() / 2.0).tan();
+    }
+
+    /// Stretches the world, raycast at render_width x render_height in the top left corner of the
+    /// buffer, over the whole buffer. Goes backwards so no pixel is overwritten before it's read.
+    fn upscale(&mut self) {
+        let (w, h) = (self.render_width, self.render_height);
+        if (w, h) == (WIDTH, HEIGHT) {
+            return;
+        }
+        for y in (0..HEIGHT).rev() {
+            let src_row = y * h / HEIGHT * WIDTH;
+            for x in (0..WIDTH).rev() {
+                self.buffer[y * WIDTH + x] = self.buffer[src_row + x * w / WIDTH];
+            }
+        }
+    }
+
+    /// Shakes the screen after the local player is hit
+    pub fn shake_from_hit(&mut self) {
+        self.add_shake(SHAKE_HIT_INTENSITY);
+    }
+
+    /// Shakes the screen a little after firing, unless turned off in the menu
+    pub fn shake_from_firing(&mut self) {
+        if self.fire_shake {
+            self.add_shake(SHAKE_FIRE_INTENSITY);
+        }
+    }
+
+    fn add_shake(&mut self, amount: f32) {
+        self.decay_shake();
+        self.shake_intensity = (self.shake_intensity + amount).min(SHAKE_MAX);
+    }
+
+    /// Lets the shake die down for the time since the last update, settling at exactly zero
+    fn decay_shake(&mut self) {
+        let elapsed = self.shake_updated.elapsed().as_secs_f32();
+        self.shake_updated = Instant::now();
+        self.shake_intensity *= (-SHAKE_DECAY * elapsed).exp();
+        if self.shake_intensity < 0.01 {
+            self.shake_intensity = 0.0;
+        }
+    }
+
+    /// Random vertical offset in pixels for this frame's shake, 0 once it has settled
+    fn shake_offset(&mut self) -> isize {
+        self.decay_shake();
+        if self.shake_intensity == 0.0 {
+            return 0;
+        }
+        let max = self.shake_intensity * SHAKE_PIXELS;
+        rand::rng().random_range(-max..=max).round() as isize
+    }
+
+    /// Vertical camera offset from walking, eased in and out so starting and stopping don't snap.
+    /// The walk cycle is tracked even with the bob off, the feet drawn by render_body follow it.
+    fn camera_bob_offset(&mut self, player: &Player) -> f32 {
+        let (last_x, last_y) = self.bob_last_pos.unwrap_or((player.x, player.y));
+        self.bob_last_pos = Some((player.x, player.y));
+        let moved = ((player.x - last_x).powi(2) + (player.y - last_y).powi(2)).sqrt();
+        let elapsed = self.bob_updated.elapsed().as_secs_f32();
+        self.bob_updated = Instant::now();
+
+        let walking = moved > 0.0 && player.z == 0.0 && player.health > 0;
+        if walking {
+            self.bob_distance += moved;
+        }
+        let target = if walking { 1.0 } else { 0.0 };
+        self.bob_strength += (target - self.bob_strength) * 0.15;
+        if elapsed > 0.0 {
+            let speed = if walking { moved / elapsed } else { 0.0 };
+            self.bob_speed += (speed - self.bob_speed) * 0.15;
+        }
+
+        if !self.camera_bob {
+            return 0.0;
+        }
+        self.walk_cycle() * self.camera_bob_amplitude * self.bob_strength
+    }
+
+    /// Where in the walk cycle the player is, from -1.0 to 1.0
+    fn walk_cycle(&self) -> f32 {
+        (self.bob_distance * self.camera_bob_frequency * std::f32::consts::TAU).sin()
+    }
+
+    /// Gun offset in pixels from walking. It sways side to side once every two steps and dips
+    /// with each step, wider the faster the player moves, and settles when they stop.
+    fn gun_bob_offset(&self) -> (isize, isize) {
+        if !self.camera_bob {
+            return (0, 0);
+        }
+        let walk_speed = DEFAULT_PLAYER_MOVE_SPEED * TICK_RATE as f32;
+        let scale = self.bob_strength
+            * (self.bob_speed / walk_speed).clamp(0.0, PLAYER_SPRINT_SPEED_MULTIPLIER);
+        let sway = (self.bob_distance * self.camera_bob_frequency * std::f32::consts::PI).sin();
+        (
+            (sway * GUN_BOB_X * scale).round() as isize,
+            (sway * sway * GUN_BOB_Y * scale).round() as isize,
+        )
+    }
+
+    /// Own shadow and feet rising from the bottom of the screen as the player looks down. The
+    /// feet swing with the walk cycle and the shadow stays on the floor, fading during jumps.
+    fn render_body(&mut self, player: &Player) {
+        let look_down = -player.pitch / PLAYER_PITCH_LIMIT;
+        let rise = ((look_down - BODY_SHOW_PITCH) / (1.0 - BODY_SHOW_PITCH)).clamp(0.0, 1.0);
+        if rise == 0.0 {
+            return;
+        }
+
+        let (foot_w, foot_h) = BODY_FOOT_SIZE;
+        let center_x = WIDTH as f32 / 2.0;
+        let feet_y = HEIGHT as f32 + foot_h - rise * foot_h * 2.5;
+
+        let shadow_alpha = SHADOW_ALPHA * (1.0 - player.z / SHADOW_FADE_HEIGHT);
+        if shadow_alpha > 0.0 {
+            Self::draw_floor_shadow(
+                &mut self.buffer,
+                &self.z_buffer,
+                (center_x, feet_y + foot_h * 0.5),
+                BODY_FOOT_SPACING + foot_w * 2.0,
+                // drawn over the stretched world, where the depths no longer line up
+                f32::NEG_INFINITY,
+                shadow_alpha,
+            );
+        }
+
+        let step = self.walk_cycle() * BODY_STRIDE * self.bob_strength;
+        for (side, stride) in [(-1.0, step), (1.0, -step)] {
+            self.fill_ellipse(
+                (center_x + side * BODY_FOOT_SPACING, feet_y - stride),
+                (foot_w, foot_h),
+                BODY_FOOT_COLOR,
+            );
+        }
+    }
+
+    /// Solid ellipse with a one pixel soft edge
+    fn fill_ellipse(&mut self, center: (f32, f32), radii: (f32, f32), color: u32) {
+        let start_x = (center.0 - radii.0).floor() as i32;
+        let end_x = (center.0 + radii.0).ceil() as i32;
+        let start_y = (center.1 - radii.1).floor() as i32;
+        let end_y = (center.1 + radii.1).ceil() as i32;
+
+        for y in start_y..=end_y {
+            let dy = (y as f32 - center.1) / radii.1;
+            for x in start_x..=end_x {
+                let dx = (x as f32 - center.0) / radii.0;
+                let coverage = ((1.0 - (dx * dx + dy * dy).sqrt()) * radii.0.min(radii.1) + 0.5)
+                    .clamp(0.0, 1.0);
+                if coverage > 0.0 {
+                    self.blend_pixel(x, y, color, coverage);
+                }
+            }
+        }
+    }
+
+    /// Projects a world point (height measured from the floor) to coordinates in the raycast world.
+    /// Multiply by WIDTH / render_width to get frame pixels.
+    /// Returns (screen x, screen y, depth), or None if the point is behind the camera.
+    fn project_to_screen(
+        &self,
+        player: &Player,
+        camera_offset: f32,
+        pitch_offset: isize,
+        point: (f32, f32, f32),
+    ) -> Option<(f32, f32, f32)> {
+        let rel_x = point.0 - player.x;
+        let rel_y = point.1 - player.y;
+
+        let dir_x = player.angle.cos();
+        let dir_y = player.angle.sin();
+        let plane_x = -dir_y * self.camera_plane_scale;
+        let plane_y = dir_x * self.camera_plane_scale;
+
+        let inv_det = 1.0 / (plane_x * dir_y - dir_x * plane_y);
+        let transform_x = inv_det * (dir_y * rel_x - dir_x * rel_y);
+        let transform_y = inv_det * (-plane_y * rel_x + plane_x * rel_y);
+
+        if transform_y <= 0.05 {
+            return None;
+        }
+
+        let (w, h) = (self.render_width as f32, self.render_height as f32);
+        let screen_x = (w / 2.0) * (1.0 + transform_x / transform_y);
+        let screen_y = h / 2.0
+            + pitch_offset as f32
+            + (player.z + camera_offset + 0.5 - point.2) * h / transform_y;
+        Some((screen_x, screen_y, transform_y))
+    }
+
+    /// Blends a color over the buffer pixel, alpha in 0.0..=1.0
+    pub(crate) fn blend_pixel(&mut self, x: i32, y: i32, color: u32, alpha: f32) {
+        if x < 0 || y < 0 || x >= WIDTH as i32 || y >= HEIGHT as i32 {
+            return;
+        }
+        let idx = y as usize * WIDTH + x as usize;
+        let bg = self.buffer[idx];
+        let mix = |shift: u32| {
+            let fg = ((color >> shift) & 0xFF) as f32;
+            let bg = ((bg >> shift) & 0xFF) as f32;
+            ((fg * alpha + bg * (1.0 - alpha)) as u32) << shift
+        };
+        self.buffer[idx] = mix(16) | mix(8) | mix(0);
+    }
+
+    /// How far into the floor and ceiling something this deep has faded, 0.0 near and 1.0 at the
+    /// view distance. The fade ends exactly where drawing stops, so the cutoff leaves no edge.
+    fn view_fade(&self, depth: f32) -> f32 {
+        let Some(max) = self.view_distance else {
+            return 0.0;
+        };
+        let fade_start = max * (1.0 - VIEW_DISTANCE_FADE);
+        ((depth - fade_start) / (max - fade_start)).clamp(0.0, 1.0)
+    }
+
+    /// Blends a color this deep into the distance fog, unchanged with the fog off
+    fn fogged(&self, color: u32, depth: f32) -> u32 {
+        if !self.distance_fog {
+            return color;
+        }
+        fade_towards(color, FOG_COLOR, (depth / FOG_MAX_DISTANCE).clamp(0.0, 1.0))
+    }
+
+    /// Darkens a soft ellipse on the floor, skipping columns hidden behind walls
+    fn draw_floor_shadow(
+        buffer: &mut [u32],
+        z_buffer: &[f32],
+        center: (f32, f32),
+        radius_x: f32,
+        depth: f32,
+        alpha: f32,
+    ) {
+        let radius_y = (radius_x * SHADOW_FLATTEN).max(1.0);
+        let start_x = (center.0 - radius_x).max(0.0) as usize;
+        let end_x = (center.0 + radius_x).min(WIDTH as f32) as usize;
+        let start_y = (center.1 - radius_y).max(0.0) as usize;
+        let end_y = (center.1 + radius_y).min(HEIGHT as f32) as usize;
+
+        for x in start_x..end_x {
+            if depth >= z_buffer[x] {
+                continue;
+            }
+            let dx = (x as f32 - center.0) / radius_x;
+            for y in start_y..end_y {
+                let dy = (y as f32 - center.1) / radius_y;
+                let dist_sq = dx * dx + dy * dy;
+                if dist_sq >= 1.0 {
+                    continue;
+                }
+                // fade towards the edge
+                let keep = 1.0 - alpha * (1.0 - dist_sq);
+                let pixel = &mut buffer[y * WIDTH + x];
+                let scale =
+                    |shift: u32| ((((*pixel >> shift) & 0xFF) as f32 * keep) as u32) << shift;
+                *pixel = (*pixel & 0xFF00_0000) | scale(16) | scale(8) | scale(0);
+            }
+        }
+    }
+
+    /// Fills everything above the horizon with the sky panorama, which wraps once around the
+    /// player. Columns sample it by their ray angle, so it turns with the player, and rows by their
+    /// height above the horizon, so pitching looks over it rather than dragging it along.
+    /// Returns false without a sky texture.
+    fn draw_sky(&mut self, player: &Player, pitch_offset: isize) -> bool {
+        let Some(sky) = self.texture_manager.get_texture("sky") else {
+            return false;
+        };
+        let (w, h) = (self.render_width, self.render_height);
+        let horizon = h as f32 / 2.0 + pitch_offset as f32;
+        // the top of the sky is just in view when looking up as far as possible
+        let sky_rows = h as f32 / 2.0 * (1.0 + PLAYER_PITCH_LIMIT);
+
+        let columns: Vec<u32> = (0..w)
+            .map(|x| {
+                let camera_x = 2.0 * x as f32 / w as f32 - 1.0;
+                let ray_angle = player.angle + (camera_x * self.camera_plane_scale).atan();
+                let u = (ray_angle / std::f32::consts::TAU).rem_euclid(1.0);
+                ((u * sky.width as f32) as u32).min(sky.width - 1)
+            })
+            .collect();
+        for y in 0..(horizon.clamp(0.0, h as f32) as usize) {
+            let v = 1.0 - (horizon - y as f32) / sky_rows;
+            let tex_y = ((v.max(0.0) * sky.height as f32) as u32).min(sky.height - 1);
+            let row = &sky.pixels[(tex_y * sky.width) as usize..];
+            for (x, tex_x) in columns.iter().enumerate() {
+                self.buffer[y * WIDTH + x] = row[*tex_x as usize];
+            }
+        }
+        true
+    }
+
+    /// Textures the floor and ceiling by casting each pixel back onto the map. The ceiling is at the
+    /// top of the walls. Rows fade into the flat colors towards the view distance like walls do.
+    fn cast_floor_and_ceiling(&mut self, player: &Player, camera_offset: f32, pitch_offset: isize) {
+        let (Some(floor), Some(ceiling)) = (
+            self.texture_manager.get_texture("floor"),
+            self.texture_manager.get_texture("ceiling"),
+        ) else {
+            return;
+        };
+        let eye_height = player.z + camera_offset + 0.5;
+        let dir_x = player.angle.cos();
+        let dir_y = player.angle.sin();
+        let plane_x = -dir_y * self.camera_plane_scale;
+        let plane_y = dir_x * self.camera_plane_scale;
+        let (w, h) = (self.render_width, self.render_height);
+        let horizon = h as f32 / 2.0 + pitch_offset as f32;
+
+        for y in 0..h {
+            let row = y as f32 + 0.5;
+            // distance along the view direction to where this row meets the floor or ceiling
+            let (texture, background, row_depth) = if row > horizon {
+                (floor, FLOOR_COLOR, eye_height * h as f32 / (row - horizon))
+            } else {
+                (
+                    ceiling,
+                    CEILING_COLOR,
+                    (1.0 - eye_height) * h as f32 / (horizon - row),
+                )
+            };
+            // a jump can lift the eye past the ceiling, where there's nothing to see above
+            if row_depth <= 0.0 {
+                continue;
+            }
+            let background = self.fogged(background, row_depth);
+            let fade = self.view_fade(row_depth);
+            if fade >= 1.0 {
+                continue;
+            }
+
+            // map position under the left edge of the row, stepped one pixel at a time
+            let mut world_x = player.x + row_depth * (dir_x - plane_x);
+            let mut world_y = player.y + row_depth * (dir_y - plane_y);
+            let step_x = row_depth * plane_x * 2.0 / w as f32;
+            let step_y = row_depth * plane_y * 2.0 / w as f32;
+            for x in 0..w {
+                let tex_x = ((world_x - world_x.floor()) * texture.width as f32) as u32;
+                let tex_y = ((world_y - world_y.floor()) * texture.height as f32) as u32;
+                let color = texture.pixels[(tex_y.min(texture.height - 1) * texture.width
+                    + tex_x.min(texture.width - 1))
+                    as usize];
+                let color = self.fogged(color, row_depth);
+                self.buffer[y * WIDTH + x] = if fade > 0.0 {
+                    fade_towards(color, background, fade)
+                } else {
+                    color
+                };
+                world_x += step_x;
+                world_y += step_y;
+            }
+        }
+    }
+
+    /// Colors the floor inside gravity zones, on teleporters and on jump pads by casting each floor
+    /// pixel back onto the map. Jump pads pulse.
+    fn tint_floor_zones(
+        &mut self,
+        game_state: &GameState,
+        player: &Player,
+        camera_offset: f32,
+        pitch_offset: isize,
+    ) {
+        let eye_height = player.z + camera_offset + 0.5;
+        let dir_x = player.angle.cos();
+        let dir_y = player.angle.sin();
+        let plane_x = -dir_y * self.camera_plane_scale;
+        let plane_y = dir_x * self.camera_plane_scale;
+        let (w, h) = (self.render_width, self.render_height);
+        let horizon = h as f32 / 2.0 + pitch_offset as f32;
+        let world = &game_state.world;
+        let pulse_phase =
+            self.created.elapsed().as_secs_f32() / JUMP_PAD_PULSE_PERIOD.as_secs_f32();
+        let pad_color = fade_towards(
+            JUMP_PAD_FLOOR_COLOR,
+            JUMP_PAD_PULSE_COLOR,
+            0.5 - 0.5 * (pulse_phase * std::f32::consts::TAU).cos(),
+        );
+
+        for y in (horizon.max(0.0) as usize)..h {
+            let rows_below = y as f32 + 0.5 - horizon;
+            if rows_below <= 0.0 {
+                continue;
+            }
+            // distance along the view direction to where this row meets the floor
+            let row_depth = eye_height * h as f32 / rows_below;
+            for x in 0..w {
+                let camera_x = 2.0 * x as f32 / w as f32 - 1.0;
+                let floor_x = player.x + row_depth * (dir_x + plane_x * camera_x);
+                let floor_y = player.y + row_depth * (dir_y + plane_y * camera_x);
+                let gravity = world.gravity_at(floor_x, floor_y);
+                let tint = if world
+                    .teleport_destination(floor_x as usize, floor_y as usize)
+                    .is_some()
+                {
+                    TELEPORTER_FLOOR_COLOR
+                } else if world.jump_pad_at(floor_x as usize, floor_y as usize) {
+                    pad_color
+                } else if gravity < 1.0 {
+                    LOW_GRAVITY_FLOOR_COLOR
+                } else if gravity > 1.0 {
+                    HIGH_GRAVITY_FLOOR_COLOR
+                } else {
+                    continue;
+                };
+                self.buffer[y * WIDTH + x] = self.fogged(tint, row_depth);
+            }
+        }
+    }
+
+    /// Draws projectiles as glowing dots that shrink with distance, hidden behind walls
+    fn render_projectiles(&mut self, player: &Player, camera_offset: f32, pitch_offset: isize) {
+        for i in 0..self.projectiles.len() {
+            let position = self.projectiles[i].position;
+            let Some((x, y, depth)) =
+                self.project_to_screen(player, camera_offset, pitch_offset, position)
+            else {
+                continue;
+            };
+            if x < 0.0 || x >= self.render_width as f32 || depth >= self.z_buffer[x as usize] {
+                continue;
+            }
+            let radius = (PROJECTILE_RADIUS * self.render_height as f32 / depth).max(1.0);
+            self.fill_ellipse((x, y), (radius, radius), PROJECTILE_COLOR);
+        }
+    }
+
+    /// Draws fading tracer lines, split into segments so walls in front can hide parts of them
+    fn render_tracers(&mut self, player: &Player, camera_offset: f32, pitch_offset: isize) {
+        self.tracers
+            .retain(|(_, start)| start.elapsed() < TRACER_DURATION);
+
+        for i in 0..self.tracers.len() {
+            let (tracer, start) = self.tracers[i].clone();
+            let alpha = 1.0 - start.elapsed().as_secs_f32() / TRACER_DURATION.as_secs_f32();
+
+            let point_at = |t: f32| {
+                (
+                    tracer.start.0 + (tracer.end.0 - tracer.start.0) * t,
+                    tracer.start.1 + (tracer.end.1 - tracer.start.1) * t,
+                    tracer.start.2 + (tracer.end.2 - tracer.start.2) * t,
+                )
+            };
+
+            for segment in 0..TRACER_SEGMENTS {
+                let t0 = segment as f32 / TRACER_SEGMENTS as f32;
+                let t1 = (segment + 1) as f32 / TRACER_SEGMENTS as f32;
+                let a = self.project_to_screen(player, camera_offset, pitch_offset, point_at(t0));
+                let b = self.project_to_screen(player, camera_offset, pitch_offset, point_at(t1));
+                let (Some((x0, y0, depth0)), Some((x1, y1, depth1))) = (a, b) else {
+                    continue;
+                };
+
+                // Skip the segment if a wall is in front of it
+                let mid_x = ((x0 + x1) / 2.0) as isize;
+                if mid_x < 0 || mid_x >= self.render_width as isize {
+                    continue;
+                }
+                if (depth0 + depth1) / 2.0 >= self.z_buffer[mid_x as usize] {
+                    continue;
+                }
+
+                let mut points = Vec::new();
+                line_points(x0 as i32, y0 as i32, x1 as i32, y1 as i32, |x, y| {
+                    points.push((x, y))
+                });
+                for (x, y) in points {
+                    self.blend_pixel(x, y, TRACER_COLOR, alpha);
+                }
+            }
+        }
+    }
+
     fn draw_sprite_2d(
         &mut self,
         texture: &textures::Texture,
@@ -149,37 +986,90 @@ impl<'a> Renderer<'a> {
         }
     }
 
+    /// The player followed while spectating, None when seeing through one's own eyes
+    fn spectated<'g>(&self, game_state: &'g GameState) -> Option<(u64, &'g Player)> {
+        match self.camera {
+            Camera::Own => None,
+            Camera::Spectating(id) => game_state.players.get(&id.to_string()).map(|p| (id, p)),
+        }
+    }
+
     pub fn render(&mut self, game_state: &GameState, my_id: u64) {
-        if let Some(player) = game_state.players.get(&my_id.to_string()) {
-            let pitch_offset = (player.pitch * HEIGHT as f32 / 2.0) as isize;
-            let horizon = (HEIGHT as isize / 2 + pitch_offset).clamp(0, HEIGHT as isize) as usize;
+        let free_camera = self.free_camera.clone();
+        let spectated = self.spectated(game_state);
+        // own body, gun and crosshair only belong to the player's own view
+        let own_view = free_camera.is_none() && spectated.is_none();
+        let viewer_id = spectated.map_or(my_id, |(id, _)| id);
+        if let Some(player) = free_camera
+            .as_ref()
+            .or(spectated.map(|(_, player)| player))
+            .or_else(|| game_state.players.get(&my_id.to_string()))
+        {
+            // the world is drawn at the render size, rows are still WIDTH apart in the buffer
+            let (w, h) = (self.render_width, self.render_height);
+            let shake = self.shake_offset() * h as isize / HEIGHT as isize;
+            let pitch_offset = (player.pitch * h as f32 / 2.0) as isize + shake;
+            let horizon = (h as isize / 2 + pitch_offset).clamp(0, h as isize) as usize;
+
+            let camera_bob = if !own_view {
+                0.0
+            } else {
+                self.camera_bob_offset(player)
+            };
+            let camera_offset = if player.health > 0 {
+                player.camera_height() + camera_bob
+            } else {
+                // slump down over the dying animation
+                CAMERA_HEIGHT_OFFSET
+                    + (CAMERA_HEIGHT_OFFSET_DEAD - CAMERA_HEIGHT_OFFSET) * player.death_progress()
+            };
 
-            // Clear the buffer with ceiling and floor colors
-            for y in 0..horizon {
-                for x in 0..WIDTH {
-                    self.buffer[y * WIDTH + x] = CEILING_COLOR;
+            // Clear the buffer with the sky, or the ceiling color under a textured ceiling or
+            // without a sky, and the floor color. Fog thickens the flat colors row by row towards
+            // the horizon.
+            let eye_height = player.z + camera_offset + 0.5;
+            let horizon_row = h as f32 / 2.0 + pitch_offset as f32;
+            if self.textured_floor || !self.draw_sky(player, pitch_offset) {
+                for y in 0..horizon {
+                    let depth = (1.0 - eye_height) * h as f32 / (horizon_row - y as f32 - 0.5);
+                    let color = self.fogged(CEILING_COLOR, depth);
+                    for x in 0..w {
+                        self.buffer[y * WIDTH + x] = color;
+                    }
                 }
             }
-            for y in horizon..HEIGHT {
-                for x in 0..WIDTH {
-                    self.buffer[y * WIDTH + x] = FLOOR_COLOR;
+            for y in horizon..h {
+                let depth = eye_height * h as f32 / (y as f32 + 0.5 - horizon_row);
+                let color = self.fogged(FLOOR_COLOR, depth);
+                for x in 0..w {
+                    self.buffer[y * WIDTH + x] = color;
                 }
             }
 
-            let camera_offset = if player.health > 0 {
-                CAMERA_HEIGHT_OFFSET
-            } else {
-                CAMERA_HEIGHT_OFFSET_DEAD
-            };
+            if self.textured_floor {
+                self.cast_floor_and_ceiling(player, camera_offset, pitch_offset);
+            }
+            if !game_state.world.gravity_zones.is_empty()
+                || !game_state.world.teleporters.is_empty()
+                || !game_state.world.jump_pads.is_empty()
+            {
+                self.tint_floor_zones(game_state, player, camera_offset, pitch_offset);
+            }
+
+            self.last_camera = (camera_offset, pitch_offset);
+
+            // animated wall textures all run on the same clock
+            let animation_time = self.created.elapsed();
+            let flashes = self.active_muzzle_flashes(game_state);
 
             // cast one ray for each pixel in width
-            for x in 0..WIDTH {
+            for x in 0..w {
                 // ray direction
-                let camera_x = 2.0 * x as f32 / WIDTH as f32 - 1.0;
+                let camera_x = 2.0 * x as f32 / w as f32 - 1.0;
                 let ray_dir_x =
-                    player.angle.cos() + CAMERA_PLANE_SCALE * camera_x * (-player.angle.sin());
+                    player.angle.cos() + self.camera_plane_scale * camera_x * (-player.angle.sin());
                 let ray_dir_y =
-                    player.angle.sin() + CAMERA_PLANE_SCALE * camera_x * player.angle.cos();
+                    player.angle.sin() + self.camera_plane_scale * camera_x * player.angle.cos();
 
                 // direction and steps to measure if wall was hit
                 let mut map_x = player.x as usize;
@@ -208,10 +1098,20 @@ impl<'a> Renderer<'a> {
                     wall_dist_y = (map_y as f32 + 1.0 - player.y) * delta_dist_y;
                 }
 
-                // find wall hits
+                // find wall hits, giving up past the view distance. The side distances are
+                // along the ray, the limit is perpendicular to the camera plane.
+                let ray_length = ray_dir_x.hypot(ray_dir_y);
+                let max_ray_dist = self
+                    .view_distance
+                    .map_or(f32::INFINITY, |max| max * ray_length);
                 let mut hit = false;
                 let mut wall_type = 0;
+                // distance to a door panel hit and how far that door has slid aside
+                let mut door_hit: Option<(f32, f32)> = None;
                 while !hit {
+                    if wall_dist_x.min(wall_dist_y) > max_ray_dist {
+                        break;
+                    }
                     if wall_dist_x < wall_dist_y {
                         wall_dist_x += delta_dist_x;
                         map_x = (map_x as isize + step_x) as usize;
@@ -222,39 +1122,71 @@ impl<'a> Renderer<'a> {
                         wall_type = 1;
                     }
 
-                    if game_state.world.get_tile(map_x, map_y) > 0 {
+                    if let Some(openness) = game_state.world.door_openness(map_x, map_y) {
+                        door_hit = door_panel_hit(
+                            (player.x, player.y),
+                            (ray_dir_x, ray_dir_y),
+                            (map_x, map_y),
+                            wall_type,
+                            openness,
+                        )
+                        .map(|distance| (distance, openness));
+                        hit = door_hit.is_some();
+                    } else if game_state.world.get_tile(map_x, map_y) > 0 {
                         hit = true;
                     }
                 }
 
                 // how far wall hit was
-                let perp_wall_dist = if wall_type == 0 {
+                let perp_wall_dist = if let Some((distance, _)) = door_hit {
+                    distance
+                } else if wall_type == 0 {
                     (map_x as f32 - player.x + (1.0 - step_x as f32) / 2.0) / ray_dir_x
                 } else {
                     (map_y as f32 - player.y + (1.0 - step_y as f32) / 2.0) / ray_dir_y
                 };
 
+                // nothing in sight, the column keeps its floor and ceiling
+                if !hit || self.view_fade(perp_wall_dist) >= 1.0 {
+                    self.z_buffer[x] = f32::INFINITY;
+                    continue;
+                }
                 self.z_buffer[x] = perp_wall_dist;
+                let fade = self.view_fade(perp_wall_dist);
+                let light = muzzle_light(
+                    &flashes,
+                    player.x + perp_wall_dist * ray_dir_x,
+                    player.y + perp_wall_dist * ray_dir_y,
+                );
 
                 // line hight from distance, start and end points account for jump, pitch and camera offset
-                let line_height = (HEIGHT as f32 / perp_wall_dist) as isize;
+                let line_height = (h as f32 / perp_wall_dist) as isize;
                 let z_offset = ((player.z + camera_offset) * line_height as f32) as isize;
-                let draw_start = (-line_height / 2 + HEIGHT as isize / 2 + pitch_offset + z_offset)
-                    .clamp(0, HEIGHT as isize - 1) as usize;
-                let draw_end = (line_height / 2 + HEIGHT as isize / 2 + pitch_offset + z_offset)
-                    .clamp(0, HEIGHT as isize) as usize;
+                let draw_start = (-line_height / 2 + h as isize / 2 + pitch_offset + z_offset)
+                    .clamp(0, h as isize - 1) as usize;
+                let draw_end = (line_height / 2 + h as isize / 2 + pitch_offset + z_offset)
+                    .clamp(0, h as isize) as usize;
 
                 let wall_tile = game_state.world.get_tile(map_x, map_y);
-                let wall_texture_name = format!("wall{}", wall_tile);
+                let wall_texture_name = if door_hit.is_some() {
+                    "door".to_string()
+                } else {
+                    format!("wall{}", wall_tile)
+                };
 
-                if let Some(texture) = self.texture_manager.get_texture(&wall_texture_name) {
+                if let Some(texture) = self
+                    .texture_manager
+                    .get_frame(&wall_texture_name, animation_time)
+                {
                     // calculate where the wall was hit
                     let wall_x = if wall_type == 0 {
                         player.y + perp_wall_dist * ray_dir_y
                     } else {
                         player.x + perp_wall_dist * ray_dir_x
                     };
-                    let wall_x = wall_x - wall_x.floor();
+                    // a door's texture slides along with it
+                    let wall_x =
+                        wall_x - wall_x.floor() - door_hit.map_or(0.0, |(_, openness)| openness);
 
                     // x coordinate on the texture
                     let mut tex_x = (wall_x * texture.width as f32) as u32;
@@ -264,10 +1196,9 @@ impl<'a> Renderer<'a> {
 
                     // save vertical wall line to buffer
                     for y in draw_start..draw_end {
-                        let tex_y_num =
-                            (y as isize - HEIGHT as isize / 2 - pitch_offset - z_offset
-                                + line_height / 2)
-                                * texture.height as isize;
+                        let tex_y_num = (y as isize - h as isize / 2 - pitch_offset - z_offset
+                            + line_height / 2)
+                            * texture.height as isize;
                         if line_height == 0 {
                             continue;
                         }
@@ -290,7 +1221,18 @@ impl<'a> Renderer<'a> {
                                 let a = (color >> 24) & 0xFF;
                                 (a << 24) | ((r / 2) << 16) | ((g / 2) << 8) | (b / 2)
                             };
-                            self.buffer[y * WIDTH + x] = final_color;
+                            let final_color = if light > 0.0 {
+                                light_with_flash(final_color, light)
+                            } else {
+                                final_color
+                            };
+                            let final_color = self.fogged(final_color, perp_wall_dist);
+                            let pixel = &mut self.buffer[y * WIDTH + x];
+                            *pixel = if fade > 0.0 {
+                                fade_towards(final_color, *pixel, fade)
+                            } else {
+                                final_color
+                            };
                         }
                     }
                 } else {
@@ -300,12 +1242,17 @@ impl<'a> Renderer<'a> {
                     } else {
                         WALL_COLOR_SECONDARY
                     };
+                    let wall_color =
+                        self.fogged(light_with_flash(wall_color, light), perp_wall_dist);
                     for y in draw_start..draw_end {
-                        self.buffer[y * WIDTH + x] = wall_color;
+                        let pixel = &mut self.buffer[y * WIDTH + x];
+                        *pixel = fade_towards(wall_color, *pixel, fade);
                     }
                 }
             }
 
+            let dummy_texture = "character2".to_string();
+
             // floor sprites (puddles) from world
             let mut sprite_infos: Vec<SpriteInfo> = game_state
                 .floor_sprites
@@ -322,6 +1269,7 @@ impl<'a> Renderer<'a> {
                         height: s.height,
                         dist_sq: sprite_x * sprite_x + sprite_y * sprite_y,
                         frame: None,
+                        shimmer: false,
                     }
                 })
                 .collect();
@@ -329,26 +1277,19 @@ impl<'a> Renderer<'a> {
             // sprites from other players
             let mut player_sprites = Vec::new();
             for (id, other_player) in &game_state.players {
-                if id != &my_id.to_string() {
+                // a free camera is away from the player's body, so draw that too
+                if id != &viewer_id.to_string() || free_camera.is_some() {
+                    // skip players whose sheet isn't loaded yet
+                    let Some(sheet) = self.sprite_sheets.get(&other_player.texture) else {
+                        continue;
+                    };
                     let direction = get_direction(other_player.angle, player.angle);
                     let frame = match other_player.animation_state {
-                        Idle => {
-                            &self.sprite_sheets.get(&other_player.texture).unwrap().idle
-                                [direction as usize]
-                        }
-                        Walking => {
-                            &self.sprite_sheets.get(&other_player.texture).unwrap().walk
-                                [direction as usize][other_player.frame]
-                        }
-                        Shooting => {
-                            &self.sprite_sheets.get(&other_player.texture).unwrap().shoot
-                                [direction as usize]
-                        }
-                        Dying => {
-                            &self.sprite_sheets.get(&other_player.texture).unwrap().die
-                                [other_player.frame]
-                        }
-                        Dead => &self.sprite_sheets.get(&other_player.texture).unwrap().dead[0],
+                        Idle => &sheet.idle[direction as usize],
+                        Walking => &sheet.walk[direction as usize][other_player.frame],
+                        Shooting => &sheet.shoot[direction as usize],
+                        Dying => &sheet.die[other_player.frame],
+                        Dead => &sheet.dead[0],
                     };
 
                     let sprite_x = other_player.x - player.x;
@@ -356,16 +1297,35 @@ impl<'a> Renderer<'a> {
                     player_sprites.push(SpriteInfo {
                         x: other_player.x,
                         y: other_player.y,
-                        z: other_player.z,
+                        // a player killed mid-air settles on the floor by the end of the animation
+                        z: other_player.z * (1.0 - other_player.death_progress()),
                         texture: &other_player.texture,
                         width: SPRITE_OTHER_PLAYER_WIDTH,
-                        height: SPRITE_OTHER_PLAYER_HEIGHT,
+                        height: other_player.body_height(),
                         dist_sq: sprite_x * sprite_x + sprite_y * sprite_y,
                         frame: Some(frame),
+                        shimmer: other_player.protected,
                     });
                 }
             }
 
+            // practice dummies stand among the players until they're shot down
+            for dummy in game_state.dummies.iter().filter(|dummy| dummy.health > 0) {
+                let sprite_x = dummy.x - player.x;
+                let sprite_y = dummy.y - player.y;
+                player_sprites.push(SpriteInfo {
+                    x: dummy.x,
+                    y: dummy.y,
+                    z: 0.0,
+                    texture: &dummy_texture,
+                    width: SPRITE_NPC_WIDTH,
+                    height: SPRITE_NPC_HEIGHT,
+                    dist_sq: sprite_x * sprite_x + sprite_y * sprite_y,
+                    frame: None,
+                    shimmer: false,
+                });
+            }
+
             // Sort floor sprites (puddles) by distance
             sprite_infos.sort_by(|a, b| {
                 b.dist_sq
@@ -383,6 +1343,10 @@ impl<'a> Renderer<'a> {
             // Combine sprite vectors so puddles are always behind players
             sprite_infos.append(&mut player_sprites);
 
+            // spawn protected players shimmer with bands of light sweeping across them
+            let shimmer_phase =
+                self.created.elapsed().as_secs_f32() * SPAWN_PROTECTION_SHIMMER_SPEED;
+
             // sprites to buffer
             for sprite_info in sprite_infos {
                 let sprite_x = sprite_info.x - player.x;
@@ -391,41 +1355,60 @@ impl<'a> Renderer<'a> {
                 let dir_x = player.angle.cos();
                 let dir_y = player.angle.sin();
 
-                let plane_x = -dir_y * CAMERA_PLANE_SCALE;
-                let plane_y = dir_x * CAMERA_PLANE_SCALE;
+                let plane_x = -dir_y * self.camera_plane_scale;
+                let plane_y = dir_x * self.camera_plane_scale;
 
                 let inv_det = 1.0 / (plane_x * dir_y - dir_x * plane_y);
                 let transform_x = inv_det * (dir_y * sprite_x - dir_x * sprite_y);
                 let transform_y = inv_det * (-plane_y * sprite_x + plane_x * sprite_y);
 
-                // only draw sprites in front of the player
-                if transform_y > 0.0 {
-                    let sprite_screen_x = (WIDTH as f32 / 2.0) * (1.0 + transform_x / transform_y);
+                // only draw sprites in front of the player and within the view distance
+                let fade = self.view_fade(transform_y);
+                let light = muzzle_light(&flashes, sprite_info.x, sprite_info.y);
+                if transform_y > 0.0 && fade < 1.0 {
+                    let sprite_screen_x = (w as f32 / 2.0) * (1.0 + transform_x / transform_y);
 
                     // put sprite on the floor if its z is 0
-                    let sprite_height = (HEIGHT as f32 / transform_y).abs() * sprite_info.height;
-                    let world_half = (HEIGHT as f32 / transform_y).abs() * 0.5;
+                    let sprite_height = (h as f32 / transform_y).abs() * sprite_info.height;
+                    let world_half = (h as f32 / transform_y).abs() * 0.5;
                     let sprite_vertical_offset =
-                        (player.z + camera_offset - sprite_info.z) * HEIGHT as f32 / transform_y
+                        (player.z + camera_offset - sprite_info.z) * h as f32 / transform_y
                             - sprite_height * 0.5
                             + world_half;
 
                     // start and end points with both z:s, pitch and camera offset accounted for
                     let draw_start_y = (-sprite_height / 2.0
-                        + HEIGHT as f32 / 2.0
+                        + h as f32 / 2.0
                         + pitch_offset as f32
                         + sprite_vertical_offset)
                         .max(0.0) as usize;
                     let draw_end_y = (sprite_height / 2.0
-                        + HEIGHT as f32 / 2.0
+                        + h as f32 / 2.0
                         + pitch_offset as f32
                         + sprite_vertical_offset)
-                        .min(HEIGHT as f32) as usize;
-
-                    let sprite_width = (WIDTH as f32 / transform_y).abs() * sprite_info.width;
+                        .min(h as f32) as usize;
+
+                    let sprite_width = (w as f32 / transform_y).abs() * sprite_info.width;
+
+                    // ground other players with a shadow on the floor below them, fading with height
+                    if sprite_info.frame.is_some() {
+                        let alpha = SHADOW_ALPHA * (1.0 - sprite_info.z / SHADOW_FADE_HEIGHT);
+                        if alpha > 0.0 {
+                            let floor_y = h as f32 / 2.0
+                                + pitch_offset as f32
+                                + (player.z + camera_offset + 0.5) * h as f32 / transform_y;
+                            Self::draw_floor_shadow(
+                                &mut self.buffer,
+                                &self.z_buffer,
+                                (sprite_screen_x, floor_y),
+                                w as f32 / transform_y * SHADOW_RADIUS,
+                                transform_y,
+                                alpha.min(SHADOW_ALPHA),
+                            );
+                        }
+                    }
                     let draw_start_x = (sprite_screen_x - sprite_width / 2.0).max(0.0) as usize;
-                    let draw_end_x =
-                        (sprite_screen_x + sprite_width / 2.0).min(WIDTH as f32) as usize;
+                    let draw_end_x = (sprite_screen_x + sprite_width / 2.0).min(w as f32) as usize;
 
                     // animation frames or static sprites
                     if let Some(raster) = sprite_info
@@ -444,7 +1427,7 @@ impl<'a> Renderer<'a> {
                                 // get pixels on the vertical line
                                 for y in draw_start_y..draw_end_y {
                                     let tex_y = ((y as f32
-                                        - (HEIGHT as f32 / 2.0 - sprite_height / 2.0
+                                        - (h as f32 / 2.0 - sprite_height / 2.0
                                             + pitch_offset as f32
                                             + sprite_vertical_offset as f32))
                                         * raster.height as f32
@@ -458,7 +1441,19 @@ impl<'a> Renderer<'a> {
 
                                         // save to renderer buffer
                                         if alpha > 0 {
-                                            self.buffer[y * WIDTH + stripe] = color;
+                                            let color = if sprite_info.shimmer {
+                                                shimmer_pixel(color, stripe + y, shimmer_phase)
+                                            } else {
+                                                color
+                                            };
+                                            let color = if light > 0.0 {
+                                                light_with_flash(color, light)
+                                            } else {
+                                                color
+                                            };
+                                            let color = self.fogged(color, transform_y);
+                                            let pixel = &mut self.buffer[y * WIDTH + stripe];
+                                            *pixel = fade_towards(color, *pixel, fade);
                                         }
                                     }
                                 }
@@ -468,19 +1463,45 @@ impl<'a> Renderer<'a> {
                 }
             }
 
+            if self.show_tracers {
+                self.render_tracers(player, camera_offset, pitch_offset);
+            }
+            self.render_projectiles(player, camera_offset, pitch_offset);
+            if own_view {
+                self.render_own_muzzle_glow(my_id);
+            }
+            self.upscale();
+
+            // drawn before the gun and crosshair so both stay on top
+            if self.show_body && player.health > 0 && own_view {
+                self.render_body(player);
+            }
+
             // Render minimap overlay
             self.render_minimap(game_state, my_id);
 
-            if player.health > 0 {
+            if player.health > 0 && own_view {
                 // Render gun
                 if let Some(player) = game_state.players.get(&my_id.to_string()) {
-                    let gun_texture_name = if player.shooting { "gunshot" } else { "gun" };
-                    if let Some(gun_texture) =
-                        self.texture_manager.get_texture(gun_texture_name).cloned()
+                    let gun_texture_name = player.current_weapon.texture(player.shooting);
+                    let fallback = if player.shooting { "gunshot" } else { "gun" };
+                    if let Some(gun_texture) = self
+                        .texture_manager
+                        .get_texture(gun_texture_name)
+                        .or_else(|| self.texture_manager.get_texture(fallback))
+                        .cloned()
                     {
-                        let gun_x =
-                            WIDTH - (gun_texture.width as f32 * GUN_SCALE) as usize - GUN_X_OFFSET;
-                        let gun_y = HEIGHT - (gun_texture.height as f32 * GUN_SCALE) as usize;
+                        let (bob_x, bob_y) = self.gun_bob_offset();
+                        let gun_x = (WIDTH
+                            - (gun_texture.width as f32 * GUN_SCALE) as usize
+                            - GUN_X_OFFSET)
+                            .saturating_add_signed(bob_x);
+                        let mut gun_y = (HEIGHT - (gun_texture.height as f32 * GUN_SCALE) as usize)
+                            .saturating_add_signed(bob_y);
+                        // the gun is held low while it's being switched
+                        if player.switching {
+                            gun_y += (gun_texture.height as f32 * GUN_SCALE) as usize / 2;
+                        }
                         self.draw_sprite_2d(&gun_texture, gun_x, gun_y, GUN_SCALE);
                     }
                 }
@@ -525,26 +1546,7 @@ impl<'a> Renderer<'a> {
     }
 
     fn measure_text_bounds(&self, text: &str, size: f32) -> (f32, f32) {
-        let scale = Scale::uniform(size);
-        let mut min_x = f32::INFINITY;
-        let mut max_x = f32::NEG_INFINITY;
-        let mut min_y = f32::INFINITY;
-        let mut max_y = f32::NEG_INFINITY;
-
-        for glyph in self.font.layout(text, scale, point(0.0, 0.0)) {
-            if let Some(bb) = glyph.pixel_bounding_box() {
-                min_x = min_x.min(bb.min.x as f32);
-                max_x = max_x.max(bb.max.x as f32);
-                min_y = min_y.min(bb.min.y as f32);
-                max_y = max_y.max(bb.max.y as f32);
-            }
-        }
-
-        if !min_x.is_finite() {
-            (0.0, 0.0)
-        } else {
-            (max_x - min_x, max_y - min_y)
-        }
+        self.font.measure(text, size)
     }
 
     pub fn fill_rect(
@@ -609,41 +1611,131 @@ impl<'a> Renderer<'a> {
                 HEIGHT - 50,
                 [255, 255, 255, 255],
             );
+
+            // Magazine and reserve in a box of their own right of the health
+            let ammo_x = rect_x + rect_w + 10;
+            let ammo_w = 170;
+            Self::fill_rect(frame, ammo_x, rect_y, ammo_w, rect_h, color);
+            let (ammo_text, ammo_color) = if player.reloading {
+                ("Reloading".to_string(), [255, 200, 80, 255])
+            } else if player.ammo == 0 {
+                (
+                    format!("Ammo 0/{}", player.reserve_ammo),
+                    [255, 80, 80, 255],
+                )
+            } else {
+                (
+                    format!("Ammo {}/{}", player.ammo, player.reserve_ammo),
+                    [255, 255, 255, 255],
+                )
+            };
+            draw_text(
+                frame,
+                &self.font,
+                &ammo_text,
+                30.0,
+                ammo_x + 10,
+                HEIGHT - 50,
+                ammo_color,
+            );
         }
     }
 
+    /// Weapon heat bar above the health box, only while the weapon is warm. Blinks when overheated.
+    pub fn display_heat(&self, game_state: &GameState, my_id: u64, frame: &mut [u8]) {
+        let Some(player) = game_state.players.get(&my_id.to_string()) else {
+            return;
+        };
+        if player.heat <= 0.0 && !player.overheated {
+            return;
+        }
+
+        let rect_x = 100;
+        let rect_y = HEIGHT - 75;
+        let rect_w = 150;
+        let rect_h = 12;
+        Self::fill_rect(frame, rect_x, rect_y, rect_w, rect_h, [0, 0, 0, 128]);
+
+        let blink_on = (self.created.elapsed().as_millis() / OVERHEAT_FLASH_INTERVAL.as_millis())
+            .is_multiple_of(2);
+        let color = if !player.overheated {
+            [255, 160, 40, 255]
+        } else if blink_on {
+            [255, 40, 40, 255]
+        } else {
+            return;
+        };
+        let fill_w = (rect_w as f32 * player.heat.clamp(0.0, 1.0)) as usize;
+        Self::fill_rect(frame, rect_x, rect_y, fill_w, rect_h, color);
+    }
+
+    /// Stamina bar under the health box while it isn't full. Turns red when exhausted.
+    pub fn display_stamina(&self, game_state: &GameState, my_id: u64, frame: &mut [u8]) {
+        let Some(player) = game_state.players.get(&my_id.to_string()) else {
+            return;
+        };
+        if player.stamina >= 1.0 || player.health == 0 {
+            return;
+        }
+
+        let rect_x = 100;
+        let rect_y = HEIGHT - 12;
+        let rect_w = 150;
+        let rect_h = 6;
+        Self::fill_rect(frame, rect_x, rect_y, rect_w, rect_h, [0, 0, 0, 128]);
+
+        let color = if player.exhausted {
+            [220, 60, 60, 255]
+        } else {
+            [120, 220, 120, 255]
+        };
+        let fill_w = (rect_w as f32 * player.stamina.clamp(0.0, 1.0)) as usize;
+        Self::fill_rect(frame, rect_x, rect_y, fill_w, rect_h, color);
+    }
+
     pub fn display_leaderboard(&self, game_state: &GameState, frame: &mut [u8]) {
         let mut sorted_entries: Vec<_> = game_state.leaderboard.iter().collect();
         sorted_entries.sort_by(|(name_a, score_a), (name_b, score_b)| {
-            score_b.cmp(score_a).then_with(|| name_a.cmp(name_b))
+            score_b
+                .kills
+                .cmp(&score_a.kills)
+                .then_with(|| name_a.cmp(name_b))
+                .then_with(|| score_a.deaths.cmp(&score_b.deaths))
         });
 
-        let formatted_entries: Vec<String> = sorted_entries
-            .into_iter()
-            .map(|(name, score)| format!("{}: {}", name, score))
-            .collect();
+        // name, kills out of the points to win and deaths, with the column headers as the first row
+        let mut rows = vec![(String::new(), "K".to_string(), "D".to_string())];
+        rows.extend(sorted_entries.into_iter().map(|(name, score)| {
+            (
+                name.clone(),
+                format!("{} / {}", score.kills, self.score_to_win),
+                score.deaths.to_string(),
+            )
+        }));
 
         let title_text = "Leaderboard";
         let title_font_size = 28.0;
         let entry_font_size = 24.0;
 
         let (title_width, title_height) = self.measure_text_bounds(title_text, title_font_size);
-        let mut max_entry_width = title_width;
-        for entry in &formatted_entries {
-            let (entry_width, _) = self.measure_text_bounds(entry, entry_font_size);
-            max_entry_width = max_entry_width.max(entry_width);
-        }
+        let text_width =
+            |text: &str| self.measure_text_bounds(text, entry_font_size).0.ceil() as usize;
+        let name_width = rows.iter().map(|row| text_width(&row.0)).max().unwrap_or(0);
+        let kills_width = rows.iter().map(|row| text_width(&row.1)).max().unwrap_or(0);
+        let deaths_width = rows.iter().map(|row| text_width(&row.2)).max().unwrap_or(0);
 
         let padding_x = 16;
         let padding_y = 12;
         let header_gap = 10;
         let row_gap = 6;
+        let column_gap = 18;
         let rect_margin = 20;
 
         let header_height = title_height.ceil() as usize + header_gap;
         let row_height = entry_font_size.ceil() as usize + row_gap;
-        let rect_width = max_entry_width.ceil() as usize + padding_x * 2;
-        let rect_height = padding_y * 2 + header_height + formatted_entries.len() * row_height;
+        let table_width = name_width + column_gap + kills_width + column_gap + deaths_width;
+        let rect_width = (title_width.ceil() as usize).max(table_width) + padding_x * 2;
+        let rect_height = padding_y * 2 + header_height + rows.len() * row_height;
 
         let rect_x = WIDTH.saturating_sub(rect_width + rect_margin);
         let desired_rect_y = MINIMAP_MARGIN * 2 + MINIMAP_HEIGHT;
@@ -672,21 +1764,335 @@ impl<'a> Renderer<'a> {
             [220, 210, 200, 255],
         );
 
+        // kills and deaths are right aligned to the table's right edge
+        let deaths_right = rect_x + rect_width - padding_x;
+        let kills_right = deaths_right - deaths_width - column_gap;
         text_y += header_height;
-        for entry in &formatted_entries {
+        for (i, (name, kills, deaths)) in rows.iter().enumerate() {
+            let color = if i == 0 {
+                [220, 210, 200, 255]
+            } else {
+                [255, 255, 255, 255]
+            };
+            for (text, x) in [
+                (name, text_x),
+                (kills, kills_right.saturating_sub(text_width(kills))),
+                (deaths, deaths_right.saturating_sub(text_width(deaths))),
+            ] {
+                draw_text(frame, &self.font, text, entry_font_size, x, text_y, color);
+            }
+            text_y += row_height;
+        }
+    }
+
+    /// Frame time graph with FPS and min/avg/max numbers, in the top left corner
+    pub fn display_frame_stats(&self, frame_times: &FrameTimes, frame: &mut [u8]) {
+        let Some((min, max, avg)) = frame_times.stats() else {
+            return;
+        };
+
+        let padding = 8;
+        let font_size = 18.0;
+        let line_height = font_size as usize + 4;
+        let graph_width = FRAME_GRAPH_SAMPLES * FRAME_GRAPH_BAR_WIDTH;
+        let rect_x = MINIMAP_MARGIN;
+        let rect_y = MINIMAP_MARGIN;
+        let rect_w = graph_width + padding * 2;
+        let rect_h = line_height * 2 + FRAME_GRAPH_HEIGHT + padding * 3;
+        Self::fill_rect(frame, rect_x, rect_y, rect_w, rect_h, [0, 0, 0, 160]);
+
+        let text_x = rect_x + padding;
+        let text_y = rect_y + padding;
+        let fps = if avg > 0.0 { 1000.0 / avg } else { 0.0 };
+        draw_text(
+            frame,
+            &self.font,
+            &format!("FPS {:.0}   avg {:.1} ms", fps, avg),
+            font_size,
+            text_x,
+            text_y,
+            [255, 255, 255, 255],
+        );
+        draw_text(
+            frame,
+            &self.font,
+            &format!("min {:.1} ms   max {:.1} ms", min, max),
+            font_size,
+            text_x,
+            text_y + line_height,
+            [220, 210, 200, 255],
+        );
+
+        // One bar per frame, newest on the right
+        let graph_x = text_x;
+        let graph_bottom = text_y + line_height * 2 + padding + FRAME_GRAPH_HEIGHT;
+        for (i, ms) in frame_times.samples().enumerate() {
+            let bar_h = ((ms / FRAME_GRAPH_MAX_MS).min(1.0) * FRAME_GRAPH_HEIGHT as f32) as usize;
+            let color = if ms <= FRAME_GRAPH_TARGET_MS {
+                [80, 220, 80, 255]
+            } else if ms <= FRAME_GRAPH_TARGET_MS * 2.0 {
+                [230, 200, 60, 255]
+            } else {
+                [230, 70, 60, 255]
+            };
+            Self::fill_rect(
+                frame,
+                graph_x + i * FRAME_GRAPH_BAR_WIDTH,
+                graph_bottom - bar_h.max(1),
+                FRAME_GRAPH_BAR_WIDTH,
+                bar_h.max(1),
+                color,
+            );
+        }
+
+        // Reference line at the target frame time
+        let target_h =
+            (FRAME_GRAPH_TARGET_MS / FRAME_GRAPH_MAX_MS * FRAME_GRAPH_HEIGHT as f32) as usize;
+        Self::fill_rect(
+            frame,
+            graph_x,
+            graph_bottom - target_h,
+            graph_width,
+            1,
+            [255, 255, 255, 120],
+        );
+    }
+
+    /// Damage numbers rising and fading above hit players, hidden behind walls and offscreen
+    pub fn display_damage_numbers(&mut self, game_state: &GameState, my_id: u64, frame: &mut [u8]) {
+        self.damage_numbers
+            .retain(|number| number.start.elapsed() < DAMAGE_NUMBER_DURATION);
+        let Some(player) = self
+            .free_camera
+            .as_ref()
+            .or(self.spectated(game_state).map(|(_, player)| player))
+            .or_else(|| game_state.players.get(&my_id.to_string()))
+        else {
+            return;
+        };
+        let (camera_offset, pitch_offset) = self.last_camera;
+
+        for number in &self.damage_numbers {
+            let (x, y, z) = number.position;
+            let progress =
+                number.start.elapsed().as_secs_f32() / DAMAGE_NUMBER_DURATION.as_secs_f32();
+            let height = z + SPRITE_OTHER_PLAYER_HEIGHT + DAMAGE_NUMBER_RISE * progress;
+            let Some((render_x, render_y, depth)) =
+                self.project_to_screen(player, camera_offset, pitch_offset, (x, y, height))
+            else {
+                continue;
+            };
+            if render_x < 0.0
+                || render_x >= self.render_width as f32
+                || render_y < 0.0
+                || render_y >= self.render_height as f32
+            {
+                continue;
+            }
+            if depth >= self.z_buffer[render_x as usize] {
+                continue;
+            }
+            // the world was raycast smaller and stretched over the frame
+            let screen_x = render_x * WIDTH as f32 / self.render_width as f32;
+            let screen_y = render_y * HEIGHT as f32 / self.render_height as f32;
+
+            let text = number.damage.to_string();
+            let (text_width, text_height) =
+                self.measure_text_bounds(&text, DAMAGE_NUMBER_FONT_SIZE);
+            let [r, g, b] = if number.killed {
+                DAMAGE_NUMBER_KILL_COLOR
+            } else {
+                DAMAGE_NUMBER_COLOR
+            };
+            let alpha = (255.0 * (1.0 - progress)) as u8;
+            draw_text(
+                frame,
+                &self.font,
+                &text,
+                DAMAGE_NUMBER_FONT_SIZE,
+                (screen_x - text_width / 2.0).max(0.0) as usize,
+                (screen_y - text_height).max(0.0) as usize,
+                [r, g, b, alpha],
+            );
+        }
+    }
+
+    /// Keeps the tally of a running vote on screen, or announces how a finished one went
+    pub fn show_vote_kick(&mut self, status: VoteKickStatus) {
+        match status.passed {
+            None => self.vote_kick = Some((status, Instant::now())),
+            Some(passed) => {
+                self.vote_kick = None;
+                let text = if passed {
+                    format!("{} was kicked", status.target)
+                } else {
+                    format!("Vote to kick {} failed", status.target)
+                };
+                self.show_announcement(text, ANNOUNCEMENT_DURATION);
+            }
+        }
+    }
+
+    /// Tally and time left of the running vote kick, on the left side of the screen
+    pub fn display_vote_kick(&self, frame: &mut [u8]) {
+        let Some((status, received)) = &self.vote_kick else {
+            return;
+        };
+        let remaining = status.remaining.saturating_sub(received.elapsed());
+        if remaining.is_zero() {
+            return;
+        }
+
+        let lines = [
+            format!(
+                "Vote to kick {}: {}/{} ({} s)",
+                status.target,
+                status.votes,
+                status.required,
+                remaining.as_secs() + 1
+            ),
+            "Vote in the Escape menu".to_string(),
+        ];
+        let padding = 10;
+        let font_size = 22.0;
+        let line_height = font_size as usize + 6;
+        let text_width = lines
+            .iter()
+            .map(|line| self.measure_text_bounds(line, font_size).0)
+            .fold(0.0, f32::max);
+        let rect_x = MINIMAP_MARGIN;
+        let rect_y = HEIGHT / 3;
+        let rect_w = text_width as usize + padding * 2;
+        let rect_h = line_height * lines.len() + padding * 2;
+        Self::fill_rect(frame, rect_x, rect_y, rect_w, rect_h, [0, 0, 0, 160]);
+
+        for (i, line) in lines.iter().enumerate() {
             draw_text(
                 frame,
                 &self.font,
-                entry,
-                entry_font_size,
-                text_x,
-                text_y,
+                line,
+                font_size,
+                rect_x + padding,
+                rect_y + padding + i * line_height,
                 [255, 255, 255, 255],
             );
-            text_y += row_height;
         }
     }
 
+    /// Keeps the warmup banner up while the server runs one, and announces the live match after
+    pub fn show_warmup(&mut self, remaining: Option<Duration>) {
+        match remaining {
+            Some(remaining) => self.warmup = Some((remaining, Instant::now())),
+            None => {
+                if self.warmup.take().is_some() {
+                    self.show_announcement("Match is live!".to_string(), ANNOUNCEMENT_DURATION);
+                }
+            }
+        }
+    }
+
+    /// WARMUP with a countdown to the live match, at the top of the screen
+    pub fn display_warmup(&self, frame: &mut [u8]) {
+        let Some((remaining, received)) = &self.warmup else {
+            return;
+        };
+        let seconds = remaining.saturating_sub(received.elapsed()).as_secs();
+        let text = format!("WARMUP {}:{:02}", seconds / 60, seconds % 60);
+        self.draw_top_banner(frame, &text, [255, 220, 80, 255]);
+    }
+
+    /// Starts the match clock, or with zero left announces the sudden death that breaks a tie
+    pub fn show_round_time(&mut self, remaining: Duration) {
+        if remaining.is_zero() {
+            self.show_announcement("Sudden death!".to_string(), ANNOUNCEMENT_DURATION);
+        }
+        self.round_time = Some((remaining, Instant::now()));
+    }
+
+    pub fn clear_round_time(&mut self) {
+        self.round_time = None;
+    }
+
+    /// Countdown to the end of the match at the top of the screen, red for the last seconds
+    pub fn display_round_time(&self, frame: &mut [u8]) {
+        let Some((remaining, received)) = &self.round_time else {
+            return;
+        };
+        if remaining.is_zero() {
+            self.draw_top_banner(frame, "SUDDEN DEATH", [255, 80, 60, 255]);
+            return;
+        }
+        let left = remaining.saturating_sub(received.elapsed());
+        let seconds = left.as_secs();
+        let color = if left < ROUND_CLOCK_WARNING {
+            [255, 80, 60, 255]
+        } else {
+            [255, 255, 255, 255]
+        };
+        self.draw_top_banner(
+            frame,
+            &format!("{}:{:02}", seconds / 60, seconds % 60),
+            color,
+        );
+    }
+
+    /// Text on a dark box centered below the top edge
+    fn draw_top_banner(&self, frame: &mut [u8], text: &str, color: [u8; 4]) {
+        let padding = 8;
+        let font_size = 36.0;
+        let (text_width, text_height) = self.measure_text_bounds(text, font_size);
+        let rect_w = text_width as usize + padding * 2;
+        let rect_h = text_height as usize + padding * 2;
+        let rect_x = WIDTH.saturating_sub(rect_w) / 2;
+        let rect_y = padding;
+        Self::fill_rect(frame, rect_x, rect_y, rect_w, rect_h, [0, 0, 0, 160]);
+
+        draw_text(
+            frame,
+            &self.font,
+            text,
+            font_size,
+            rect_x + padding,
+            rect_y + padding,
+            color,
+        );
+    }
+
+    /// Banner for the latest announcement, centered below the top edge
+    pub fn display_announcement(&self, frame: &mut [u8]) {
+        let Some((text, start, duration)) = &self.announcement else {
+            return;
+        };
+        if start.elapsed() >= *duration {
+            return;
+        }
+
+        let padding = 16;
+        // shrink long texts to fit the screen
+        let mut font_size = 48.0;
+        let (mut text_width, mut text_height) = self.measure_text_bounds(text, font_size);
+        let max_width = (WIDTH - padding * 4) as f32;
+        if text_width > max_width {
+            font_size *= max_width / text_width;
+            (text_width, text_height) = self.measure_text_bounds(text, font_size);
+        }
+        let rect_w = text_width as usize + padding * 2;
+        let rect_h = text_height as usize + padding * 2;
+        let rect_x = WIDTH.saturating_sub(rect_w) / 2;
+        let rect_y = HEIGHT / 8;
+        Self::fill_rect(frame, rect_x, rect_y, rect_w, rect_h, [0, 0, 0, 160]);
+
+        draw_text(
+            frame,
+            &self.font,
+            text,
+            font_size,
+            rect_x + padding,
+            rect_y + padding,
+            [255, 140, 40, 255],
+        );
+    }
+
     pub fn display_winner(&self, winner_name: &str, frame: &mut [u8]) {
         let font_size = 150.0;
         let text = format!("{} Won!", winner_name);
@@ -726,6 +2132,103 @@ impl<'a> Renderer<'a> {
         );
     }
 
+    /// Post-match panel with the final scores of every player, winner highlighted
+    pub fn display_match_end(&self, stats: &MatchStats, frame: &mut [u8]) {
+        let mut players: Vec<_> = stats.players.iter().collect();
+        players.sort_by(|a, b| {
+            b.kills
+                .cmp(&a.kills)
+                .then_with(|| a.deaths.cmp(&b.deaths))
+                .then_with(|| a.name.cmp(&b.name))
+        });
+
+        let title_font_size = 90.0;
+        let info_font_size = 30.0;
+        let entry_font_size = 34.0;
+        let row_height = 40;
+        let padding = 30;
+
+        let rect_w = 700;
+        let rect_h = padding * 2 + 100 + 50 + row_height * (players.len() + 1) + 60;
+        let rect_x = (WIDTH - rect_w) / 2;
+        let rect_y = HEIGHT.saturating_sub(rect_h) / 2;
+        Self::fill_rect(frame, rect_x, rect_y, rect_w, rect_h, [0, 0, 0, 200]);
+
+        let gold = [255, 215, 0, 255];
+        let white = [255, 255, 255, 255];
+        let grey = [220, 210, 200, 255];
+
+        let title = format!("{} Won!", stats.winner);
+        let (title_width, _) = self.measure_text_bounds(&title, title_font_size);
+        let title_x = WIDTH / 2 - (title_width / 2.0) as usize;
+        let mut text_y = rect_y + padding;
+        draw_text(
+            frame,
+            &self.font,
+            &title,
+            title_font_size,
+            title_x,
+            text_y,
+            gold,
+        );
+        text_y += 100;
+
+        let secs = stats.duration.as_secs();
+        let duration_text = format!("Match time {}:{:02}", secs / 60, secs % 60);
+        let (duration_width, _) = self.measure_text_bounds(&duration_text, info_font_size);
+        let duration_x = WIDTH / 2 - (duration_width / 2.0) as usize;
+        draw_text(
+            frame,
+            &self.font,
+            &duration_text,
+            info_font_size,
+            duration_x,
+            text_y,
+            grey,
+        );
+        text_y += 50;
+
+        // Columns: name, kills, deaths, K/D
+        let columns = [rect_x + padding, rect_x + 360, rect_x + 460, rect_x + 580];
+        let headers = ["Player", "Kills", "Deaths", "K/D"];
+        for (x, header) in columns.iter().zip(headers) {
+            draw_text(frame, &self.font, header, entry_font_size, *x, text_y, grey);
+        }
+        text_y += row_height;
+
+        for player in players {
+            let color = if player.name == stats.winner {
+                gold
+            } else {
+                white
+            };
+            let kd = player.kills as f32 / player.deaths.max(1) as f32;
+            let cells = [
+                player.name.clone(),
+                player.kills.to_string(),
+                player.deaths.to_string(),
+                format!("{:.2}", kd),
+            ];
+            for (x, cell) in columns.iter().zip(cells) {
+                draw_text(frame, &self.font, &cell, entry_font_size, *x, text_y, color);
+            }
+            text_y += row_height;
+        }
+
+        let prompt = "Next match starts soon - press Enter to close";
+        let (prompt_width, _) = self.measure_text_bounds(prompt, info_font_size);
+        let prompt_x = WIDTH / 2 - (prompt_width / 2.0) as usize;
+        draw_text(
+            frame,
+            &self.font,
+            prompt,
+            info_font_size,
+            prompt_x,
+            text_y + 20,
+            grey,
+        );
+    }
+
     pub fn took_damage(&mut self, frame: &mut [u8]) {
         if let Some(start) = self.damage_flash_start {
             if start.elapsed() < self.damage_flash_duration {
@@ -738,43 +2241,93 @@ impl<'a> Renderer<'a> {
         }
     }
 
-    pub fn get_menu_item_bounds(&self, mouse_sensitivity: f32) -> (MenuBounds, MenuBounds) {
-        let font_size = 80.0;
-        let item_spacing = 120;
+    /// Fades out a white overlay after a teleport so the jump to a new view isn't jarring
+    pub fn display_teleport_flash(&mut self, frame: &mut [u8]) {
+        if let Some(start) = self.teleport_flash_start {
+            let progress = start.elapsed().as_secs_f32() / TELEPORT_FLASH_DURATION.as_secs_f32();
+            if progress < 1.0 {
+                let alpha = (200.0 * (1.0 - progress)) as u8;
+                Self::fill_rect(frame, 0, 0, WIDTH, HEIGHT, [255, 255, 255, alpha]);
+            } else {
+                self.teleport_flash_start = None;
+            }
+        }
+    }
+
+    /// Pulsing tint and a countdown while the local player is spawn protected
+    pub fn display_spawn_protection(
+        &mut self,
+        game_state: &GameState,
+        my_id: u64,
+        frame: &mut [u8],
+    ) {
+        let protected = game_state
+            .players
+            .get(&my_id.to_string())
+            .is_some_and(|player| player.protected);
+        if !protected {
+            self.protection_start = None;
+            return;
+        }
+        let start = *self.protection_start.get_or_insert_with(Instant::now);
+
+        let pulse =
+            (self.created.elapsed().as_secs_f32() * SPAWN_PROTECTION_PULSE_SPEED).sin() * 0.5 + 0.5;
+        let [r, g, b, a] = SPAWN_PROTECTION_TINT;
+        let alpha = (a as f32 * (0.5 + 0.5 * pulse)) as u8;
+        Self::fill_rect(frame, 0, 0, WIDTH, HEIGHT, [r, g, b, alpha]);
+
+        let remaining = self.spawn_protection.saturating_sub(start.elapsed());
+        let text = format!("Protected {:.1}s", remaining.as_secs_f32());
+        let font_size = 32.0;
+        let (text_width, _) = self.measure_text_bounds(&text, font_size);
+        draw_text(
+            frame,
+            &self.font,
+            &text,
+            font_size,
+            (WIDTH as f32 / 2.0 - text_width / 2.0) as usize,
+            HEIGHT * 2 / 3,
+            [180, 220, 255, 255],
+        );
+    }
+
+    /// Lays out the menu items below the title, shrinking them to fit when there are many.
+    fn menu_layout(&self, items: &[(MenuHover, String)]) -> (f32, Vec<MenuBounds>) {
         let margin = 100;
         let title_y = margin + 80;
         let title_bottom = title_y + 100;
         let menu_center_x = WIDTH / 2;
         let menu_start_y = title_bottom + 60;
 
-        let quit_text = "Quit";
-        let (quit_width, quit_height) = self.measure_text_bounds(quit_text, font_size);
-        let quit_x = menu_center_x - (quit_width / 2.0) as usize;
-        let quit_y = menu_start_y;
-        let quit_bounds = MenuBounds {
-            x: quit_x,
-            y: quit_y,
-            width: quit_width as usize,
-            height: quit_height as usize,
-        };
+        let available_height = HEIGHT - margin - menu_start_y;
+        let item_spacing = (available_height / items.len().max(1)).min(120);
+        let font_size = (item_spacing as f32 * 2.0 / 3.0).min(80.0);
+
+        let bounds = items
+            .iter()
+            .enumerate()
+            .map(|(i, (_, text))| {
+                let (width, height) = self.measure_text_bounds(text, font_size);
+                MenuBounds {
+                    x: menu_center_x - (width / 2.0) as usize,
+                    y: menu_start_y + i * item_spacing,
+                    width: width as usize,
+                    height: height as usize,
+                }
+            })
+            .collect();
 
-        let sensitivity_text = format!("Mouse Sensitivity: {:.2}", mouse_sensitivity);
-        let (sens_width, sens_height) = self.measure_text_bounds(&sensitivity_text, font_size);
-        let sens_x = menu_center_x - (sens_width / 2.0) as usize;
-        let sens_y = menu_start_y + item_spacing;
-        let sens_bounds = MenuBounds {
-            x: sens_x,
-            y: sens_y,
-            width: sens_width as usize,
-            height: sens_height as usize,
-        };
+        (font_size, bounds)
+    }
 
-        (quit_bounds, sens_bounds)
+    pub fn get_menu_item_bounds(&self, items: &[(MenuHover, String)]) -> Vec<MenuBounds> {
+        self.menu_layout(items).1
     }
 
     pub fn display_menu(
         &self,
-        mouse_sensitivity: f32,
+        items: &[(MenuHover, String)],
         frame: &mut [u8],
         hovered_item: Option<MenuHover>,
     ) {
@@ -804,53 +2357,37 @@ impl<'a> Renderer<'a> {
             title_color,
         );
 
-        let font_size = 80.0;
-        let item_spacing = 120;
-        let title_bottom = title_y + 100;
-
-        let menu_center_x = WIDTH / 2;
-        let menu_start_y = title_bottom + 60;
-
-        let quit_text = "Quit";
-        let (quit_width, _quit_height) = self.measure_text_bounds(quit_text, font_size);
-        let quit_x = menu_center_x - (quit_width / 2.0) as usize;
-        let quit_y = menu_start_y;
-
-        let quit_color = if hovered_item == Some(MenuHover::Quit) {
-            [255, 200, 0, 255]
-        } else {
-            [255, 255, 255, 255]
-        };
-
-        draw_text(
-            frame,
-            &self.font,
-            quit_text,
-            font_size,
-            quit_x,
-            quit_y,
-            quit_color,
-        );
-
-        let sensitivity_text = format!("Mouse Sensitivity: {:.2}", mouse_sensitivity);
-        let (sens_width, _sens_height) = self.measure_text_bounds(&sensitivity_text, font_size);
-        let sens_x = menu_center_x - (sens_width / 2.0) as usize;
-        let sens_y = menu_start_y + item_spacing;
+        let (font_size, bounds) = self.menu_layout(items);
+        for ((item, text), item_bounds) in items.iter().zip(bounds) {
+            let selected = hovered_item == Some(*item);
+            let color = if selected {
+                [255, 200, 0, 255]
+            } else {
+                [255, 255, 255, 255]
+            };
 
-        let sens_color = if hovered_item == Some(MenuHover::MouseSensitivity) {
-            [255, 200, 0, 255]
-        } else {
-            [255, 255, 255, 255]
-        };
+            // marker left of the selected item, so keyboard selection is easy to follow
+            if selected {
+                let marker_size = (item_bounds.height / 2).max(4);
+                Self::fill_rect(
+                    frame,
+                    item_bounds.x.saturating_sub(marker_size * 2),
+                    item_bounds.y + (item_bounds.height - marker_size) / 2,
+                    marker_size,
+                    marker_size,
+                    color,
+                );
+            }
 
-        draw_text(
-            frame,
-            &self.font,
-            &sensitivity_text,
-            font_size,
-            sens_x,
-            sens_y,
-            sens_color,
-        );
+            draw_text(
+                frame,
+                &self.font,
+                text,
+                font_size,
+                item_bounds.x,
+                item_bounds.y,
+                color,
+            );
+        }
     }
 }
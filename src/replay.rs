@@ -0,0 +1,194 @@
+use std::collections::{HashMap, VecDeque};
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use serde::Serialize;
+
+use crate::consts::{REPLAY_DIR, REPLAY_DUMP_COOLDOWN, TICK_RATE};
+use crate::gamestate::GameState;
+
+/// One player's authoritative state in a tick
+#[derive(Serialize, Clone, Debug)]
+pub struct PlayerFrame {
+    pub name: String,
+    pub x: f32,
+    pub y: f32,
+    pub z: f32,
+    pub angle: f32,
+    pub pitch: f32,
+    pub health: u16,
+}
+
+/// A shot fired during a tick and who it hit
+#[derive(Serialize, Clone, Debug)]
+pub struct ShotFrame {
+    pub shooter: String,
+    pub target: Option<String>,
+    pub distance: Option<f32>,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct ReplayFrame {
+    pub time_ms: u64,
+    pub players: Vec<PlayerFrame>,
+    pub shots: Vec<ShotFrame>,
+}
+
+#[derive(Serialize)]
+struct ReplayDump<'a> {
+    reason: &'a str,
+    frames: &'a VecDeque<ReplayFrame>,
+}
+
+/// Ring buffer of the last few seconds of server ticks for --replay-buffer. Suspicious hits and
+/// turns dump the buffer to a file in REPLAY_DIR so hosts can look into them later.
+pub struct ReplayBuffer {
+    frames: VecDeque<ReplayFrame>,
+    capacity: usize,
+    pending_shots: Vec<ShotFrame>,
+    pending_reason: Option<String>,
+    // angle and position at the previous tick, for the turn rate check
+    last_poses: HashMap<String, (f32, (f32, f32), Instant)>,
+    suspicious_distance: f32,
+    suspicious_turn_rate: f32,
+    started: Instant,
+    last_dump: Option<Instant>,
+}
+
+impl ReplayBuffer {
+    pub fn new(seconds: f32, suspicious_distance: f32, suspicious_turn_rate: f32) -> Self {
+        let capacity = ((seconds * TICK_RATE as f32) as usize).max(1);
+        ReplayBuffer {
+            frames: VecDeque::with_capacity(capacity),
+            capacity,
+            pending_shots: Vec::new(),
+            pending_reason: None,
+            last_poses: HashMap::new(),
+            suspicious_distance,
+            suspicious_turn_rate,
+            started: Instant::now(),
+            last_dump: None,
+        }
+    }
+
+    /// Stores the players' state for this tick, along with the shots since the last one.
+    /// Returns a reason if one of those shots or someone's turn since the last tick was suspicious.
+    pub fn record_tick(
+        &mut self,
+        game_state: &GameState,
+        clients: &HashMap<SocketAddr, (u64, String, Instant)>,
+    ) -> Option<String> {
+        let now = Instant::now();
+        let mut suspicious = self.pending_reason.take();
+        let mut players = Vec::with_capacity(clients.len());
+
+        for (id, name, _) in clients.values() {
+            let Some(player) = game_state.players.get(&id.to_string()) else {
+                continue;
+            };
+            // respawns and map reloads move the player and reset the angle, don't count those
+            if let Some((last_angle, last_pos, last_time)) = self.last_poses.get(name) {
+                let moved = (player.x - last_pos.0).hypot(player.y - last_pos.1);
+                let elapsed = now.duration_since(*last_time).as_secs_f32();
+                let turn_rate = (player.angle - last_angle).abs() / elapsed.max(f32::EPSILON);
+                if moved < 1.0 && turn_rate > self.suspicious_turn_rate && suspicious.is_none() {
+                    suspicious = Some(format!("{} turned at {:.0} rad/s", name, turn_rate));
+                }
+            }
+            self.last_poses
+                .insert(name.clone(), (player.angle, (player.x, player.y), now));
+            players.push(PlayerFrame {
+                name: name.clone(),
+                x: player.x,
+                y: player.y,
+                z: player.z,
+                angle: player.angle,
+                pitch: player.pitch,
+                health: player.health,
+            });
+        }
+        self.last_poses
+            .retain(|name, _| players.iter().any(|p| &p.name == name));
+
+        if self.frames.len() == self.capacity {
+            self.frames.pop_front();
+        }
+        self.frames.push_back(ReplayFrame {
+            time_ms: self.started.elapsed().as_millis() as u64,
+            players,
+            shots: std::mem::take(&mut self.pending_shots),
+        });
+        suspicious
+    }
+
+    /// Queues a shot for the next tick's frame, flagging hits from unusually far away
+    pub fn record_shot(
+        &mut self,
+        game_state: &GameState,
+        clients: &HashMap<SocketAddr, (u64, String, Instant)>,
+        shooter_id: u64,
+        target_id: Option<u64>,
+    ) {
+        let name_of = |id: u64| {
+            clients
+                .values()
+                .find(|(client_id, _, _)| *client_id == id)
+                .map(|(_, name, _)| name.clone())
+        };
+        let position = |id: u64| {
+            game_state
+                .players
+                .get(&id.to_string())
+                .map(|player| (player.x, player.y))
+        };
+        let distance = target_id.and_then(|target_id| {
+            let (shooter, target) = (position(shooter_id)?, position(target_id)?);
+            Some((target.0 - shooter.0).hypot(target.1 - shooter.1))
+        });
+        let shot = ShotFrame {
+            shooter: name_of(shooter_id).unwrap_or_default(),
+            target: target_id.and_then(name_of),
+            distance,
+        };
+
+        if let (Some(target), Some(distance)) = (&shot.target, shot.distance)
+            && distance > self.suspicious_distance
+            && self.pending_reason.is_none()
+        {
+            self.pending_reason = Some(format!(
+                "{} hit {} from {:.1} tiles",
+                shot.shooter, target, distance
+            ));
+        }
+        self.pending_shots.push(shot);
+    }
+
+    /// Writes the buffered frames to a new file. Returns None without writing if another dump
+    /// was made less than REPLAY_DUMP_COOLDOWN ago, so one incident doesn't produce a pile of files.
+    pub fn dump(&mut self, reason: &str) -> std::io::Result<Option<PathBuf>> {
+        if self
+            .last_dump
+            .is_some_and(|last| last.elapsed() < REPLAY_DUMP_COOLDOWN)
+        {
+            return Ok(None);
+        }
+        self.last_dump = Some(Instant::now());
+
+        let dump = ReplayDump {
+            reason,
+            frames: &self.frames,
+        };
+        let contents = toml::to_string(&dump)
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+
+        std::fs::create_dir_all(REPLAY_DIR)?;
+        let stamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or(Duration::ZERO)
+            .as_secs();
+        let path = PathBuf::from(REPLAY_DIR).join(format!("replay_{}.toml", stamp));
+        std::fs::write(&path, contents)?;
+        Ok(Some(path))
+    }
+}
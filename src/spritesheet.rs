@@ -1,4 +1,10 @@
-use crate::{consts::CYAN_TRANSPARENT, textures::Texture};
+use crate::{
+    consts::{
+        CYAN_TRANSPARENT, DIE_FRAME_COUNT, SPRITE_SHEET_BASE_PATH, SPRITE_SHEET_COUNT,
+        WALK_FRAME_COUNT,
+    },
+    textures::Texture,
+};
 use image::error::{ParameterError, ParameterErrorKind};
 use image::{self, GenericImageView};
 
@@ -8,9 +14,9 @@ use palette::{FromColor, Hsv, Srgb};
 #[derive(Debug)]
 pub struct SpriteSheet {
     pub idle: [Texture; 8],
-    pub walk: [[Texture; 4]; 8],
+    pub walk: [[Texture; WALK_FRAME_COUNT]; 8],
     pub shoot: [Texture; 8],
-    pub die: [Texture; 3],
+    pub die: [Texture; DIE_FRAME_COUNT],
     pub dead: [Texture; 1],
 }
 
@@ -29,22 +35,34 @@ impl SpriteSheet {
 
         // walking: frames are 276 x 338 pixels each with 4 vertical and 2 horizontal lines of pixels in between.
         let walk_frames_vec = (0..8)
-            .map(|i| -> Result<[Texture; 4], image::ImageError> {
-                let frames =
-                    Self::load_animation_frames(&img, 1, 342 + i * 340, 4, 4, 276, 338, 4, 2)?;
-                frames.try_into().map_err(|_| {
-                    image::ImageError::Parameter(ParameterError::from_kind(
-                        ParameterErrorKind::Generic("Incorrect number of walk frames".into()),
-                    ))
-                })
-            })
-            .collect::<Result<Vec<[Texture; 4]>, _>>()?;
-
-        let walk_frames: [[Texture; 4]; 8] = walk_frames_vec.try_into().map_err(|_| {
-            image::ImageError::Parameter(ParameterError::from_kind(ParameterErrorKind::Generic(
-                "Incorrect number of walk animation rows".into(),
-            )))
-        })?;
+            .map(
+                |i| -> Result<[Texture; WALK_FRAME_COUNT], image::ImageError> {
+                    let frames = Self::load_animation_frames(
+                        &img,
+                        1,
+                        342 + i * 340,
+                        WALK_FRAME_COUNT as u32,
+                        4,
+                        276,
+                        338,
+                        4,
+                        2,
+                    )?;
+                    frames.try_into().map_err(|_| {
+                        image::ImageError::Parameter(ParameterError::from_kind(
+                            ParameterErrorKind::Generic("Incorrect number of walk frames".into()),
+                        ))
+                    })
+                },
+            )
+            .collect::<Result<Vec<[Texture; WALK_FRAME_COUNT]>, _>>()?;
+
+        let walk_frames: [[Texture; WALK_FRAME_COUNT]; 8] =
+            walk_frames_vec.try_into().map_err(|_| {
+                image::ImageError::Parameter(ParameterError::from_kind(
+                    ParameterErrorKind::Generic("Incorrect number of walk animation rows".into()),
+                ))
+            })?;
 
         // shooting
         let shoot_frames_vec = Self::load_animation_frames(&img, 1122, 342, 8, 1, 276, 338, 4, 2)?;
@@ -55,8 +73,9 @@ impl SpriteSheet {
         })?;
 
         // dying
-        let die_frames_vec = Self::load_animation_frames(&img, 2, 3062, 3, 3, 276, 338, 4, 2)?;
-        let die_frames: [Texture; 3] = die_frames_vec.try_into().map_err(|_| {
+        let die_frames_vec =
+            Self::load_animation_frames(&img, 2, 3062, DIE_FRAME_COUNT as u32, 3, 276, 338, 4, 2)?;
+        let die_frames: [Texture; DIE_FRAME_COUNT] = die_frames_vec.try_into().map_err(|_| {
             image::ImageError::Parameter(ParameterError::from_kind(ParameterErrorKind::Generic(
                 "Incorrect number of die frames".into(),
             )))
@@ -157,17 +176,25 @@ fn shift_hue(img: &DynamicImage, degrees: f32) -> RgbaImage {
     out
 }
 
-pub fn hue_variations(path: &str) {
-    let base = image::open(path).expect("can't load base sheet");
-
-    for i in 1..10 {
-        let out = format!("assets/blob{i}.png");
-        if std::path::Path::new(&out).exists() {
-            continue;
-        }
+/// Path of the sprite sheet for a player texture
+pub fn sprite_sheet_path(texture: &str) -> String {
+    format!("assets/blob{texture}.png")
+}
 
-        let degrees = 360.0 * (i as f32 / 10.0);
-        let shifted = shift_hue(&base, degrees);
-        shifted.save(&out).unwrap();
+/// Generates the hue variation of the base sheet for a player texture, if it doesn't already exist
+pub fn hue_variation(texture: &str) -> Result<(), image::ImageError> {
+    let out = sprite_sheet_path(texture);
+    if std::path::Path::new(&out).exists() {
+        return Ok(());
     }
+    let index: usize = texture.parse().map_err(|_| {
+        image::ImageError::Parameter(ParameterError::from_kind(ParameterErrorKind::Generic(
+            format!("Unknown player texture {texture}"),
+        )))
+    })?;
+
+    let base = image::open(SPRITE_SHEET_BASE_PATH)?;
+    let degrees = 360.0 * (index as f32 / SPRITE_SHEET_COUNT as f32);
+    let shifted = shift_hue(&base, degrees);
+    shifted.save(&out)
 }
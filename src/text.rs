@@ -1,29 +1,149 @@
 use crate::consts::{HEIGHT, WIDTH};
 use rusttype::{Font, Scale, point};
+use std::cell::RefCell;
+use std::collections::HashMap;
+
+/// A glyph rasterized once at one size. Offsets are from the pen position on the baseline.
+struct CachedGlyph {
+    coverage: Vec<u8>,
+    width: usize,
+    height: usize,
+    offset_x: i32,
+    offset_y: i32,
+    advance: f32,
+}
+
+/// Font with a cache of rasterized glyphs keyed by (char, size), so text drawn every frame is only
+/// rasterized the first time. The cache belongs to its font and is dropped with it.
+pub struct GlyphCache<'a> {
+    font: Font<'a>,
+    glyphs: RefCell<HashMap<(char, u32), CachedGlyph>>,
+}
+
+impl<'a> GlyphCache<'a> {
+    pub fn new(font: Font<'a>) -> Self {
+        GlyphCache {
+            font,
+            glyphs: RefCell::new(HashMap::new()),
+        }
+    }
+
+    fn rasterize(&self, c: char, scale: Scale) -> CachedGlyph {
+        let glyph = self.font.glyph(c).scaled(scale);
+        let advance = glyph.h_metrics().advance_width;
+        let glyph = glyph.positioned(point(0.0, 0.0));
+
+        let Some(bb) = glyph.pixel_bounding_box() else {
+            // whitespace has no pixels, only an advance
+            return CachedGlyph {
+                coverage: Vec::new(),
+                width: 0,
+                height: 0,
+                offset_x: 0,
+                offset_y: 0,
+                advance,
+            };
+        };
+        let width = bb.width() as usize;
+        let height = bb.height() as usize;
+        let mut coverage = vec![0; width * height];
+        glyph.draw(|gx, gy, gv| {
+            coverage[gy as usize * width + gx as usize] = (gv * 255.0) as u8;
+        });
+
+        CachedGlyph {
+            coverage,
+            width,
+            height,
+            offset_x: bb.min.x,
+            offset_y: bb.min.y,
+            advance,
+        }
+    }
+
+    /// Walks the text like a layout would, calling place with each glyph and its top left pixel
+    fn layout(
+        &self,
+        text: &str,
+        size: f32,
+        x: f32,
+        baseline: f32,
+        mut place: impl FnMut(&CachedGlyph, i32, i32),
+    ) {
+        let scale = Scale::uniform(size);
+        let mut glyphs = self.glyphs.borrow_mut();
+        let mut pen_x = x;
+        let mut previous = None;
+
+        for c in text.chars() {
+            if let Some(previous) = previous {
+                pen_x += self.font.pair_kerning(scale, previous, c);
+            }
+            let glyph = glyphs
+                .entry((c, size.to_bits()))
+                .or_insert_with(|| self.rasterize(c, scale));
+            place(
+                glyph,
+                pen_x.round() as i32 + glyph.offset_x,
+                baseline.round() as i32 + glyph.offset_y,
+            );
+            pen_x += glyph.advance;
+            previous = Some(c);
+        }
+    }
+
+    /// Width and height of the pixels the text covers
+    pub fn measure(&self, text: &str, size: f32) -> (f32, f32) {
+        let mut min_x = i32::MAX;
+        let mut max_x = i32::MIN;
+        let mut min_y = i32::MAX;
+        let mut max_y = i32::MIN;
+
+        self.layout(text, size, 0.0, 0.0, |glyph, left, top| {
+            if glyph.width > 0 {
+                min_x = min_x.min(left);
+                max_x = max_x.max(left + glyph.width as i32);
+                min_y = min_y.min(top);
+                max_y = max_y.max(top + glyph.height as i32);
+            }
+        });
+
+        if min_x > max_x {
+            (0.0, 0.0)
+        } else {
+            ((max_x - min_x) as f32, (max_y - min_y) as f32)
+        }
+    }
+}
 
 pub fn draw_text(
     frame: &mut [u8],
-    font: &Font,
+    font: &GlyphCache,
     text: &str,
     size: f32,
     x: usize,
     y: usize,
     color: [u8; 4],
 ) {
-    let scale = Scale::uniform(size);
-    let v_metrics = font.v_metrics(scale);
-    let layout = font.layout(text, scale, point(x as f32, y as f32 + v_metrics.ascent));
-
-    for g in layout {
-        if let Some(bb) = g.pixel_bounding_box() {
-            g.draw(|gx, gy, gv| {
-                let gx = gx as i32 + bb.min.x;
-                let gy = gy as i32 + bb.min.y;
-
-                if gx >= 0 && gx < WIDTH as i32 && gy >= 0 && gy < HEIGHT as i32 {
-                    let idx = (gy as usize * WIDTH + gx as usize) * 4;
+    let v_metrics = font.font.v_metrics(Scale::uniform(size));
+    let baseline = y as f32 + v_metrics.ascent;
+
+    font.layout(text, size, x as f32, baseline, |glyph, left, top| {
+        for gy in 0..glyph.height {
+            for gx in 0..glyph.width {
+                let px = left + gx as i32;
+                let py = top + gy as i32;
+
+                if px >= 0 && px < WIDTH as i32 && py >= 0 && py < HEIGHT as i32 {
+                    let idx = (py as usize * WIDTH + px as usize) * 4;
                     if idx + 3 < frame.len() {
-                        let pixel_alpha = (gv * 255.0) as u8;
+                        // the color's alpha fades the whole text
+                        let pixel_alpha = (glyph.coverage[gy * glyph.width + gx] as u16
+                            * color[3] as u16
+                            / 255) as u8;
+                        if pixel_alpha == 0 {
+                            continue;
+                        }
                         let bg_r = frame[idx];
                         let bg_g = frame[idx + 1];
                         let bg_b = frame[idx + 2];
@@ -48,7 +168,7 @@ pub fn draw_text(
                         frame[idx + 3] = a as u8;
                     }
                 }
-            });
+            }
         }
-    }
+    });
 }
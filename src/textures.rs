@@ -1,5 +1,9 @@
+use crate::map::World;
 use image::{self, GenericImageView};
+use serde::Deserialize;
 use std::collections::HashMap;
+use std::path::Path;
+use std::time::Duration;
 
 #[derive(Debug, Clone)]
 pub struct Texture {
@@ -32,28 +36,161 @@ impl Texture {
             pixels,
         })
     }
+
+    /// Cuts a horizontal strip into equally wide frames
+    fn split_frames(&self, count: u32) -> Vec<Texture> {
+        let frame_width = self.width / count;
+        (0..count)
+            .map(|frame| {
+                let mut pixels = Vec::with_capacity((frame_width * self.height) as usize);
+                for y in 0..self.height {
+                    let row_start = (y * self.width + frame * frame_width) as usize;
+                    pixels.extend_from_slice(
+                        &self.pixels[row_start..row_start + frame_width as usize],
+                    );
+                }
+                Texture {
+                    width: frame_width,
+                    height: self.height,
+                    pixels,
+                }
+            })
+            .collect()
+    }
+}
+
+/// Settings from an image's .anim.toml sidecar, e.g. lava.png with lava.anim.toml
+#[derive(Deserialize)]
+struct AnimationSidecar {
+    frames: u32,
+    frame_ms: u64,
+}
+
+struct Animation {
+    frames: Vec<Texture>,
+    frame_time: Duration,
+}
+
+fn read_sidecar(path: &Path) -> Result<AnimationSidecar, String> {
+    let contents = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
+    let sidecar: AnimationSidecar = toml::from_str(&contents).map_err(|e| e.to_string())?;
+    if sidecar.frames == 0 || sidecar.frame_ms == 0 {
+        return Err("frames and frame_ms must be above zero".to_string());
+    }
+    Ok(sidecar)
 }
 
 pub struct TextureManager {
     textures: HashMap<String, Texture>,
+    animations: HashMap<String, Animation>,
+    // file each texture was loaded from, so loading the same one again is skipped
+    sources: HashMap<String, String>,
 }
 
 impl TextureManager {
     pub fn new() -> Self {
         TextureManager {
             textures: HashMap::new(),
+            animations: HashMap::new(),
+            sources: HashMap::new(),
         }
     }
 
+    /// Loads an image. If it has an .anim.toml sidecar, the image is a horizontal strip of frames
+    /// that get_frame cycles through, and get_texture returns the first frame.
     pub fn load_texture(&mut self, name: String, path: &str) -> Result<(), image::ImageError> {
+        if self.sources.get(&name).is_some_and(|source| source == path) {
+            return Ok(());
+        }
         let texture = Texture::from_file(path)?;
-        self.textures.insert(name, texture);
+        self.sources.insert(name.clone(), path.to_string());
+        let sidecar_path = Path::new(path).with_extension("anim.toml");
+        if !sidecar_path.exists() {
+            self.animations.remove(&name);
+            self.textures.insert(name, texture);
+            return Ok(());
+        }
+
+        let sidecar = read_sidecar(&sidecar_path)
+            .and_then(|sidecar| {
+                if texture.width % sidecar.frames == 0 {
+                    Ok(sidecar)
+                } else {
+                    Err(format!(
+                        "image width {} doesn't split into {} frames",
+                        texture.width, sidecar.frames
+                    ))
+                }
+            })
+            .map_err(|e| {
+                image::ImageError::IoError(std::io::Error::new(
+                    std::io::ErrorKind::InvalidData,
+                    format!("{}: {}", sidecar_path.display(), e),
+                ))
+            })?;
+        let frames = texture.split_frames(sidecar.frames);
+        self.textures.insert(name.clone(), frames[0].clone());
+        self.animations.insert(
+            name,
+            Animation {
+                frames,
+                frame_time: Duration::from_millis(sidecar.frame_ms),
+            },
+        );
         Ok(())
     }
 
+    pub fn remove_texture(&mut self, name: &str) {
+        self.textures.remove(name);
+        self.animations.remove(name);
+        self.sources.remove(name);
+    }
+
     pub fn get_texture(&self, name: &str) -> Option<&Texture> {
         self.textures.get(name)
     }
+
+    /// The frame to show at the given time for animated textures, the texture itself otherwise
+    pub fn get_frame(&self, name: &str, time: Duration) -> Option<&Texture> {
+        match self.animations.get(name) {
+            Some(animation) => {
+                let index = (time.as_millis() / animation.frame_time.as_millis()) as usize
+                    % animation.frames.len();
+                animation.frames.get(index)
+            }
+            None => self.textures.get(name),
+        }
+    }
+}
+
+/// Wall textures for tile values a map doesn't set itself
+const DEFAULT_WALL_TEXTURES: [(u8, &str); 3] = [
+    (1, "assets/woodtiles4.png"),
+    (2, "assets/carpet2.png"),
+    (3, "assets/woodtiles2.png"),
+];
+
+/// Loads the wall textures a map sets as wall{tile}, over the defaults. Tile values the map
+/// leaves out go back to their default texture, or to none, and one that fails to load is
+/// dropped too. Walls without a texture are drawn in the solid wall colors.
+pub fn load_map_textures(texture_manager: &mut TextureManager, world: &World) {
+    for tile in 1..=u8::MAX {
+        let name = format!("wall{}", tile);
+        let path = world.texture_for(tile).or_else(|| {
+            DEFAULT_WALL_TEXTURES
+                .iter()
+                .find(|(default_tile, _)| *default_tile == tile)
+                .map(|(_, path)| *path)
+        });
+        let Some(path) = path else {
+            texture_manager.remove_texture(&name);
+            continue;
+        };
+        if let Err(e) = texture_manager.load_texture(name.clone(), path) {
+            eprintln!("Failed to load texture {} for tile {}: {}", path, tile, e);
+            texture_manager.remove_texture(&name);
+        }
+    }
 }
 
 pub fn load_game_textures(texture_manager: &mut TextureManager) -> Result<(), image::ImageError> {
@@ -63,10 +200,16 @@ pub fn load_game_textures(texture_manager: &mut TextureManager) -> Result<(), im
     texture_manager.load_texture("gun".to_string(), "assets/gun01.png")?;
     texture_manager.load_texture("gunshot".to_string(), "assets/gun01shot.png")?;
     texture_manager.load_texture("crosshair".to_string(), "assets/crosshair01.png")?;
-    texture_manager.load_texture("wall1".to_string(), "assets/woodtiles4.png")?;
-    texture_manager.load_texture("wall2".to_string(), "assets/carpet2.png")?;
-    texture_manager.load_texture("wall3".to_string(), "assets/woodtiles2.png")?;
+    for (tile, path) in DEFAULT_WALL_TEXTURES {
+        texture_manager.load_texture(format!("wall{}", tile), path)?;
+    }
+    texture_manager.load_texture("floor".to_string(), "assets/carpet.png")?;
+    texture_manager.load_texture("ceiling".to_string(), "assets/woodtiles.png")?;
+    texture_manager.load_texture("sky".to_string(), "assets/sky.png")?;
+    texture_manager.load_texture("door".to_string(), "assets/door.png")?;
     texture_manager.load_texture("puddle".to_string(), "assets/bloodpuddle.png")?;
+    texture_manager.load_texture("healthpack".to_string(), "assets/healthpack.png")?;
+    texture_manager.load_texture("ammopack".to_string(), "assets/ammopack.png")?;
     // navigator icon used for the minimap player indicator
     texture_manager.load_texture("navigator".to_string(), "assets/navigator.png")?;
     Ok(())
@@ -1,19 +1,115 @@
 // Utility functions / functions I'm not sure where to put
 
-use crate::ServerMessage;
+use crate::{MatchStats, PlayerStats, Score, ServerMessage};
+use crate::diagnostics::PACKETS_SENT;
+use crate::reliable::{self, OUTBOX};
+use crate::bot::Bot;
 use crate::gamestate::GameState;
 use crate::map::World;
 use std::collections::HashMap;
 use std::net::{SocketAddr, UdpSocket};
+use std::sync::mpsc;
+use std::time::{Duration, Instant};
 use rand::seq::SliceRandom;
 use rand::Rng;
 use crate::consts::{DEFAULT_RANDOM_MAP_PATH_DEVIATION_CHANCE, DEFAULT_RANDOM_MAP_HOLE_CHANCE};
+use crate::consts::{
+    AUDIO_MAX_RANGE, AUDIO_OCCLUSION_LOW_PASS_HZ, AUDIO_OCCLUSION_VOLUME_PER_WALL,
+    FIRST_BLOOD_BONUS, KILL_STREAK_STEP, KILL_STREAK_TITLES,
+};
+
+/// Last modification time of a file, None if it can't be read
+pub fn file_modified(path: &str) -> Option<std::time::SystemTime> {
+    std::fs::metadata(path)
+        .and_then(|meta| meta.modified())
+        .ok()
+}
+
+/// Points for a kill with --kill-bonuses: one, plus a point for every KILL_STREAK_STEP kills in a
+/// row, plus the first blood bonus
+pub fn kill_points(streak: usize, first_blood: bool) -> usize {
+    let mut points = 1 + streak / KILL_STREAK_STEP;
+    if first_blood {
+        points += FIRST_BLOOD_BONUS;
+    }
+    points
+}
+
+/// Banner title for a kill streak that just reached a named length
+pub fn streak_title(streak: usize) -> Option<&'static str> {
+    KILL_STREAK_TITLES
+        .iter()
+        .find(|(length, _)| *length == streak)
+        .map(|(_, title)| *title)
+}
+
+/// Checks whether a connected client already uses the username (case-insensitive)
+pub fn username_taken(clients: &HashMap<SocketAddr, (u64, String, Instant)>, username: &str) -> bool {
+    clients
+        .values()
+        .any(|(_, name, _)| name.to_lowercase() == username.to_lowercase())
+}
+
+/// Address of the connected client with exactly this username
+pub fn client_named(
+    clients: &HashMap<SocketAddr, (u64, String, Instant)>,
+    username: &str,
+) -> Option<SocketAddr> {
+    clients
+        .iter()
+        .find(|(_, (_, name, _))| name == username)
+        .map(|(addr, _)| *addr)
+}
+
+/// Appends the lowest free number to a taken username, e.g. "Blob" becomes "Blob(2)"
+pub fn suffixed_username(clients: &HashMap<SocketAddr, (u64, String, Instant)>, username: &str) -> String {
+    (2..)
+        .map(|n| format!("{username}({n})"))
+        .find(|name| !username_taken(clients, name))
+        .unwrap()
+}
+
+/// How a sound should be muffled on its way from the source to the listener
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SoundOcclusion {
+    pub volume: f32,
+    pub low_pass_hz: Option<u32>,
+}
+
+/// Tests line of sight from the listener to a sound source. Each wall in between lowers the
+/// volume and the low-pass cutoff. Returns None if the source is too far away to be heard.
+pub fn sound_occlusion(
+    world: &World,
+    listener: (f32, f32),
+    source: (f32, f32),
+) -> Option<SoundOcclusion> {
+    let dx = source.0 - listener.0;
+    let dy = source.1 - listener.1;
+    if dx * dx + dy * dy > AUDIO_MAX_RANGE * AUDIO_MAX_RANGE {
+        return None;
+    }
+
+    let walls = world.walls_between(listener, source);
+    if walls == 0 {
+        return Some(SoundOcclusion {
+            volume: 1.0,
+            low_pass_hz: None,
+        });
+    }
+
+    Some(SoundOcclusion {
+        volume: AUDIO_OCCLUSION_VOLUME_PER_WALL.powi(walls as i32),
+        low_pass_hz: Some(AUDIO_OCCLUSION_LOW_PASS_HZ >> (walls - 1).min(4)),
+    })
+}
 
 pub fn set_winner(
     game_state: &mut GameState,
     winner_name: String,
+    match_duration: Duration,
     socket: &UdpSocket,
     clients: &HashMap<SocketAddr, (u64, String, std::time::Instant)>,
+    bots: &[Bot],
 ) {
     game_state.winner = Some(winner_name.clone());
     broadcast_message(
@@ -24,9 +120,40 @@ pub fn set_winner(
     )
     .unwrap();
 
+    // Final stats for the post-match screen, bots included
+    let players = clients
+        .values()
+        .map(|(id, name, _)| (id, name))
+        .chain(bots.iter().map(|bot| (&bot.id, &bot.name)))
+        .filter_map(|(id, name)| {
+            game_state
+                .players
+                .get(&id.to_string())
+                .map(|player| PlayerStats {
+                    name: name.clone(),
+                    kills: player.kills,
+                    deaths: player.deaths,
+                })
+        })
+        .collect();
+    let stats = MatchStats {
+        winner: winner_name.clone(),
+        duration: match_duration,
+        players,
+    };
+    broadcast_message(ServerMessage::MatchEnd(stats), socket, Some(clients), None).unwrap();
+
     println!("Game over! Winner is {winner_name}");
 }
 
+/// The player with the most points and their points, None on a tie or with nobody playing
+pub fn sole_leader(leaderboard: &HashMap<String, Score>) -> Option<(String, usize)> {
+    let top = leaderboard.values().map(|score| score.kills).max()?;
+    let mut leaders = leaderboard.iter().filter(|(_, score)| score.kills == top);
+    let (name, _) = leaders.next()?;
+    leaders.next().is_none().then(|| (name.clone(), top))
+}
+
 /// Updates the leaderboard with a new score and broadcasts the update to all clients. Returns the new score.
 pub fn update_leaderboard(
     game_state: &mut GameState,
@@ -45,13 +172,12 @@ pub fn update_leaderboard(
         game_state
             .leaderboard
             .get(&shooter_name)
-            .copied()
-            .unwrap_or(0)
+            .map_or(0, |score| score.kills)
             + increment
     } else if reset_score_all {
         // Reset the score of all players
         game_state.leaderboard.iter_mut().for_each(|(_, score)| {
-            *score = 0;
+            *score = Score::default();
         });
         return 0;
     } else {
@@ -59,13 +185,14 @@ pub fn update_leaderboard(
         return game_state
             .leaderboard
             .get(&shooter_name)
-            .copied()
-            .unwrap_or(0);
+            .map_or(0, |score| score.kills);
     };
 
     game_state
         .leaderboard
-        .insert(shooter_name.clone(), new_score);
+        .entry(shooter_name.clone())
+        .or_default()
+        .kills = new_score;
 
     let leaderboard = game_state.leaderboard.clone();
 
@@ -87,15 +214,27 @@ pub fn broadcast_message(
     clients: Option<&HashMap<SocketAddr, (u64, String, std::time::Instant)>>,
     client: Option<SocketAddr>,
 ) -> std::io::Result<()> {
-    let encoded_message = bincode::serialize(&message).unwrap();
+    // critical messages are numbered and kept until the recipients acknowledge them
+    let encoded_message = if reliable::is_critical(&message) {
+        let recipients: Vec<SocketAddr> = match (clients, client) {
+            (Some(clients), None) => clients.keys().copied().collect(),
+            (None, Some(client)) => vec![client],
+            _ => Vec::new(),
+        };
+        OUTBOX.with_borrow_mut(|outbox| outbox.wrap(message, &recipients).to_vec())
+    } else {
+        bincode::serialize(&message).unwrap()
+    };
     match (clients, client) {
         (Some(clients), None) => {
             for client_addr in clients.keys() {
                 socket.send_to(&encoded_message, client_addr)?;
             }
+            PACKETS_SENT.set(PACKETS_SENT.get() + clients.len() as u64);
         }
         (None, Some(client)) => {
             socket.send_to(&encoded_message, client)?;
+            PACKETS_SENT.set(PACKETS_SENT.get() + 1);
         }
         _ => {
             return Err(std::io::Error::new(
@@ -107,6 +246,45 @@ pub fn broadcast_message(
     Ok(())
 }
 
+/// Reads commands typed into the server's terminal on a background thread. The main thread picks
+/// them up from the receiver and passes them on to every arena.
+pub fn spawn_console() -> mpsc::Receiver<String> {
+    let (sender, receiver) = mpsc::channel();
+    std::thread::spawn(move || {
+        for line in std::io::stdin().lines() {
+            let Ok(line) = line else {
+                break;
+            };
+            let command = line.trim().to_string();
+            if !command.is_empty() && sender.send(command).is_err() {
+                break;
+            }
+        }
+    });
+    receiver
+}
+
+/// Markers for World::to_ascii, the players' letters in id order along with a legend
+pub fn player_markers<'a>(
+    game_state: &GameState,
+    names: impl IntoIterator<Item = (u64, &'a str)>,
+) -> (Vec<(char, f32, f32)>, String) {
+    let mut names: Vec<_> = names.into_iter().collect();
+    names.sort_by_key(|(id, _)| *id);
+    let mut markers = Vec::new();
+    let mut legend = String::new();
+    for ((id, name), letter) in names.into_iter().zip('A'..='Z') {
+        if let Some(player) = game_state.players.get(&id.to_string()) {
+            markers.push((letter, player.x, player.y));
+            legend.push_str(&format!(
+                "{} = {} ({:.2}, {:.2})\n",
+                letter, name, player.x, player.y
+            ));
+        }
+    }
+    (markers, legend)
+}
+
 /// Returns true if all adjacent tiles are walls, also checks corners if include_corners is true
 pub fn check_adjacent_tiles(world: &World, tile: (usize, usize), ignore_tile: (usize, usize), include_corners: bool) -> bool {
     for dx in -1..=1 {
@@ -184,3 +362,27 @@ pub fn carve_path(world: &mut World, tile: (usize, usize), include_corners: bool
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn kill_points_grow_with_the_streak() {
+        assert_eq!(kill_points(1, false), 1);
+        assert_eq!(kill_points(KILL_STREAK_STEP - 1, false), 1);
+        assert_eq!(kill_points(KILL_STREAK_STEP, false), 2);
+        assert_eq!(kill_points(KILL_STREAK_STEP * 2, false), 3);
+        assert_eq!(kill_points(1, true), 1 + FIRST_BLOOD_BONUS);
+    }
+
+    #[test]
+    fn streak_titles_only_at_named_lengths() {
+        for (length, title) in KILL_STREAK_TITLES {
+            assert_eq!(streak_title(length), Some(title));
+        }
+        assert_eq!(streak_title(0), None);
+        assert_eq!(streak_title(1), None);
+        assert_eq!(streak_title(4), None);
+    }
+}
@@ -0,0 +1,117 @@
+use std::collections::{HashMap, HashSet};
+use std::time::Instant;
+
+use crate::VoteKickStatus;
+use crate::consts::{VOTE_KICK_COOLDOWN, VOTE_KICK_DURATION, VOTE_KICK_MIN_VOTES};
+
+struct Vote {
+    target: String,
+    voters: HashSet<String>,
+    started: Instant,
+}
+
+/// The running vote to kick a player, one at a time, and when each target may be voted on again
+#[derive(Default)]
+pub struct VoteKicks {
+    active: Option<Vote>,
+    cooldowns: HashMap<String, Instant>,
+}
+
+/// Votes needed to kick someone: a majority of everyone else, and never a single player alone
+pub fn required_votes(players: usize) -> usize {
+    (players.saturating_sub(1) / 2 + 1).max(VOTE_KICK_MIN_VOTES)
+}
+
+impl VoteKicks {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Starts a vote against the target or adds to the running one. Returns the tally, passed
+    /// once enough players agree, or the reason the vote isn't counted.
+    pub fn cast(
+        &mut self,
+        voter: &str,
+        target: &str,
+        player_names: &[&str],
+    ) -> Result<VoteKickStatus, String> {
+        if voter == target {
+            return Err("You can't vote to kick yourself".to_string());
+        }
+        if !player_names.contains(&target) {
+            return Err(format!("No player named {}", target));
+        }
+
+        match &mut self.active {
+            Some(vote) if vote.target != target => {
+                return Err(format!("A vote to kick {} is already running", vote.target));
+            }
+            Some(vote) => {
+                vote.voters.insert(voter.to_string());
+            }
+            None => {
+                if let Some(until) = self.cooldowns.get(target)
+                    && *until > Instant::now()
+                {
+                    return Err(format!(
+                        "{} can be voted on again in {} s",
+                        target,
+                        (*until - Instant::now()).as_secs() + 1
+                    ));
+                }
+                self.active = Some(Vote {
+                    target: target.to_string(),
+                    voters: HashSet::from([voter.to_string()]),
+                    started: Instant::now(),
+                });
+            }
+        }
+
+        let status = self.status(player_names.len()).expect("a vote is running");
+        if status.passed == Some(true) {
+            self.active = None;
+        }
+        Ok(status)
+    }
+
+    /// Tally of the running vote. passed is Some(true) once it has enough votes.
+    fn status(&self, players: usize) -> Option<VoteKickStatus> {
+        let vote = self.active.as_ref()?;
+        let votes = vote.voters.len();
+        let required = required_votes(players);
+        Some(VoteKickStatus {
+            target: vote.target.clone(),
+            votes,
+            required,
+            remaining: VOTE_KICK_DURATION.saturating_sub(vote.started.elapsed()),
+            passed: (votes >= required).then_some(true),
+        })
+    }
+
+    /// Ends a vote that ran out of time, returning its final tally. The target then can't be voted
+    /// on again for VOTE_KICK_COOLDOWN.
+    pub fn expire(&mut self, players: usize) -> Option<VoteKickStatus> {
+        if self
+            .active
+            .as_ref()
+            .is_none_or(|vote| vote.started.elapsed() < VOTE_KICK_DURATION)
+        {
+            return None;
+        }
+        let mut status = self.status(players)?;
+        status.passed = Some(false);
+        let vote = self.active.take()?;
+        self.cooldowns
+            .insert(vote.target, Instant::now() + VOTE_KICK_COOLDOWN);
+        Some(status)
+    }
+
+    /// Drops a player's part in the running vote when they leave. A leaving target ends the vote.
+    pub fn forget(&mut self, name: &str) {
+        if self.active.as_ref().is_some_and(|vote| vote.target == name) {
+            self.active = None;
+        } else if let Some(vote) = &mut self.active {
+            vote.voters.remove(name);
+        }
+    }
+}